@@ -0,0 +1,90 @@
+use std::{fmt, path::PathBuf, str::FromStr};
+
+use crate::{
+    ingredient_store::IngredientStore,
+    recipie_store::RecipieStore,
+    sqlite_store::{SqliteIngredientStore, SqliteRecipieStore},
+    store::{Store, StoreError, StoreLoadError},
+};
+
+/// What the stores are persisted in, picked at startup
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Backend {
+    #[default]
+    Json,
+    Sqlite,
+}
+
+impl fmt::Display for Backend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Backend::Json => write!(f, "json"),
+            Backend::Sqlite => write!(f, "sqlite"),
+        }
+    }
+}
+
+impl FromStr for Backend {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "json" => Ok(Backend::Json),
+            "sqlite" => Ok(Backend::Sqlite),
+            _ => Err(format!("Unknown storage backend '{}'", s)),
+        }
+    }
+}
+
+impl Backend {
+    pub fn load_recipies(self, config_dir: PathBuf) -> Result<RecipieStore, StoreLoadError> {
+        match self {
+            Backend::Json => RecipieStore::from_config(config_dir),
+            Backend::Sqlite => SqliteRecipieStore::from_config(config_dir).map(|s| s.into_inner()),
+        }
+    }
+
+    pub fn load_ingredients(self, config_dir: PathBuf) -> Result<IngredientStore, StoreLoadError> {
+        match self {
+            Backend::Json => IngredientStore::from_config(config_dir),
+            Backend::Sqlite => {
+                SqliteIngredientStore::from_config(config_dir).map(|s| s.into_inner())
+            }
+        }
+    }
+
+    /// Just to look at, so it works while someone else has the stores open
+    pub fn load_recipies_read_only(
+        self,
+        config_dir: PathBuf,
+    ) -> Result<RecipieStore, StoreLoadError> {
+        match self {
+            Backend::Json => RecipieStore::from_config_read_only(config_dir),
+            Backend::Sqlite => {
+                SqliteRecipieStore::from_config_read_only(config_dir).map(|s| s.into_inner())
+            }
+        }
+    }
+
+    pub fn load_ingredients_read_only(
+        self,
+        config_dir: PathBuf,
+    ) -> Result<IngredientStore, StoreLoadError> {
+        match self {
+            Backend::Json => IngredientStore::from_config_read_only(config_dir),
+            Backend::Sqlite => {
+                SqliteIngredientStore::from_config_read_only(config_dir).map(|s| s.into_inner())
+            }
+        }
+    }
+
+    /// Either way the store knows which file it came from, and refuses to save over
+    /// someone else's changes
+    pub fn save_recipies(self, store: &mut RecipieStore) -> Result<(), StoreError> {
+        store.save()
+    }
+
+    pub fn save_ingredients(self, store: &mut IngredientStore) -> Result<(), StoreError> {
+        store.save()
+    }
+}
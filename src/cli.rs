@@ -0,0 +1,735 @@
+use std::{
+    fmt,
+    io::{Read, Write},
+    path::{Path, PathBuf},
+};
+
+use serde::Serialize;
+use strum::IntoEnumIterator;
+use uuid::Uuid;
+
+use crate::{
+    backend::Backend,
+    ingredient::{Ingredient, IngredientTag, Quality},
+    ingredient_store::IngredientStore,
+    inventory_csv::{self, CsvError, Table},
+    json_ld::{self, JsonLdError},
+    markdown,
+    menu::{self, MenuServer},
+    profile::{DEFAULT_PROFILE, profile_dir},
+    recipie::{Component, Recipie},
+    recipie_store::RecipieStore,
+    server::{Api, DEFAULT_ADDRESS, Server, WithId, with_ids},
+    store::{Store, StoreError, StoreLoadError},
+};
+
+pub const USAGE: &str = "\
+Usage: drink_solver [--backend json|sqlite] [--data-dir DIR] [COMMAND]
+
+Without a command, opens the app. Commands:
+  ingredients list [--in-stock]
+  ingredients add NAME [--quality Q] [--tag TAG]... [--stock N] [--liquor]
+  ingredients stock NAME N|+N|-N
+  ingredients export [FILE]   as CSV, to stdout unless given a file
+  ingredients import FILE [--dry-run]
+                              add and update ingredients from a CSV, matching them by name
+  recipes list
+  recipes show NAME
+  recipes add FILE            a recipie as JSON, - reads it from stdin
+  recipes import FILE [--markdown]
+                              schema.org Recipe JSON-LD or a Markdown note (.md),
+                              - reads it from stdin
+  recipes export NAME [FILE] [--markdown]
+                              as schema.org Recipe JSON-LD or a Markdown note (.md),
+                              to stdout unless given a file
+  recipes sync DIR            two-way sync with a folder of Markdown notes
+  can-make                    recipies everything's in stock for
+  missing RECIPE              what's out of stock for a recipie
+  serve [--listen ADDR]       serve the stores as a JSON API, on 127.0.0.1:8080 unless told
+  menu [--listen ADDR]        serve what we can make to guests, on 0.0.0.0:8081 unless told
+
+Every command takes --profile NAME, and --json for machine readable output.
+NAME can be an id as well.";
+
+/// Why a command failed
+#[derive(Debug)]
+pub enum CliError {
+    /// The command line didn't make sense
+    Usage(String),
+    NotFound(String),
+    Invalid(String),
+    Load(StoreLoadError),
+    Store(StoreError),
+    Io(std::io::Error),
+    Csv(CsvError),
+    JsonLd(JsonLdError),
+}
+
+impl From<StoreLoadError> for CliError {
+    fn from(err: StoreLoadError) -> Self {
+        CliError::Load(err)
+    }
+}
+
+impl From<StoreError> for CliError {
+    fn from(err: StoreError) -> Self {
+        CliError::Store(err)
+    }
+}
+
+impl From<std::io::Error> for CliError {
+    fn from(err: std::io::Error) -> Self {
+        CliError::Io(err)
+    }
+}
+
+impl From<CsvError> for CliError {
+    fn from(err: CsvError) -> Self {
+        CliError::Csv(err)
+    }
+}
+
+impl From<JsonLdError> for CliError {
+    fn from(err: JsonLdError) -> Self {
+        CliError::JsonLd(err)
+    }
+}
+
+impl From<serde_json::Error> for CliError {
+    fn from(err: serde_json::Error) -> Self {
+        CliError::Io(err.into())
+    }
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CliError::Usage(message) => write!(f, "{}\n\n{}", message, USAGE),
+            CliError::NotFound(message) | CliError::Invalid(message) => write!(f, "{}", message),
+            CliError::Load(err) => err.fmt(f),
+            CliError::Store(err) => err.fmt(f),
+            CliError::Io(err) => err.fmt(f),
+            CliError::Csv(err) => err.fmt(f),
+            CliError::JsonLd(err) => err.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for CliError {}
+
+/// The stores a command works on
+struct Bar {
+    backend: Backend,
+    dir: PathBuf,
+    json: bool,
+    /// Recipies are imported and exported as Markdown rather than JSON-LD
+    markdown: bool,
+}
+
+/// Runs the command in `args`, e.g. `["ingredients", "list", "--json"]`, against the stores
+/// in `data_dir`. Read-only commands don't need the stores to themselves, so they work
+/// while the app has them open.
+pub fn run(
+    backend: Backend,
+    data_dir: &Path,
+    args: &[String],
+    out: &mut impl Write,
+) -> Result<(), CliError> {
+    let mut args = args.to_vec();
+    let json = take_switch(&mut args, "--json");
+    let markdown = take_switch(&mut args, "--markdown");
+    let dry_run = take_switch(&mut args, "--dry-run");
+    let profile = take_value(&mut args, "--profile")?;
+    let bar = Bar {
+        backend,
+        dir: profile_dir(data_dir, profile.as_deref().unwrap_or(DEFAULT_PROFILE)),
+        json,
+        markdown,
+    };
+    let args: Vec<&str> = args.iter().map(|a| a.as_str()).collect();
+    // Better to refuse than to quietly make changes someone meant to try out first
+    if dry_run && !matches!(args.as_slice(), ["ingredients", "import", _]) {
+        return Err(CliError::Usage(
+            "--dry-run only works with ingredients import".to_string(),
+        ));
+    }
+    match args.as_slice() {
+        ["help"] | ["--help"] | ["-h"] => Ok(writeln!(out, "{}", USAGE)?),
+        ["ingredients", "list", rest @ ..] => bar.list_ingredients(rest, out),
+        ["ingredients", "add", name, rest @ ..] => bar.add_ingredient(name, rest, out),
+        ["ingredients", "stock", name, amount] => bar.set_stock(name, amount, out),
+        ["ingredients", "export"] => bar.export_ingredients(None, out),
+        ["ingredients", "export", file] => bar.export_ingredients(Some(file), out),
+        ["ingredients", "import", file] => bar.import_ingredients(file, dry_run, out),
+        ["recipes", "list"] => bar.list_recipies(out),
+        ["recipes", "show", name] => bar.show_recipie(name, out),
+        ["recipes", "add", file] => bar.add_recipie(file, out),
+        ["recipes", "import", file] => bar.import_recipies(file, out),
+        ["recipes", "export", name] => bar.export_recipie(name, None, out),
+        ["recipes", "export", name, file] => bar.export_recipie(name, Some(file), out),
+        ["recipes", "sync", dir] => bar.sync_recipies(dir, out),
+        ["can-make"] => bar.can_make(out),
+        ["missing", name] => bar.missing(name, out),
+        ["serve"] => bar.serve(DEFAULT_ADDRESS, out),
+        ["serve", "--listen", address] => bar.serve(address, out),
+        ["menu"] => bar.serve_menu(menu::DEFAULT_ADDRESS, out),
+        ["menu", "--listen", address] => bar.serve_menu(address, out),
+        _ => Err(CliError::Usage(format!(
+            "Unknown command '{}'",
+            args.join(" ")
+        ))),
+    }
+}
+
+impl Bar {
+    fn ingredients(&self) -> Result<IngredientStore, CliError> {
+        Ok(self.backend.load_ingredients_read_only(self.dir.clone())?)
+    }
+
+    fn recipies(&self) -> Result<RecipieStore, CliError> {
+        Ok(self.backend.load_recipies_read_only(self.dir.clone())?)
+    }
+
+    fn list_ingredients(&self, flags: &[&str], out: &mut impl Write) -> Result<(), CliError> {
+        let in_stock = match flags {
+            [] => false,
+            ["--in-stock"] => true,
+            _ => {
+                return Err(CliError::Usage(format!(
+                    "Unknown flags {}",
+                    flags.join(" ")
+                )));
+            }
+        };
+        let mut entries: Vec<(Uuid, Ingredient)> = self
+            .ingredients()?
+            .get_entries_with_ids()
+            .into_iter()
+            .filter(|(_, i)| !in_stock || i.stock > 0)
+            .collect();
+        entries.sort_by_key(|(_, i)| i.name.to_lowercase());
+        if self.json {
+            return print_json(&with_ids(&entries), out);
+        }
+        let width = entries.iter().map(|(_, i)| i.name.len()).max().unwrap_or(0);
+        for (_, ingredient) in &entries {
+            writeln!(out, "{}", ingredient_line(ingredient, width))?;
+        }
+        Ok(())
+    }
+
+    fn add_ingredient(
+        &self,
+        name: &str,
+        flags: &[&str],
+        out: &mut impl Write,
+    ) -> Result<(), CliError> {
+        let mut ingredient = Ingredient {
+            name: name.to_string(),
+            ..Default::default()
+        };
+        let mut flags = flags.iter();
+        while let Some(flag) = flags.next() {
+            let mut value = || {
+                flags
+                    .next()
+                    .ok_or_else(|| CliError::Usage(format!("{} needs a value", flag)))
+            };
+            match *flag {
+                "--quality" => ingredient.quality = parse_quality(value()?)?,
+                "--tag" => ingredient.tags.push(IngredientTag {
+                    value: value()?.to_string(),
+                }),
+                "--stock" => ingredient.stock = parse_stock(value()?)?,
+                "--liquor" => ingredient.is_liquor = true,
+                _ => return Err(CliError::Usage(format!("Unknown flag {}", flag))),
+            }
+        }
+        let mut store = self.backend.load_ingredients(self.dir.clone())?;
+        if store
+            .get_entries()
+            .iter()
+            .any(|i| i.name.eq_ignore_ascii_case(name))
+        {
+            return Err(CliError::Invalid(format!(
+                "There's already an ingredient named {}",
+                name
+            )));
+        }
+        let id = store.register(ingredient.clone());
+        self.backend.save_ingredients(&mut store)?;
+        if self.json {
+            return print_json(
+                &WithId {
+                    id,
+                    entry: &ingredient,
+                },
+                out,
+            );
+        }
+        Ok(writeln!(out, "Added {} ({})", ingredient.name, id)?)
+    }
+
+    fn set_stock(&self, name: &str, amount: &str, out: &mut impl Write) -> Result<(), CliError> {
+        let mut store = self.backend.load_ingredients(self.dir.clone())?;
+        let (id, mut ingredient) = find(
+            store.get_entries_with_ids(),
+            name,
+            |i| &i.name,
+            "ingredient",
+        )?;
+        ingredient.stock = match amount.strip_prefix('+') {
+            Some(more) => ingredient.stock.saturating_add(parse_stock(more)?),
+            None => match amount.strip_prefix('-') {
+                Some(less) => ingredient.stock.saturating_sub(parse_stock(less)?),
+                None => parse_stock(amount)?,
+            },
+        };
+        store.insert(id, ingredient.clone());
+        self.backend.save_ingredients(&mut store)?;
+        if self.json {
+            return print_json(
+                &WithId {
+                    id,
+                    entry: &ingredient,
+                },
+                out,
+            );
+        }
+        Ok(writeln!(
+            out,
+            "{}: {} in stock",
+            ingredient.name, ingredient.stock
+        )?)
+    }
+
+    fn export_ingredients(&self, file: Option<&str>, out: &mut impl Write) -> Result<(), CliError> {
+        let store = self.ingredients()?;
+        match file {
+            Some(path) => {
+                inventory_csv::export(&store, std::fs::File::create(path)?)?;
+                writeln!(
+                    out,
+                    "Exported {} ingredients to {}",
+                    store.get_entries().len(),
+                    path
+                )?;
+            }
+            None => inventory_csv::export(&store, out)?,
+        }
+        Ok(())
+    }
+
+    // Columns are guessed from the headers; the app lets them be mapped by hand
+    fn import_ingredients(
+        &self,
+        file: &str,
+        dry_run: bool,
+        out: &mut impl Write,
+    ) -> Result<(), CliError> {
+        let table = Table::read(std::fs::File::open(file)?)?;
+        let mut store = self.backend.load_ingredients(self.dir.clone())?;
+        let plan = inventory_csv::plan(&table, &table.guess_columns(), &store)?;
+        writeln!(out, "{}", plan.report())?;
+        if dry_run {
+            return Ok(writeln!(out, "Dry run, nothing was changed")?);
+        }
+        let changed = plan.apply(&mut store);
+        if changed > 0 {
+            self.backend.save_ingredients(&mut store)?;
+        }
+        Ok(writeln!(out, "Imported {} ingredients", changed)?)
+    }
+
+    fn list_recipies(&self, out: &mut impl Write) -> Result<(), CliError> {
+        let entries = sorted_recipies(&self.recipies()?);
+        self.print_recipie_list(&entries, out)
+    }
+
+    fn show_recipie(&self, name: &str, out: &mut impl Write) -> Result<(), CliError> {
+        let entries = self.recipies()?.get_entries_with_ids();
+        let (id, recipie) = find(entries, name, |r| &r.name, "recipie")?;
+        if self.json {
+            return print_json(
+                &WithId {
+                    id,
+                    entry: &recipie,
+                },
+                out,
+            );
+        }
+        writeln!(out, "{}", recipie.name)?;
+        if !recipie.short_description.is_empty() {
+            writeln!(out, "{}", recipie.short_description)?;
+        }
+        if !recipie.description.is_empty() {
+            writeln!(out, "\n{}", recipie.description)?;
+        }
+        writeln!(out)?;
+        for component in &recipie.components {
+            writeln!(out, "  {}", component_line(component))?;
+        }
+        if !recipie.instructions.is_empty() {
+            writeln!(out)?;
+        }
+        for (step, instruction) in recipie.instructions.iter().enumerate() {
+            writeln!(out, "{}. {}", step + 1, instruction)?;
+        }
+        if !recipie.notes.is_empty() {
+            writeln!(out, "\nNotes: {}", recipie.notes)?;
+        }
+        Ok(())
+    }
+
+    fn add_recipie(&self, file: &str, out: &mut impl Write) -> Result<(), CliError> {
+        let text = read_input(file)?;
+        let recipie: Recipie = serde_json::from_str(&text)
+            .map_err(|err| CliError::Invalid(format!("That isn't a recipie: {}", err)))?;
+        let mut store = self.backend.load_recipies(self.dir.clone())?;
+        let id = store.register(recipie.clone());
+        self.backend.save_recipies(&mut store)?;
+        if self.json {
+            return print_json(
+                &WithId {
+                    id,
+                    entry: &recipie,
+                },
+                out,
+            );
+        }
+        Ok(writeln!(out, "Added {} ({})", recipie.name, id)?)
+    }
+
+    fn import_recipies(&self, file: &str, out: &mut impl Write) -> Result<(), CliError> {
+        let text = read_input(file)?;
+        let mut store = self.backend.load_recipies(self.dir.clone())?;
+        let added: Vec<(Uuid, Recipie)> = if self.is_markdown(file) {
+            // Notes exported from here keep their id, so importing one again updates it
+            let (id, recipie) = markdown::from_markdown(&text);
+            let id = match id {
+                Some(id) => {
+                    store.insert(id, recipie.clone());
+                    id
+                }
+                None => store.register(recipie.clone()),
+            };
+            vec![(id, recipie)]
+        } else {
+            json_ld::read(text.as_bytes())?
+                .into_iter()
+                .map(|recipie| (store.register(recipie.clone()), recipie))
+                .collect()
+        };
+        self.backend.save_recipies(&mut store)?;
+        if self.json {
+            return print_json(&with_ids(&added), out);
+        }
+        for (id, recipie) in &added {
+            writeln!(out, "Added {} ({})", recipie.name, id)?;
+        }
+        Ok(())
+    }
+
+    fn export_recipie(
+        &self,
+        name: &str,
+        file: Option<&str>,
+        out: &mut impl Write,
+    ) -> Result<(), CliError> {
+        let entries = self.recipies()?.get_entries_with_ids();
+        let (id, recipie) = find(entries, name, |r| &r.name, "recipie")?;
+        let markdown = self.is_markdown(file.unwrap_or_default());
+        match file {
+            Some(path) if markdown => {
+                std::fs::write(path, markdown::to_markdown(id, &recipie))?;
+                writeln!(out, "Exported {} to {}", recipie.name, path)?;
+            }
+            Some(path) => {
+                json_ld::write(&recipie, std::fs::File::create(path)?)?;
+                writeln!(out, "Exported {} to {}", recipie.name, path)?;
+            }
+            None if markdown => write!(out, "{}", markdown::to_markdown(id, &recipie))?,
+            None => {
+                json_ld::write(&recipie, &mut *out)?;
+                writeln!(out)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn sync_recipies(&self, dir: &str, out: &mut impl Write) -> Result<(), CliError> {
+        let mut store = self.backend.load_recipies(self.dir.clone())?;
+        let report = markdown::sync(&mut store, Path::new(dir))?;
+        if report.changed_store() {
+            self.backend.save_recipies(&mut store)?;
+        }
+        Ok(writeln!(out, "{}", report.summary())?)
+    }
+
+    fn is_markdown(&self, file: &str) -> bool {
+        self.markdown || file.ends_with(".md")
+    }
+
+    fn can_make(&self, out: &mut impl Write) -> Result<(), CliError> {
+        let ingredients = self.ingredients()?;
+        let entries: Vec<(Uuid, Recipie)> = sorted_recipies(&self.recipies()?)
+            .into_iter()
+            .filter(|(_, r)| r.can_make(&ingredients))
+            .collect();
+        self.print_recipie_list(&entries, out)
+    }
+
+    fn missing(&self, name: &str, out: &mut impl Write) -> Result<(), CliError> {
+        let ingredients = self.ingredients()?;
+        let entries = self.recipies()?.get_entries_with_ids();
+        let (_, recipie) = find(entries, name, |r| &r.name, "recipie")?;
+        let missing: Vec<&Component> = recipie
+            .components
+            .iter()
+            .filter(|c| {
+                let mut selector = c.ingredient.clone();
+                selector.in_stock = Some(true);
+                ingredients.select(&selector).is_empty()
+            })
+            .collect();
+        if self.json {
+            return print_json(&missing, out);
+        }
+        if missing.is_empty() {
+            return Ok(writeln!(
+                out,
+                "Everything for {} is in stock",
+                recipie.name
+            )?);
+        }
+        for component in missing {
+            writeln!(out, "{}", component_line(component))?;
+        }
+        Ok(())
+    }
+
+    fn serve(&self, address: &str, out: &mut impl Write) -> Result<(), CliError> {
+        let api = Api::open(self.backend, self.dir.clone())?;
+        let server = Server::bind(api, address)?;
+        if let Some(address) = server.address() {
+            writeln!(out, "Serving on http://{}", address)?;
+            out.flush()?;
+        }
+        // Errors go to stderr, so redirecting the output keeps them apart
+        server.run(|err| eprintln!("{}", err));
+        Ok(())
+    }
+
+    fn serve_menu(&self, address: &str, out: &mut impl Write) -> Result<(), CliError> {
+        let mut server = MenuServer::bind(self.backend, self.dir.clone(), address)?;
+        if let Some(url) = server.lan_url() {
+            writeln!(out, "Guests can open {}", url)?;
+            out.flush()?;
+        }
+        server.run(|err| eprintln!("{}", err));
+        Ok(())
+    }
+
+    fn print_recipie_list(
+        &self,
+        entries: &[(Uuid, Recipie)],
+        out: &mut impl Write,
+    ) -> Result<(), CliError> {
+        if self.json {
+            return print_json(&with_ids(entries), out);
+        }
+        for (_, recipie) in entries {
+            match recipie.short_description.as_str() {
+                "" => writeln!(out, "{}", recipie.name)?,
+                short => writeln!(out, "{} - {}", recipie.name, short)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+fn sorted_recipies(store: &RecipieStore) -> Vec<(Uuid, Recipie)> {
+    let mut entries = store.get_entries_with_ids();
+    entries.sort_by_key(|(_, r)| r.name.to_lowercase());
+    entries
+}
+
+/// Looks an entry up by id, or failing that by name, ignoring case
+fn find<T>(
+    entries: Vec<(Uuid, T)>,
+    name: &str,
+    name_of: fn(&T) -> &String,
+    kind: &str,
+) -> Result<(Uuid, T), CliError> {
+    let id = Uuid::parse_str(name).ok();
+    let mut found: Vec<(Uuid, T)> = entries
+        .into_iter()
+        .filter(|(i, e)| Some(*i) == id || name_of(e).eq_ignore_ascii_case(name))
+        .collect();
+    match found.len() {
+        0 => Err(CliError::NotFound(format!("No {} named {}", kind, name))),
+        1 => Ok(found.remove(0)),
+        _ => Err(CliError::Invalid(format!(
+            "More than one {} is named {}, use its id instead",
+            kind, name
+        ))),
+    }
+}
+
+fn print_json<T: Serialize + ?Sized>(value: &T, out: &mut impl Write) -> Result<(), CliError> {
+    serde_json::to_writer_pretty(&mut *out, value)?;
+    Ok(writeln!(out)?)
+}
+
+fn ingredient_line(ingredient: &Ingredient, width: usize) -> String {
+    let mut details = vec![ingredient.quality.to_string()];
+    if ingredient.is_liquor {
+        details.push("liquor".to_string());
+    }
+    details.extend(ingredient.tags.iter().map(|t| t.value.clone()));
+    format!(
+        "{:<width$}  {:>4}  {}",
+        ingredient.name,
+        ingredient.stock,
+        details.join(", "),
+        width = width
+    )
+}
+
+fn component_line(component: &Component) -> String {
+    format!("{}: {}", component.ingredient, component.amount)
+}
+
+/// A file's contents, or stdin's for `-`
+fn read_input(file: &str) -> Result<String, CliError> {
+    match file {
+        "-" => {
+            let mut text = String::new();
+            std::io::stdin().read_to_string(&mut text)?;
+            Ok(text)
+        }
+        path => Ok(std::fs::read_to_string(path)?),
+    }
+}
+
+fn parse_quality(value: &str) -> Result<Quality, CliError> {
+    Quality::iter()
+        .find(|q| q.to_string().eq_ignore_ascii_case(value))
+        .ok_or_else(|| CliError::Usage(format!("Unknown quality '{}'", value)))
+}
+
+fn parse_stock(value: &str) -> Result<u16, CliError> {
+    value
+        .parse()
+        .map_err(|_| CliError::Usage(format!("'{}' isn't a stock count", value)))
+}
+
+fn take_switch(args: &mut Vec<String>, flag: &str) -> bool {
+    let before = args.len();
+    args.retain(|a| a != flag);
+    args.len() != before
+}
+
+fn take_value(args: &mut Vec<String>, flag: &str) -> Result<Option<String>, CliError> {
+    let Some(i) = args.iter().position(|a| a == flag) else {
+        return Ok(None);
+    };
+    if i + 1 >= args.len() {
+        return Err(CliError::Usage(format!("{} needs a value", flag)));
+    }
+    let value = args.remove(i + 1);
+    args.remove(i);
+    Ok(Some(value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_dir::TestDir;
+
+    fn run_ok(data_dir: &Path, args: &str) -> String {
+        let args: Vec<String> = args.split_whitespace().map(String::from).collect();
+        let mut out = vec![];
+        run(Backend::Json, data_dir, &args, &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_stock_and_can_make() {
+        let data_dir = TestDir::new();
+        run_ok(
+            &data_dir,
+            "ingredients add Gin --liquor --quality high --stock 1",
+        );
+        run_ok(&data_dir, "ingredients add Vermouth --tag fortified");
+        let recipie = serde_json::json!({
+            "name": "Martini",
+            "description": "",
+            "short_description": "Stirred, up",
+            "notes": "",
+            "components": [
+                { "ingredient": { "name": "Gin" }, "amount": { "Oz": 2.0 } },
+                { "ingredient": { "name": "Vermouth" }, "amount": { "Oz": 1.0 } }
+            ],
+            "instructions": ["Stir with ice", "Strain"]
+        });
+        let file = data_dir.join("martini.json");
+        std::fs::write(&file, recipie.to_string()).unwrap();
+        run_ok(&data_dir, &format!("recipes add {}", file.display()));
+
+        assert_eq!(run_ok(&data_dir, "can-make"), "");
+        assert_eq!(run_ok(&data_dir, "missing martini"), "Vermouth: 1 oz\n");
+        assert_eq!(
+            run_ok(&data_dir, "ingredients stock vermouth +2"),
+            "Vermouth: 2 in stock\n"
+        );
+        assert_eq!(run_ok(&data_dir, "can-make"), "Martini - Stirred, up\n");
+        assert!(run_ok(&data_dir, "recipes show Martini").contains("2. Strain"));
+
+        let listed: serde_json::Value =
+            serde_json::from_str(&run_ok(&data_dir, "ingredients list --json")).unwrap();
+        assert_eq!(listed[0]["name"], "Gin");
+        assert_eq!(listed[0]["quality"], "High");
+        assert_eq!(listed[1]["stock"], 2);
+        assert!(listed[1]["id"].is_string());
+
+        // --dry-run can come before the file too
+        let csv = data_dir.join("inventory.csv");
+        std::fs::write(&csv, "name,stock\nGin,5\n").unwrap();
+        run_ok(
+            &data_dir,
+            &format!("ingredients import --dry-run {}", csv.display()),
+        );
+        assert_eq!(
+            run_ok(&data_dir, "ingredients stock gin +0"),
+            "Gin: 1 in stock\n"
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        let data_dir = TestDir::new();
+        let run_err = |args: &str| {
+            let args: Vec<String> = args.split_whitespace().map(String::from).collect();
+            run(Backend::Json, &data_dir, &args, &mut vec![]).unwrap_err()
+        };
+        assert!(matches!(
+            run_err("ingredients frobnicate"),
+            CliError::Usage(_)
+        ));
+        assert!(matches!(run_err("missing Martini"), CliError::NotFound(_)));
+        run_ok(&data_dir, "ingredients add Gin");
+        assert!(matches!(
+            run_err("ingredients add gin"),
+            CliError::Invalid(_)
+        ));
+        assert!(matches!(
+            run_err("ingredients stock Gin lots"),
+            CliError::Usage(_)
+        ));
+        assert!(matches!(
+            run_err("ingredients stock Gin 2 --dry-run"),
+            CliError::Usage(_)
+        ));
+    }
+}
@@ -0,0 +1,320 @@
+use std::time::{Duration, Instant};
+
+use uuid::Uuid;
+
+use crate::{
+    ingredient::Ingredient,
+    ingredient_store::IngredientStore,
+    recipie::Recipie,
+    recipie_store::RecipieStore,
+    store::{Store, StoreError},
+};
+
+/// How many steps back we remember
+const HISTORY_LIMIT: usize = 200;
+
+/// Edits to the same entry this close together undo as one step, so a name typed into a
+/// text box isn't undone a letter at a time
+const COALESCE_WINDOW: Duration = Duration::from_secs(1);
+
+/// One undoable change to a store entry. Registering, trashing and editing an entry all
+/// come down to swapping what's stored under its id.
+#[derive(Clone)]
+pub struct EntryChange<T> {
+    id: Uuid,
+    before: Option<T>,
+    after: Option<T>,
+    // The missing side lives in the trash bin rather than being gone altogether
+    via_trash: bool,
+}
+
+impl<T: Clone> EntryChange<T> {
+    fn verb(&self) -> &'static str {
+        match (&self.before, &self.after) {
+            (None, _) if self.via_trash => "restore",
+            (None, _) => "add",
+            (_, None) => "delete",
+            _ => "edit",
+        }
+    }
+
+    fn put<S: Store<T>>(&self, store: &mut S, entry: &Option<T>) -> Result<(), StoreError> {
+        match (entry, self.via_trash) {
+            (Some(_), true) => store.restore_from_trash(self.id),
+            (None, true) => store.move_to_trash(self.id),
+            (Some(entry), false) => {
+                store.insert(self.id, entry.clone());
+                Ok(())
+            }
+            (None, false) => store.deregister(self.id),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub enum Command {
+    Recipie(EntryChange<Recipie>),
+    Ingredient(EntryChange<Ingredient>),
+}
+
+impl Command {
+    fn undo(
+        &self,
+        recipies: &mut RecipieStore,
+        ingredients: &mut IngredientStore,
+    ) -> Result<(), StoreError> {
+        match self {
+            Command::Recipie(c) => c.put(recipies, &c.before),
+            Command::Ingredient(c) => c.put(ingredients, &c.before),
+        }
+    }
+
+    fn redo(
+        &self,
+        recipies: &mut RecipieStore,
+        ingredients: &mut IngredientStore,
+    ) -> Result<(), StoreError> {
+        match self {
+            Command::Recipie(c) => c.put(recipies, &c.after),
+            Command::Ingredient(c) => c.put(ingredients, &c.after),
+        }
+    }
+
+    /// e.g. "delete ingredient", for the Edit menu
+    pub fn describe(&self) -> String {
+        match self {
+            Command::Recipie(c) => format!("{} recipie", c.verb()),
+            Command::Ingredient(c) => format!("{} ingredient", c.verb()),
+        }
+    }
+}
+
+/// Entries whose changes can be recorded in the history
+pub trait Recorded: Clone + Sized {
+    fn command(change: EntryChange<Self>) -> Command;
+
+    fn change_mut(command: &mut Command) -> Option<&mut EntryChange<Self>>;
+}
+
+impl Recorded for Recipie {
+    fn command(change: EntryChange<Self>) -> Command {
+        Command::Recipie(change)
+    }
+
+    fn change_mut(command: &mut Command) -> Option<&mut EntryChange<Self>> {
+        match command {
+            Command::Recipie(change) => Some(change),
+            _ => None,
+        }
+    }
+}
+
+impl Recorded for Ingredient {
+    fn command(change: EntryChange<Self>) -> Command {
+        Command::Ingredient(change)
+    }
+
+    fn change_mut(command: &mut Command) -> Option<&mut EntryChange<Self>> {
+        match command {
+            Command::Ingredient(change) => Some(change),
+            _ => None,
+        }
+    }
+}
+
+struct Step {
+    command: Command,
+    // When it was last extended; None once it shouldn't be merged into any more
+    at: Option<Instant>,
+}
+
+/// Undo/redo stack shared by both stores. Changes made through it can be undone; changes
+/// made to a store directly can't.
+#[derive(Default)]
+pub struct History {
+    undo: Vec<Step>,
+    redo: Vec<Command>,
+}
+
+impl History {
+    pub fn register<T: Recorded, S: Store<T>>(&mut self, store: &mut S, entry: T) -> Uuid {
+        let id = store.register(entry.clone());
+        self.record(T::command(EntryChange {
+            id,
+            before: None,
+            after: Some(entry),
+            via_trash: false,
+        }));
+        id
+    }
+
+    pub fn move_to_trash<T: Recorded, S: Store<T>>(
+        &mut self,
+        store: &mut S,
+        id: Uuid,
+    ) -> Result<(), StoreError> {
+        let before = store.get_entry(id);
+        store.move_to_trash(id)?;
+        self.record(T::command(EntryChange {
+            id,
+            before,
+            after: None,
+            via_trash: true,
+        }));
+        Ok(())
+    }
+
+    pub fn restore_from_trash<T: Recorded, S: Store<T>>(
+        &mut self,
+        store: &mut S,
+        id: Uuid,
+    ) -> Result<(), StoreError> {
+        store.restore_from_trash(id)?;
+        self.record(T::command(EntryChange {
+            id,
+            before: None,
+            after: store.get_entry(id),
+            via_trash: true,
+        }));
+        Ok(())
+    }
+
+    /// Replaces an existing entry, covering field edits and stock changes alike
+    pub fn update<T: Recorded, S: Store<T>>(
+        &mut self,
+        store: &mut S,
+        id: Uuid,
+        entry: T,
+    ) -> Result<(), StoreError> {
+        let before = store.get_entry(id).ok_or(StoreError::UnknownId(id))?;
+        store.insert(id, entry.clone());
+        if let Some(last) = self.undo.last_mut()
+            && last.at.is_some_and(|at| at.elapsed() < COALESCE_WINDOW)
+            && let Some(change) = T::change_mut(&mut last.command)
+            && change.id == id
+            && change.before.is_some()
+            && change.after.is_some()
+        {
+            change.after = Some(entry);
+            last.at = Some(Instant::now());
+            self.redo.clear();
+            return Ok(());
+        }
+        self.record(T::command(EntryChange {
+            id,
+            before: Some(before),
+            after: Some(entry),
+            via_trash: false,
+        }));
+        Ok(())
+    }
+
+    fn record(&mut self, command: Command) {
+        self.undo.push(Step {
+            command,
+            at: Some(Instant::now()),
+        });
+        if self.undo.len() > HISTORY_LIMIT {
+            self.undo.remove(0);
+        }
+        self.redo.clear();
+    }
+
+    /// Ok(false) when there's nothing to undo. A step that fails to undo is dropped, since
+    /// the store no longer looks the way it expects.
+    pub fn undo(
+        &mut self,
+        recipies: &mut RecipieStore,
+        ingredients: &mut IngredientStore,
+    ) -> Result<bool, StoreError> {
+        let Some(step) = self.undo.pop() else {
+            return Ok(false);
+        };
+        step.command.undo(recipies, ingredients)?;
+        self.redo.push(step.command);
+        Ok(true)
+    }
+
+    pub fn redo(
+        &mut self,
+        recipies: &mut RecipieStore,
+        ingredients: &mut IngredientStore,
+    ) -> Result<bool, StoreError> {
+        let Some(command) = self.redo.pop() else {
+            return Ok(false);
+        };
+        command.redo(recipies, ingredients)?;
+        // Never merged with whatever gets edited next
+        self.undo.push(Step { command, at: None });
+        Ok(true)
+    }
+
+    pub fn next_undo(&self) -> Option<&Command> {
+        self.undo.last().map(|step| &step.command)
+    }
+
+    pub fn next_redo(&self) -> Option<&Command> {
+        self.redo.last()
+    }
+
+    /// For when the stores are swapped out from under the history, e.g. on reload
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn named(name: &str) -> Ingredient {
+        Ingredient {
+            name: name.into(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_undo_redo() {
+        let mut history = History::default();
+        let mut recipies = RecipieStore::default();
+        let mut ingredients = IngredientStore::default();
+        let id = history.register(&mut ingredients, named("Gin"));
+        assert!(history.move_to_trash(&mut ingredients, id).is_ok());
+        assert_eq!(history.next_undo().unwrap().describe(), "delete ingredient");
+        assert!(ingredients.get_entry(id).is_none());
+
+        assert!(history.undo(&mut recipies, &mut ingredients).unwrap());
+        assert_eq!(ingredients.get_entry(id).unwrap().name, "Gin");
+        assert!(ingredients.trash_bin().is_empty());
+        assert!(history.undo(&mut recipies, &mut ingredients).unwrap());
+        assert!(ingredients.get_entry(id).is_none());
+        assert!(!history.undo(&mut recipies, &mut ingredients).unwrap());
+
+        assert!(history.redo(&mut recipies, &mut ingredients).unwrap());
+        assert_eq!(ingredients.get_entry(id).unwrap().name, "Gin");
+        // A new change drops whatever was left to redo
+        history.register(&mut ingredients, named("Rum"));
+        assert!(history.next_redo().is_none());
+    }
+
+    #[test]
+    fn test_quick_edits_coalesce() {
+        let mut history = History::default();
+        let mut recipies = RecipieStore::default();
+        let mut ingredients = IngredientStore::default();
+        let id = history.register(&mut ingredients, named("G"));
+        history.update(&mut ingredients, id, named("Gi")).unwrap();
+        history.update(&mut ingredients, id, named("Gin")).unwrap();
+        assert_eq!(history.undo.len(), 2);
+
+        history.undo(&mut recipies, &mut ingredients).unwrap();
+        assert_eq!(ingredients.get_entry(id).unwrap().name, "G");
+        history.redo(&mut recipies, &mut ingredients).unwrap();
+        assert_eq!(ingredients.get_entry(id).unwrap().name, "Gin");
+        // Redone steps stand on their own
+        history.update(&mut ingredients, id, named("Gin!")).unwrap();
+        assert_eq!(history.undo.len(), 3);
+    }
+}
@@ -1,7 +1,8 @@
 use serde::{Deserialize, Serialize};
-use std::{fmt, hash::Hash};
+use std::{collections::BTreeMap, fmt, hash::Hash};
 use strum::{EnumIter, EnumString};
 
+/// How good a bottle is. Recipies asking for `Any` take whatever's around.
 #[derive(
     Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize, Hash, EnumString, EnumIter,
 )]
@@ -35,20 +36,60 @@ impl fmt::Display for Quality {
     }
 }
 
-// Tag for types of ingredients. e.g. "Gin" or "sweet"
+/// Tag for types of ingredients. e.g. "Gin" or "sweet"
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
 pub struct IngredientTag {
     pub value: String,
 }
 
+/// Something behind the bar, and how many of it we have
 #[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
 pub struct Ingredient {
     pub name: String,
     pub quality: Quality,
     pub tags: Vec<IngredientTag>,
+    /// Bottles, cans etc. on hand
     pub stock: u16,
     #[serde(default)]
     pub is_liquor: bool,
+    /// What a full bottle holds, in ml. Only ingredients with one have pours taken out of
+    /// their stock.
+    #[serde(default)]
+    pub bottle_ml: Option<u32>,
+    /// How much has gone out of the open bottle, in ml
+    #[serde(default)]
+    pub poured_ml: u32,
+    /// Anything else we keep about it, e.g. a supplier column from a spreadsheet
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub extra: BTreeMap<String, String>,
+}
+
+impl Ingredient {
+    /// Takes a pour out of the open bottle, opening the next one whenever it runs dry.
+    /// Without a bottle size there's no telling when that is, so stock is left alone and
+    /// this returns false.
+    pub fn pour(&mut self, ml: f32) -> bool {
+        let Some(bottle) = self.bottle_ml.filter(|b| *b > 0) else {
+            return false;
+        };
+        self.poured_ml += ml.max(0.0).round() as u32;
+        while self.poured_ml >= bottle && self.stock > 0 {
+            self.poured_ml -= bottle;
+            self.stock -= 1;
+        }
+        if self.stock == 0 {
+            self.poured_ml = 0;
+        }
+        true
+    }
+
+    /// What's left in the open bottle, in ml, if we know the bottle size
+    pub fn left_in_bottle_ml(&self) -> Option<u32> {
+        match self.bottle_ml {
+            Some(bottle) if self.stock > 0 => Some(bottle.saturating_sub(self.poured_ml)),
+            _ => None,
+        }
+    }
 }
 
 #[cfg(test)]
@@ -238,4 +279,30 @@ mod test {
         let bad_result = store.select(&bad_selector);
         assert_eq!(bad_result.len(), 0);
     }
+
+    #[test]
+    fn test_pour() {
+        let mut gin = Ingredient {
+            name: "Gin".into(),
+            stock: 2,
+            bottle_ml: Some(750),
+            ..Default::default()
+        };
+        assert!(gin.pour(700.0));
+        assert_eq!((gin.stock, gin.left_in_bottle_ml()), (2, Some(50)));
+        // Finishing a bottle opens the next one
+        gin.pour(60.0);
+        assert_eq!((gin.stock, gin.left_in_bottle_ml()), (1, Some(740)));
+        gin.pour(1000.0);
+        assert_eq!((gin.stock, gin.left_in_bottle_ml()), (0, None));
+
+        // No bottle size, no idea how much is left
+        let mut limes = Ingredient {
+            name: "Limes".into(),
+            stock: 3,
+            ..Default::default()
+        };
+        assert!(!limes.pour(30.0));
+        assert_eq!(limes.stock, 3);
+    }
 }
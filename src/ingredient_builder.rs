@@ -1,3 +1,5 @@
+use std::collections::BTreeMap;
+
 use crate::{
     builder::Builder,
     ingredient::{Ingredient, IngredientTag, Quality},
@@ -10,6 +12,9 @@ pub struct IngredientBuilder {
     pub tags: Vec<String>,
     pub stock: u16,
     pub is_liquor: bool,
+    pub bottle_ml: Option<u32>,
+    pub poured_ml: u32,
+    pub extra: BTreeMap<String, String>,
 }
 
 impl From<Ingredient> for IngredientBuilder {
@@ -20,6 +25,9 @@ impl From<Ingredient> for IngredientBuilder {
             tags: value.tags.iter().map(|t| t.value.clone()).collect(),
             stock: value.stock,
             is_liquor: value.is_liquor, 
+            bottle_ml: value.bottle_ml,
+            poured_ml: value.poured_ml,
+            extra: value.extra,
         }
     }
 }
@@ -36,6 +44,9 @@ impl Into<Ingredient> for IngredientBuilder {
                 .collect(),
             stock: self.stock,
             is_liquor: self.is_liquor,
+            bottle_ml: self.bottle_ml,
+            poured_ml: self.poured_ml,
+            extra: self.extra,
         }
     }
 }
@@ -58,6 +69,9 @@ impl Builder<Ingredient> for IngredientBuilder {
                 .collect(),
             stock: self.stock,
             is_liquor: self.is_liquor,
+            bottle_ml: self.bottle_ml,
+            poured_ml: self.poured_ml,
+            extra: self.extra.clone(),
         }
     }
 }
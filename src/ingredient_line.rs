@@ -0,0 +1,421 @@
+use std::{collections::BTreeSet, fmt};
+
+use crate::{
+    ingredient::IngredientTag,
+    ingredient_store::{IngredientSelector, IngredientStore},
+    measure::Measure,
+    recipie::Component,
+};
+
+/// Words that say how to prepare an ingredient rather than which one it is
+const PREPARATION_WORDS: &[&str] = &[
+    "fresh", "freshly", "squeezed", "pressed", "chilled", "cold", "good", "quality", "about",
+    "approx", "of", "a", "the",
+];
+
+/// Words after an ingredient that say how much of it to use, as in "Soda, to top"
+const AMOUNT_SUFFIXES: &[&str] = &[
+    "to taste", "to top", "to rinse", "to float", "rinse", "float",
+];
+
+/// e.g. "1.5 oz Gin", "2 dashes anything tagged bitters" or "Salt, to taste"
+pub fn to_line(component: &Component) -> String {
+    match component.amount {
+        Measure::Taste | Measure::Top | Measure::Rinse | Measure::Float => {
+            format!("{}, {}", component.ingredient, component.amount)
+        }
+        ref amount => format!("{} {}", amount, component.ingredient),
+    }
+}
+
+/// Reads lines like "1 1/2 oz gin", "½ oz lime juice", "Top with soda" or "Angostura
+/// bitters, to taste". Anything without an amount we recognise is kept whole, to taste.
+pub fn parse(line: &str) -> Component {
+    let (amount, name) = split(line);
+    Component {
+        ingredient: selector(&name),
+        amount,
+    }
+}
+
+/// The amount at the start of a line, and the ingredient after it. An amount at the end,
+/// like "to taste", only counts when there isn't one at the start.
+fn split(line: &str) -> (Measure, String) {
+    let mut line = line.trim();
+    let mut fallback = Measure::Taste;
+    if let Some((rest, suffix)) = AMOUNT_SUFFIXES.iter().find_map(|suffix| {
+        let rest = line.get(..line.len().checked_sub(suffix.len())?)?;
+        let whole_word = rest.is_empty() || rest.ends_with([' ', ',']);
+        (whole_word && line[rest.len()..].eq_ignore_ascii_case(suffix)).then_some((rest, suffix))
+    }) {
+        fallback = suffix.parse().unwrap_or_default();
+        line = rest.trim_end().trim_end_matches(',').trim_end();
+    }
+    let words: Vec<&str> = line.split_whitespace().collect();
+    // The longest run of words at the start that reads as an amount, like "1 1/2 fl oz"
+    for length in (1..=words.len().min(4)).rev() {
+        if let Ok(measure) = words[..length].join(" ").parse::<Measure>() {
+            let name = words[length..].join(" ");
+            let name = name.strip_prefix("of ").unwrap_or(&name);
+            return (measure, name.to_string());
+        }
+    }
+    (fallback, line.to_string())
+}
+
+/// Something in the ingredient store a pasted line could mean
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Candidate {
+    Name(String),
+    Tag(String),
+}
+
+impl Candidate {
+    /// Every ingredient name and tag in the store, names first
+    pub fn all(store: &IngredientStore) -> Vec<Candidate> {
+        let names: BTreeSet<String> = store.get_ingredient_names().into_iter().collect();
+        let tags: BTreeSet<String> = store.get_tags().into_iter().map(|t| t.value).collect();
+        names
+            .into_iter()
+            .map(Candidate::Name)
+            .chain(tags.into_iter().map(Candidate::Tag))
+            .collect()
+    }
+
+    fn text(&self) -> &str {
+        match self {
+            Candidate::Name(text) | Candidate::Tag(text) => text,
+        }
+    }
+}
+
+/// "Gin", or "anything tagged citrus"
+impl fmt::Display for Candidate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Candidate::Name(name) => write!(f, "{}", name),
+            Candidate::Tag(tag) => write!(f, "anything tagged {}", tag),
+        }
+    }
+}
+
+/// How a line's ingredient matched up with the store
+#[derive(Clone, Debug, PartialEq)]
+pub enum Resolution {
+    Matched(Candidate),
+    /// More than one ingredient or tag fits as well as the other
+    Ambiguous(Vec<Candidate>),
+    /// Nothing in the store fits; the component asks for the ingredient as it was written
+    Unmatched,
+    /// Someone decided to ask for the ingredient as it was written anyway
+    AsWritten,
+}
+
+/// A pasted line, read into a component
+#[derive(Clone, Debug)]
+pub struct ParsedLine {
+    pub line: String,
+    /// The ingredient as it was written
+    pub ingredient: String,
+    pub component: Component,
+    pub resolution: Resolution,
+}
+
+impl ParsedLine {
+    /// Whether someone has to pick what the line means
+    pub fn needs_review(&self) -> bool {
+        matches!(
+            self.resolution,
+            Resolution::Ambiguous(_) | Resolution::Unmatched
+        )
+    }
+
+    /// Settles what the line means, keeping any quality it asked for
+    pub fn resolve(&mut self, candidate: Candidate) {
+        let selector = &mut self.component.ingredient;
+        (selector.name, selector.tags) = match &candidate {
+            Candidate::Name(name) => (Some(name.clone()), None),
+            Candidate::Tag(tag) => (None, Some(vec![IngredientTag { value: tag.clone() }])),
+        };
+        self.resolution = Resolution::Matched(candidate);
+    }
+
+    /// Goes back to asking for the ingredient as it was written
+    pub fn keep_as_written(&mut self) {
+        let quality = self.component.ingredient.quality;
+        self.component.ingredient = selector(&self.ingredient);
+        self.component.ingredient.quality = quality;
+        self.resolution = Resolution::AsWritten;
+    }
+}
+
+/// Reads a pasted ingredient list, a line at a time, skipping blank lines and list markers
+pub fn parse_lines(text: &str, store: &IngredientStore) -> Vec<ParsedLine> {
+    let candidates = Candidate::all(store);
+    text.lines()
+        .map(|line| {
+            line.trim()
+                .trim_start_matches(['-', '*', '•', '·'])
+                .trim_start()
+        })
+        .filter(|line| !line.is_empty())
+        .map(|line| parse_against(line, &candidates))
+        .collect()
+}
+
+/// Reads a line, then works out which ingredient or tag in the store it's asking for
+pub fn parse_with(line: &str, store: &IngredientStore) -> ParsedLine {
+    parse_against(line, &Candidate::all(store))
+}
+
+fn parse_against(line: &str, candidates: &[Candidate]) -> ParsedLine {
+    let (amount, ingredient) = split(line);
+    let mut parsed = ParsedLine {
+        line: line.trim().to_string(),
+        component: Component {
+            ingredient: selector(&ingredient),
+            amount,
+        },
+        ingredient,
+        resolution: Resolution::Unmatched,
+    };
+    // "anything tagged bitters" already says what it wants, as long as the store has it.
+    // Several tags at once are for someone to look over.
+    if parsed.component.ingredient.name.is_none() {
+        if let Some([tag]) = parsed.component.ingredient.tags.as_deref()
+            && let Some(candidate) = candidates.iter().find(|candidate| {
+                matches!(candidate, Candidate::Tag(t) if t.eq_ignore_ascii_case(&tag.value))
+            })
+        {
+            parsed.resolve(candidate.clone());
+        }
+        return parsed;
+    }
+    // What comes after a comma is usually a note, like "for garnish"
+    let wanted = words(match &parsed.component.ingredient.name {
+        Some(name) => name.split(',').next().unwrap_or_default(),
+        None => "",
+    });
+    if wanted.is_empty() {
+        return parsed;
+    }
+    // The candidates sharing the most words with the line, as long as one's words are all
+    // in the other's
+    let mut best: Vec<Candidate> = vec![];
+    let mut best_score = 0;
+    for candidate in candidates {
+        let have = words(candidate.text());
+        if have.is_empty() || !(have.is_subset(&wanted) || wanted.is_subset(&have)) {
+            continue;
+        }
+        let mut score = have.intersection(&wanted).count() * 2;
+        // An exact name beats a tag with the same words
+        if have == wanted {
+            score += 1 + matches!(candidate, Candidate::Name(_)) as usize;
+        }
+        if score > best_score {
+            best_score = score;
+            best.clear();
+        }
+        if score == best_score {
+            best.push(candidate.clone());
+        }
+    }
+    match best.len() {
+        0 => {}
+        1 => parsed.resolve(best.remove(0)),
+        _ => parsed.resolution = Resolution::Ambiguous(best),
+    }
+    parsed
+}
+
+/// Lowercased, singular words, without punctuation, numbers or preparation
+fn words(text: &str) -> BTreeSet<String> {
+    text.split(|c: char| !c.is_alphanumeric() && c != '\'')
+        .map(|word| word.to_lowercase())
+        .filter(|word| !word.is_empty() && !word.chars().all(|c| c.is_ascii_digit()))
+        .filter(|word| !PREPARATION_WORDS.contains(&word.as_str()))
+        .map(|word| match word.strip_suffix('s') {
+            Some(single) if single.len() > 2 && !single.ends_with('s') => single.to_string(),
+            _ => word,
+        })
+        .collect()
+}
+
+fn selector(name: &str) -> IngredientSelector {
+    match name.parse() {
+        Ok(selector) => selector,
+        Err(never) => match never {},
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        ingredient::{Ingredient, Quality},
+        store::Store,
+    };
+
+    #[test]
+    fn test_round_trip() {
+        let bitters = Component {
+            ingredient: IngredientSelector {
+                tags: Some(vec![
+                    IngredientTag {
+                        value: "bitters".into(),
+                    },
+                    IngredientTag {
+                        value: "orange".into(),
+                    },
+                ]),
+                quality: Some(Quality::High),
+                ..Default::default()
+            },
+            amount: Measure::Dash(2.0),
+        };
+        assert_eq!(
+            to_line(&bitters),
+            "2 dashes anything tagged bitters, orange (High quality)"
+        );
+        let read = parse(&to_line(&bitters));
+        assert_eq!(read.amount, bitters.amount);
+        assert_eq!(read.ingredient.tags, bitters.ingredient.tags);
+        assert_eq!(read.ingredient.quality, bitters.ingredient.quality);
+        assert_eq!(read.ingredient.name, None);
+
+        let salt = parse("A pinch of salt, to taste");
+        assert_eq!(salt.amount, Measure::Pinch(1.0));
+        assert_eq!(salt.ingredient.name.as_deref(), Some("salt"));
+        let bitters = parse("Angostura bitters, to taste");
+        assert_eq!(bitters.amount, Measure::Taste);
+        assert_eq!(
+            bitters.ingredient.name.as_deref(),
+            Some("Angostura bitters")
+        );
+        let syrup = parse("0.5–0.75 oz simple syrup, to taste");
+        assert_eq!(syrup.amount, Measure::range(Measure::Oz(0.0), 0.5, 0.75));
+        assert_eq!(syrup.ingredient.name.as_deref(), Some("simple syrup"));
+        for (line, amount, name) in [
+            ("Top with soda", Measure::Top, "soda"),
+            ("Soda, to top", Measure::Top, "Soda"),
+            ("Absinthe rinse", Measure::Rinse, "Absinthe"),
+            ("Red wine, to float", Measure::Float, "Red wine"),
+        ] {
+            let component = parse(line);
+            assert_eq!(component.amount, amount, "{}", line);
+            assert_eq!(component.ingredient.name.as_deref(), Some(name), "{}", line);
+            assert_eq!(parse(&to_line(&component)).amount, amount, "{}", line);
+        }
+        let gin = parse("1½ oz Gin (Fancy quality)");
+        assert_eq!(gin.amount, Measure::Oz(1.5));
+        assert_eq!(gin.ingredient.name.as_deref(), Some("Gin (Fancy quality)"));
+    }
+
+    #[test]
+    fn test_parse_against_store() {
+        let mut store = IngredientStore::default();
+        for (name, tags) in [
+            ("Lime juice", vec!["citrus"]),
+            ("Angostura bitters", vec!["bitters"]),
+            ("Peychaud's bitters", vec!["bitters"]),
+            ("Demerara syrup", vec!["syrup"]),
+            ("Simple syrup", vec!["syrup"]),
+            ("London dry gin", vec!["gin"]),
+            ("Old Tom gin", vec!["gin"]),
+        ] {
+            store.register(Ingredient {
+                name: name.into(),
+                tags: tags
+                    .into_iter()
+                    .map(|t| IngredientTag { value: t.into() })
+                    .collect(),
+                ..Default::default()
+            });
+        }
+        let lines = parse_lines(
+            "- 3/4 oz fresh lime juice\n\n\
+             2 dashes Angostura\n\
+             1 barspoon rich demerara syrup\n\
+             2 oz gin\n\
+             1 1/2 fl oz Old Tom Gin\n\
+             2 dashes bitters\n\
+             1 egg white\n\
+             2 dashes anything tagged Bitters",
+            &store,
+        );
+        let matched: Vec<Option<&str>> = lines
+            .iter()
+            .map(|l| match &l.resolution {
+                Resolution::Matched(c) => Some(c.text()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            matched,
+            [
+                Some("Lime juice"),
+                Some("Angostura bitters"),
+                Some("Demerara syrup"),
+                Some("gin"),
+                Some("Old Tom gin"),
+                Some("bitters"),
+                None,
+                Some("bitters"),
+            ]
+        );
+        assert_eq!(lines[0].component.amount, Measure::Oz(0.75));
+        assert_eq!(lines[2].component.amount, Measure::Barspoon(1.0));
+        assert_eq!(lines[4].component.amount, Measure::Oz(1.5));
+        assert!(lines[3].component.ingredient.name.is_none());
+        assert_eq!(lines[6].resolution, Resolution::Unmatched);
+        assert_eq!(
+            lines[6].component.ingredient.name.as_deref(),
+            Some("egg white")
+        );
+        assert_eq!(
+            lines[6].component.amount,
+            Measure::Count {
+                n: 1.0,
+                unit: String::new()
+            }
+        );
+        assert_eq!(
+            lines[7].component.ingredient.tags,
+            Some(vec![IngredientTag {
+                value: "bitters".into()
+            }])
+        );
+        let unknown = parse_with("1 oz anything tagged amaro", &store);
+        assert_eq!(unknown.resolution, Resolution::Unmatched);
+
+        let mut syrup = parse_with("1/2 oz syrup (High quality)", &store);
+        assert_eq!(
+            syrup.resolution,
+            Resolution::Matched(Candidate::Tag("syrup".into()))
+        );
+        let mut either = parse_with("2 dashes Peychaud's or Angostura bitters", &store);
+        assert_eq!(
+            either.resolution,
+            Resolution::Ambiguous(vec![
+                Candidate::Name("Angostura bitters".into()),
+                Candidate::Name("Peychaud's bitters".into()),
+            ])
+        );
+        either.resolve(Candidate::Name("Angostura bitters".into()));
+        assert!(!either.needs_review());
+        assert_eq!(
+            either.component.ingredient.name.as_deref(),
+            Some("Angostura bitters")
+        );
+        either.keep_as_written();
+        assert!(!either.needs_review());
+        assert_eq!(
+            either.component.ingredient.name.as_deref(),
+            Some("Peychaud's or Angostura bitters")
+        );
+        syrup.resolve(Candidate::Name("Demerara syrup".into()));
+        assert_eq!(syrup.component.ingredient.quality, Some(Quality::High));
+        assert!(syrup.component.ingredient.tags.is_none());
+    }
+}
@@ -1,38 +1,145 @@
 use std::{
     collections::{HashMap, HashSet},
-    path::PathBuf,
+    fmt,
+    path::{Path, PathBuf},
+    str::FromStr,
 };
 
 use serde::{Deserialize, Serialize};
+use strum::IntoEnumIterator;
 
 use crate::{
     ingredient::{Ingredient, IngredientTag, Quality},
-    store::Store,
+    sqlite_store,
+    store::{self, DirtyState, DiskState, FileStamp, Migration, Store, StoreError, StoreLoadError},
+    trash::TrashBin,
 };
 
-// Struct for finding ingredients in an ingredient store
-#[derive(Serialize, Default, Deserialize, Clone)]
+/// Struct for finding ingredients in an ingredient store. Every field left as `None`
+/// matches anything.
+#[derive(Serialize, Default, Deserialize, Clone, Debug)]
 pub struct IngredientSelector {
+    /// Exact name
     pub name: Option<String>,
+    /// `Quality::Any` on either side matches every quality
     pub quality: Option<Quality>,
+    /// The ingredient has to carry all of these
     pub tags: Option<Vec<IngredientTag>>,
+    /// `Some(true)` for ingredients with stock left, `Some(false)` for ones that ran out
     pub in_stock: Option<bool>,
     pub is_liquor: Option<bool>,
 }
 
-// A store of ingredients
+/// e.g. "Gin (High quality)", or "anything tagged citrus, sweet"
+impl fmt::Display for IngredientSelector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.name, &self.tags) {
+            (Some(name), _) if !name.is_empty() => write!(f, "{}", name)?,
+            (_, Some(tags)) if !tags.is_empty() => {
+                let tags: Vec<&str> = tags.iter().map(|t| t.value.as_str()).collect();
+                write!(f, "anything tagged {}", tags.join(", "))?
+            }
+            _ => write!(f, "anything")?,
+        }
+        match self.quality {
+            Some(quality) if quality != Quality::Any => write!(f, " ({} quality)", quality),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Reads back what `Display` writes. Anything else is taken as a name.
+impl FromStr for IngredientSelector {
+    type Err = std::convert::Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut selector = IngredientSelector::default();
+        let mut rest = s.trim();
+        if let Some((start, quality)) = rest
+            .strip_suffix(" quality)")
+            .and_then(|r| r.rsplit_once(" ("))
+            && let Some(quality) = Quality::iter().find(|q| q.to_string().eq_ignore_ascii_case(quality))
+        {
+            selector.quality = Some(quality);
+            rest = start.trim_end();
+        }
+        if let Some(tags) = rest.strip_prefix("anything tagged ") {
+            selector.tags = Some(
+                tags.split(',')
+                    .map(str::trim)
+                    .filter(|t| !t.is_empty())
+                    .map(|t| IngredientTag { value: t.to_string() })
+                    .collect(),
+            );
+        } else if rest != "anything" {
+            selector.name = Some(rest.to_string());
+        }
+        Ok(selector)
+    }
+}
+
+/// A store of ingredients
 #[derive(Serialize, Deserialize, Default)]
 pub struct IngredientStore {
     ingredient_map: HashMap<uuid::Uuid, Ingredient>,
     ingredient_tags: HashSet<IngredientTag>,
     config_path: Option<PathBuf>,
+    #[serde(default)]
+    trash: TrashBin<Ingredient>,
+    #[serde(skip)]
+    dirty: DirtyState,
+    #[serde(skip)]
+    disk: DiskState,
 }
 
 impl Store<Ingredient> for IngredientStore {
+    const SCHEMA_VERSION: u32 = 1;
+
     fn get_json_name() -> std::path::PathBuf {
         "ingredient.json".into()
     }
 
+    fn migrations() -> Vec<Migration> {
+        vec![Migration {
+            from: 0,
+            migrate: migrate_v0,
+        }]
+    }
+
+    // Opened through the SQLite backend, the config path is the database rather than a JSON file
+    fn load_from(path: &Path) -> Result<Self, StoreLoadError> {
+        if sqlite_store::is_database(path) {
+            sqlite_store::load_ingredients(path)
+        } else {
+            store::load_json(path)
+        }
+    }
+
+    // The database is shared, so each store locks the file its JSON would be in
+    fn lock_path(path: &Path) -> PathBuf {
+        if sqlite_store::is_database(path) {
+            path.with_file_name(Self::get_json_name())
+        } else {
+            path.to_path_buf()
+        }
+    }
+
+    fn stamp(path: &Path) -> Option<FileStamp> {
+        if sqlite_store::is_database(path) {
+            sqlite_store::ingredients_stamp(path)
+        } else {
+            FileStamp::of(path)
+        }
+    }
+
+    fn save_to(&self, path: &Path) -> Result<(), StoreError> {
+        if sqlite_store::is_database(path) {
+            sqlite_store::save_ingredients(self, path)
+        } else {
+            store::save_json(self, path)
+        }
+    }
+
     fn set_config_path(&mut self, path: std::path::PathBuf) {
         self.config_path = Some(path)
     }
@@ -41,17 +148,48 @@ impl Store<Ingredient> for IngredientStore {
         self.config_path.clone()
     }
 
+    fn dirty(&self) -> &DirtyState {
+        &self.dirty
+    }
+
+    fn dirty_mut(&mut self) -> &mut DirtyState {
+        &mut self.dirty
+    }
+
+    fn disk(&self) -> &DiskState {
+        &self.disk
+    }
+
+    fn disk_mut(&mut self) -> &mut DiskState {
+        &mut self.disk
+    }
+
+    fn trash_bin(&self) -> &TrashBin<Ingredient> {
+        &self.trash
+    }
+
+    fn trash_bin_mut(&mut self) -> &mut TrashBin<Ingredient> {
+        &mut self.trash
+    }
+
     fn register(&mut self, entry: Ingredient) -> uuid::Uuid {
         let id = uuid::Uuid::new_v4();
+        self.insert(id, entry);
+        id
+    }
+
+    fn insert(&mut self, id: uuid::Uuid, entry: Ingredient) {
+        self.dirty.touch_id(id);
         for tag in &entry.tags {
             self.ingredient_tags.insert(tag.clone());
         }
         self.ingredient_map.insert(id, entry);
-        id
     }
 
-    fn deregister(&mut self, id: uuid::Uuid) -> bool {
-        self.ingredient_map.remove(&id).is_some()
+    fn deregister(&mut self, id: uuid::Uuid) -> Result<(), StoreError> {
+        self.ingredient_map.remove(&id).ok_or(StoreError::UnknownId(id))?;
+        self.dirty.touch_id(id);
+        Ok(())
     }
 
     fn get_entries(&self) -> Vec<Ingredient> {
@@ -59,9 +197,17 @@ impl Store<Ingredient> for IngredientStore {
     }
 
     fn get_entries_mut(&mut self) -> Vec<&mut Ingredient> {
+        self.dirty.touch();
         self.ingredient_map.values_mut().collect()
     }
 
+    fn get_entries_with_ids(&self) -> Vec<(uuid::Uuid, Ingredient)> {
+        self.ingredient_map
+            .iter()
+            .map(|(id, i)| (*id, i.clone()))
+            .collect()
+    }
+
     fn get_entry(&self, id: uuid::Uuid) -> Option<Ingredient> {
         match self.ingredient_map.get(&id) {
             Some(entry) => Some(entry.clone()),
@@ -71,30 +217,57 @@ impl Store<Ingredient> for IngredientStore {
 
     fn get_entry_mut(&mut self, id: uuid::Uuid) -> Option<&mut Ingredient> {
         match self.ingredient_map.get_mut(&id) {
-            Some(entry) => Some(entry),
+            Some(entry) => {
+                self.dirty.touch_id(id);
+                Some(entry)
+            }
             None => None,
         }
     }
 }
 
+/// v1 introduced the schema_version envelope. Ingredients from before is_liquor existed are mixers.
+fn migrate_v0(mut data: serde_json::Value) -> Result<serde_json::Value, String> {
+    if let Some(map) = data
+        .get_mut("ingredient_map")
+        .and_then(|m| m.as_object_mut())
+    {
+        for ingredient in map.values_mut().filter_map(|i| i.as_object_mut()) {
+            ingredient
+                .entry("is_liquor")
+                .or_insert(serde_json::Value::Bool(false));
+        }
+    }
+    Ok(data)
+}
+
 impl IngredientStore {
     /*pub fn new() -> IngredientStore
     {
         IngredientStore::default()
     }*/
 
+    /// Every ingredient matching `selector`, in no particular order
     pub fn select(&self, selector: &IngredientSelector) -> Vec<Ingredient> {
+        self.select_with_ids(selector)
+            .into_iter()
+            .map(|(_, i)| i)
+            .collect()
+    }
+
+    /// `select`, keeping track of which ingredient is which
+    pub fn select_with_ids(&self, selector: &IngredientSelector) -> Vec<(uuid::Uuid, Ingredient)> {
         self.ingredient_map
-            .values()
-            .filter(|i| match &selector.name {
+            .iter()
+            .filter(|(_, i)| match &selector.name {
                 Some(n) => *n == i.name,
                 None => true,
             })
-            .filter(|i| match selector.quality {
+            .filter(|(_, i)| match selector.quality {
                 Some(q) => q == Quality::Any || i.quality == Quality::Any || q == i.quality,
                 None => true,
             })
-            .filter(|i| match &selector.tags {
+            .filter(|(_, i)| match &selector.tags {
                 Some(tags) => {
                     for tag in tags {
                         if !i.tags.contains(tag) {
@@ -105,19 +278,29 @@ impl IngredientStore {
                 }
                 None => true,
             })
-            .filter(|i| match selector.in_stock {
+            .filter(|(_, i)| match selector.in_stock {
                 Some(true) => i.stock > 0,
                 Some(false) => i.stock == 0,
                 None => true,
             })
-            .filter(|i| match selector.is_liquor {
+            .filter(|(_, i)| match selector.is_liquor {
                 Some(l) => l == i.is_liquor,
                 None => true
             })
-            .cloned()
+            .map(|(id, i)| (*id, i.clone()))
             .collect()
     }
 
+    /// The in-stock ingredient that gets poured for `selector`. It's the first by name, so
+    /// it's the same bottle every time.
+    pub fn pick(&self, selector: &IngredientSelector) -> Option<(uuid::Uuid, Ingredient)> {
+        let mut selector = selector.clone();
+        selector.in_stock = Some(true);
+        self.select_with_ids(&selector)
+            .into_iter()
+            .min_by_key(|(_, i)| i.name.to_lowercase())
+    }
+
     pub fn get_ingredient_names(&self) -> Vec<String> {
         return self
             .ingredient_map
@@ -138,6 +321,7 @@ impl IngredientStore {
     }
 
     pub fn get_ingredient_entries(&mut self) -> Vec<(uuid::Uuid, &mut Ingredient)> {
+        self.dirty.touch();
         self.ingredient_map
             .iter_mut()
             .map(|(id, i)| (id.clone(), i))
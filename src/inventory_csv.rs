@@ -0,0 +1,586 @@
+use std::{
+    collections::{BTreeSet, HashMap},
+    fmt,
+    io::{Read, Write},
+};
+
+use strum::IntoEnumIterator;
+use uuid::Uuid;
+
+use crate::{
+    ingredient::{Ingredient, IngredientTag, Quality},
+    ingredient_store::IngredientStore,
+    store::Store,
+};
+
+/// Why a CSV file couldn't be read, written or imported
+#[derive(Debug)]
+pub enum CsvError {
+    Csv(csv::Error),
+    /// Nothing to match rows up with existing ingredients by
+    NoNameColumn,
+    /// Two columns would both fill in the same field
+    DuplicateColumn(Column),
+}
+
+impl From<csv::Error> for CsvError {
+    fn from(err: csv::Error) -> Self {
+        CsvError::Csv(err)
+    }
+}
+
+impl From<std::io::Error> for CsvError {
+    fn from(err: std::io::Error) -> Self {
+        CsvError::Csv(err.into())
+    }
+}
+
+impl fmt::Display for CsvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CsvError::Csv(err) => err.fmt(f),
+            CsvError::NoNameColumn => write!(f, "One of the columns has to be the name"),
+            CsvError::DuplicateColumn(column) => {
+                write!(f, "More than one column is mapped to {}", column)
+            }
+        }
+    }
+}
+
+impl std::error::Error for CsvError {}
+
+/// What a CSV column fills in on an ingredient
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Column {
+    Name,
+    Quality,
+    /// Separated by `;` or `,`
+    Tags,
+    Stock,
+    IsLiquor,
+    BottleMl,
+    /// Kept in `Ingredient::extra` under this name
+    Extra(String),
+    Skip,
+}
+
+impl Column {
+    /// The columns every ingredient has, in the order they're exported
+    pub fn fields() -> [Column; 6] {
+        [
+            Column::Name,
+            Column::Quality,
+            Column::Tags,
+            Column::Stock,
+            Column::IsLiquor,
+            Column::BottleMl,
+        ]
+    }
+
+    /// What it's called in an exported file
+    pub fn header(&self) -> &str {
+        match self {
+            Column::Name => "name",
+            Column::Quality => "quality",
+            Column::Tags => "tags",
+            Column::Stock => "stock",
+            Column::IsLiquor => "is_liquor",
+            Column::BottleMl => "bottle_ml",
+            Column::Extra(name) => name,
+            Column::Skip => "",
+        }
+    }
+
+    /// Our best guess at what a column is from its header, which the user can correct.
+    /// Headers we don't recognise are kept as extra columns.
+    pub fn guess(header: &str) -> Column {
+        let header = header.trim();
+        match header.to_lowercase().replace([' ', '-'], "_").as_str() {
+            "" => Column::Skip,
+            "name" | "ingredient" | "item" | "product" => Column::Name,
+            "quality" | "grade" => Column::Quality,
+            "tags" | "tag" | "category" | "categories" => Column::Tags,
+            "stock" | "qty" | "quantity" | "count" | "on_hand" | "bottles" => Column::Stock,
+            "is_liquor" | "liquor" | "spirit" | "alcoholic" => Column::IsLiquor,
+            "bottle_ml" | "bottle_size" | "size_ml" | "size" | "ml" => Column::BottleMl,
+            _ => Column::Extra(header.to_string()),
+        }
+    }
+}
+
+impl fmt::Display for Column {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Column::Name => write!(f, "Name"),
+            Column::Quality => write!(f, "Quality"),
+            Column::Tags => write!(f, "Tags"),
+            Column::Stock => write!(f, "Stock"),
+            Column::IsLiquor => write!(f, "Liquor?"),
+            Column::BottleMl => write!(f, "Bottle size (ml)"),
+            Column::Extra(name) => write!(f, "Keep as \"{}\"", name),
+            Column::Skip => write!(f, "Skip"),
+        }
+    }
+}
+
+/// Writes every ingredient as a row, sorted by name. Extra columns come after the usual
+/// ones, one for every extra any ingredient has.
+pub fn export(store: &IngredientStore, out: impl Write) -> Result<(), CsvError> {
+    let mut ingredients = store.get_entries();
+    ingredients.sort_by_key(|i| i.name.to_lowercase());
+    let extras: BTreeSet<String> = ingredients
+        .iter()
+        .flat_map(|i| i.extra.keys().cloned())
+        .collect();
+    let mut writer = csv::Writer::from_writer(out);
+    let mut header: Vec<String> = Column::fields()
+        .iter()
+        .map(|c| c.header().to_string())
+        .collect();
+    header.extend(extras.iter().cloned());
+    writer.write_record(&header)?;
+    for ingredient in &ingredients {
+        let tags: Vec<&str> = ingredient.tags.iter().map(|t| t.value.as_str()).collect();
+        let mut row = vec![
+            ingredient.name.clone(),
+            ingredient.quality.to_string(),
+            tags.join("; "),
+            ingredient.stock.to_string(),
+            ingredient.is_liquor.to_string(),
+            ingredient
+                .bottle_ml
+                .map(|ml| ml.to_string())
+                .unwrap_or_default(),
+        ];
+        row.extend(
+            extras
+                .iter()
+                .map(|e| ingredient.extra.get(e).cloned().unwrap_or_default()),
+        );
+        writer.write_record(&row)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// A CSV file as it was read, before its columns mean anything
+#[derive(Debug, Default, Clone)]
+pub struct Table {
+    pub headers: Vec<String>,
+    pub rows: Vec<Vec<String>>,
+}
+
+impl Table {
+    /// The first row is taken as the headers. Short rows are padded out.
+    pub fn read(input: impl Read) -> Result<Table, CsvError> {
+        let mut reader = csv::ReaderBuilder::new()
+            .flexible(true)
+            .trim(csv::Trim::All)
+            .from_reader(input);
+        let headers: Vec<String> = reader.headers()?.iter().map(String::from).collect();
+        let mut rows = vec![];
+        for record in reader.records() {
+            let mut row: Vec<String> = record?.iter().map(String::from).collect();
+            row.resize(headers.len(), String::new());
+            rows.push(row);
+        }
+        Ok(Table { headers, rows })
+    }
+
+    pub fn guess_columns(&self) -> Vec<Column> {
+        self.headers.iter().map(|h| Column::guess(h)).collect()
+    }
+}
+
+/// What importing one row would do
+#[derive(Debug, Clone, PartialEq)]
+pub enum RowAction {
+    Add,
+    /// The changes, e.g. "stock 1 → 3"
+    Update(Uuid, Vec<String>),
+    Unchanged(Uuid),
+    /// Why it's left out
+    Skip(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct PlannedRow {
+    /// Where it is in the file, counting the header as line 1
+    pub line: usize,
+    /// The ingredient as it would be after importing
+    pub ingredient: Ingredient,
+    pub action: RowAction,
+}
+
+/// What importing a file would do, worked out without touching the store, so it can be
+/// previewed and reported on first
+#[derive(Debug, Clone, Default)]
+pub struct ImportPlan {
+    pub rows: Vec<PlannedRow>,
+}
+
+/// Rows are matched up with ingredients by name, ignoring case. Matched ingredients only
+/// have the mapped columns changed; everything else about them stays as it is.
+pub fn plan(
+    table: &Table,
+    columns: &[Column],
+    store: &IngredientStore,
+) -> Result<ImportPlan, CsvError> {
+    for field in Column::fields() {
+        if columns.iter().filter(|c| **c == field).count() > 1 {
+            return Err(CsvError::DuplicateColumn(field));
+        }
+    }
+    let name_column = columns
+        .iter()
+        .position(|c| *c == Column::Name)
+        .ok_or(CsvError::NoNameColumn)?;
+    let existing: HashMap<String, (Uuid, Ingredient)> = store
+        .get_entries_with_ids()
+        .into_iter()
+        .map(|(id, i)| (i.name.to_lowercase(), (id, i)))
+        .collect();
+    // Names seen so far in the file, and on which line
+    let mut seen: HashMap<String, usize> = HashMap::new();
+    let mut rows = vec![];
+    for (index, row) in table.rows.iter().enumerate() {
+        let line = index + 2;
+        let name = row.get(name_column).cloned().unwrap_or_default();
+        let key = name.to_lowercase();
+        let found = existing.get(&key);
+        let mut ingredient = match found {
+            Some((_, ingredient)) => ingredient.clone(),
+            None => Ingredient::default(),
+        };
+        let filled = fill(&mut ingredient, columns, row);
+        let action = if name.is_empty() {
+            RowAction::Skip("no name".to_string())
+        } else if let Some(first) = seen.get(&key) {
+            RowAction::Skip(format!("{} is already on line {}", name, first))
+        } else if let Err(reason) = filled {
+            RowAction::Skip(reason)
+        } else {
+            match found {
+                None => RowAction::Add,
+                Some((id, before)) => match changes(before, &ingredient) {
+                    changes if changes.is_empty() => RowAction::Unchanged(*id),
+                    changes => RowAction::Update(*id, changes),
+                },
+            }
+        };
+        seen.entry(key).or_insert(line);
+        rows.push(PlannedRow {
+            line,
+            ingredient,
+            action,
+        });
+    }
+    Ok(ImportPlan { rows })
+}
+
+// Sets whatever the columns map to from one row
+fn fill(ingredient: &mut Ingredient, columns: &[Column], row: &[String]) -> Result<(), String> {
+    for (column, cell) in columns.iter().zip(row) {
+        match column {
+            // Rows are matched by name ignoring case, so that's no reason to rename
+            Column::Name if ingredient.name.to_lowercase() == cell.to_lowercase() => (),
+            Column::Name => ingredient.name = cell.clone(),
+            Column::Quality => ingredient.quality = parse_quality(cell)?,
+            Column::Tags => {
+                ingredient.tags = cell
+                    .split([';', ','])
+                    .map(str::trim)
+                    .filter(|t| !t.is_empty())
+                    .map(|t| IngredientTag {
+                        value: t.to_string(),
+                    })
+                    .collect()
+            }
+            Column::Stock if cell.is_empty() => ingredient.stock = 0,
+            Column::Stock => {
+                ingredient.stock = cell
+                    .parse()
+                    .map_err(|_| format!("stock '{}' isn't a whole number", cell))?
+            }
+            Column::IsLiquor => ingredient.is_liquor = parse_bool(cell)?,
+            Column::BottleMl if cell.is_empty() => ingredient.bottle_ml = None,
+            Column::BottleMl => {
+                ingredient.bottle_ml = Some(
+                    cell.trim_end_matches("ml")
+                        .trim()
+                        .parse()
+                        .map_err(|_| format!("bottle size '{}' isn't a number of ml", cell))?,
+                )
+            }
+            Column::Extra(name) if cell.is_empty() => {
+                ingredient.extra.remove(name);
+            }
+            Column::Extra(name) => {
+                ingredient.extra.insert(name.clone(), cell.clone());
+            }
+            Column::Skip => (),
+        }
+    }
+    Ok(())
+}
+
+fn parse_quality(cell: &str) -> Result<Quality, String> {
+    if cell.is_empty() {
+        return Ok(Quality::Any);
+    }
+    Quality::iter()
+        .find(|q| q.to_string().eq_ignore_ascii_case(cell))
+        .ok_or_else(|| format!("quality '{}' isn't one of Low, Medium, High or Any", cell))
+}
+
+fn parse_bool(cell: &str) -> Result<bool, String> {
+    match cell.to_lowercase().as_str() {
+        "" | "false" | "no" | "n" | "0" => Ok(false),
+        "true" | "yes" | "y" | "1" | "x" => Ok(true),
+        _ => Err(format!("'{}' isn't yes or no", cell)),
+    }
+}
+
+// What an update would change, for the preview
+fn changes(before: &Ingredient, after: &Ingredient) -> Vec<String> {
+    let mut changes = vec![];
+    let mut compare = |field: &str, before: String, after: String| {
+        if before != after {
+            changes.push(format!(
+                "{} {} → {}",
+                field,
+                or_none(&before),
+                or_none(&after)
+            ));
+        }
+    };
+    compare("name", before.name.clone(), after.name.clone());
+    compare(
+        "quality",
+        before.quality.to_string(),
+        after.quality.to_string(),
+    );
+    let tags = |i: &Ingredient| {
+        let tags: Vec<&str> = i.tags.iter().map(|t| t.value.as_str()).collect();
+        tags.join(", ")
+    };
+    compare("tags", tags(before), tags(after));
+    compare("stock", before.stock.to_string(), after.stock.to_string());
+    compare(
+        "liquor",
+        before.is_liquor.to_string(),
+        after.is_liquor.to_string(),
+    );
+    let bottle = |i: &Ingredient| i.bottle_ml.map(|ml| format!("{} ml", ml));
+    compare(
+        "bottle",
+        bottle(before).unwrap_or_default(),
+        bottle(after).unwrap_or_default(),
+    );
+    let keys: BTreeSet<&String> = before.extra.keys().chain(after.extra.keys()).collect();
+    for key in keys {
+        compare(
+            key,
+            before.extra.get(key).cloned().unwrap_or_default(),
+            after.extra.get(key).cloned().unwrap_or_default(),
+        );
+    }
+    changes
+}
+
+fn or_none(value: &str) -> &str {
+    if value.is_empty() { "(none)" } else { value }
+}
+
+impl ImportPlan {
+    pub fn added(&self) -> usize {
+        self.count(|a| matches!(a, RowAction::Add))
+    }
+
+    pub fn updated(&self) -> usize {
+        self.count(|a| matches!(a, RowAction::Update(..)))
+    }
+
+    pub fn unchanged(&self) -> usize {
+        self.count(|a| matches!(a, RowAction::Unchanged(_)))
+    }
+
+    pub fn skipped(&self) -> usize {
+        self.count(|a| matches!(a, RowAction::Skip(_)))
+    }
+
+    fn count(&self, which: fn(&RowAction) -> bool) -> usize {
+        self.rows.iter().filter(|r| which(&r.action)).count()
+    }
+
+    /// e.g. "3 to add, 1 to update, 4 unchanged, 1 skipped"
+    pub fn summary(&self) -> String {
+        format!(
+            "{} to add, {} to update, {} unchanged, {} skipped",
+            self.added(),
+            self.updated(),
+            self.unchanged(),
+            self.skipped()
+        )
+    }
+
+    /// The dry run: the summary, then what would happen to every row that'd change or
+    /// gets skipped
+    pub fn report(&self) -> String {
+        let mut report = self.summary();
+        for row in &self.rows {
+            let name = &row.ingredient.name;
+            let line = match &row.action {
+                RowAction::Add => format!("add {}", name),
+                RowAction::Update(_, changes) => {
+                    format!("update {}: {}", name, changes.join(", "))
+                }
+                RowAction::Unchanged(_) => continue,
+                RowAction::Skip(reason) => format!("skip: {}", reason),
+            };
+            report.push_str(&format!("\nline {}: {}", row.line, line));
+        }
+        report
+    }
+
+    /// Adds and updates ingredients as planned. Returns how many changed.
+    pub fn apply(&self, store: &mut IngredientStore) -> usize {
+        let mut changed = 0;
+        for row in &self.rows {
+            match &row.action {
+                RowAction::Add => {
+                    store.register(row.ingredient.clone());
+                }
+                RowAction::Update(id, _) => store.insert(*id, row.ingredient.clone()),
+                RowAction::Unchanged(_) | RowAction::Skip(_) => continue,
+            }
+            changed += 1;
+        }
+        changed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SPREADSHEET: &str = "\
+Item,Qty,Category,Supplier,Notes
+Gin,3,Gin; London dry,Bob's,
+lime juice,2,citrus,,fresh
+Rum,lots,,,
+,1,,,
+Gin,1,,,
+Tonic,6,mixer,Bob's,
+";
+
+    fn store() -> (IngredientStore, Uuid, Uuid) {
+        let mut store = IngredientStore::default();
+        let gin = store.register(Ingredient {
+            name: "Gin".into(),
+            quality: Quality::High,
+            stock: 1,
+            is_liquor: true,
+            ..Default::default()
+        });
+        let lime = store.register(Ingredient {
+            name: "Lime juice".into(),
+            tags: vec![IngredientTag {
+                value: "citrus".into(),
+            }],
+            stock: 2,
+            ..Default::default()
+        });
+        (store, gin, lime)
+    }
+
+    #[test]
+    fn test_plan_and_apply() {
+        let (mut store, gin, lime) = store();
+        let table = Table::read(SPREADSHEET.as_bytes()).unwrap();
+        let mut columns = table.guess_columns();
+        assert_eq!(
+            columns,
+            vec![
+                Column::Name,
+                Column::Stock,
+                Column::Tags,
+                Column::Extra("Supplier".into()),
+                Column::Extra("Notes".into()),
+            ]
+        );
+        columns[4] = Column::Skip;
+        let plan = plan(&table, &columns, &store).unwrap();
+        assert_eq!(
+            plan.summary(),
+            "1 to add, 1 to update, 1 unchanged, 3 skipped"
+        );
+        let report = plan.report();
+        assert!(report.contains("line 2: update Gin: tags (none) → Gin, London dry"));
+        assert!(report.contains("stock 1 → 3"));
+        assert!(report.contains("line 4: skip: stock 'lots' isn't a whole number"));
+        assert!(report.contains("line 5: skip: no name"));
+        assert!(report.contains("line 6: skip: Gin is already on line 2"));
+        assert!(report.contains("line 7: add Tonic"));
+        // Nothing happens until it's applied
+        assert_eq!(store.get_entry(gin).unwrap().stock, 1);
+
+        assert_eq!(plan.apply(&mut store), 2);
+        let updated = store.get_entry(gin).unwrap();
+        assert_eq!(updated.stock, 3);
+        // Columns that weren't in the file are left alone
+        assert_eq!(updated.quality, Quality::High);
+        assert!(updated.is_liquor);
+        assert_eq!(updated.extra["Supplier"], "Bob's");
+        assert_eq!(store.get_entry(lime).unwrap().name, "Lime juice");
+        assert_eq!(store.get_entries().len(), 3);
+    }
+
+    #[test]
+    fn test_export_round_trip() {
+        let (mut store, gin, _) = store();
+        let mut ingredient = store.get_entry(gin).unwrap();
+        ingredient.extra.insert("Supplier".into(), "Bob's".into());
+        ingredient.bottle_ml = Some(700);
+        store.insert(gin, ingredient);
+        let mut exported = vec![];
+        export(&store, &mut exported).unwrap();
+        let text = String::from_utf8(exported.clone()).unwrap();
+        assert!(text.starts_with("name,quality,tags,stock,is_liquor,bottle_ml,Supplier\n"));
+        assert!(text.contains("Gin,High,,1,true,700,Bob's\n"));
+
+        let table = Table::read(exported.as_slice()).unwrap();
+        let plan = plan(&table, &table.guess_columns(), &store).unwrap();
+        assert_eq!(plan.unchanged(), 2);
+        let mut empty = IngredientStore::default();
+        let plan = super::plan(&table, &table.guess_columns(), &empty).unwrap();
+        assert_eq!(plan.added(), 2);
+        plan.apply(&mut empty);
+        assert_eq!(
+            empty.get_ingredient(&"Gin".to_string()),
+            store.get_entry(gin)
+        );
+    }
+
+    #[test]
+    fn test_bad_mappings() {
+        let (store, _, _) = store();
+        let table = Table::read(SPREADSHEET.as_bytes()).unwrap();
+        let columns = vec![Column::Skip; 5];
+        assert!(matches!(
+            plan(&table, &columns, &store),
+            Err(CsvError::NoNameColumn)
+        ));
+        let columns = vec![
+            Column::Name,
+            Column::Stock,
+            Column::Stock,
+            Column::Skip,
+            Column::Skip,
+        ];
+        assert!(matches!(
+            plan(&table, &columns, &store),
+            Err(CsvError::DuplicateColumn(Column::Stock))
+        ));
+    }
+}
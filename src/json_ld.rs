@@ -0,0 +1,263 @@
+use std::{
+    fmt,
+    io::{Read, Write},
+};
+
+use serde_json::{Value, json};
+
+use crate::{ingredient_line, recipie::Recipie};
+
+/// Why a JSON-LD file couldn't be read or written
+#[derive(Debug)]
+pub enum JsonLdError {
+    Json(serde_json::Error),
+    /// The file is JSON, but there's no schema.org `Recipe` in it
+    NoRecipe,
+}
+
+impl From<serde_json::Error> for JsonLdError {
+    fn from(err: serde_json::Error) -> Self {
+        JsonLdError::Json(err)
+    }
+}
+
+impl fmt::Display for JsonLdError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JsonLdError::Json(err) => err.fmt(f),
+            JsonLdError::NoRecipe => write!(f, "There's no schema.org Recipe in that file"),
+        }
+    }
+}
+
+impl std::error::Error for JsonLdError {}
+
+/// A recipie as a schema.org `Recipe`. Notes and ingredient qualities have nowhere to go,
+/// so they're left out.
+pub fn to_json_ld(recipie: &Recipie) -> Value {
+    let mut recipe = json!({
+        "@context": "https://schema.org",
+        "@type": "Recipe",
+        "name": recipie.name,
+        "recipeCategory": "Drink",
+        "recipeIngredient": recipie.components.iter().map(ingredient_line::to_line).collect::<Vec<String>>(),
+        "recipeInstructions": recipie
+            .instructions
+            .iter()
+            .map(|step| json!({ "@type": "HowToStep", "text": step }))
+            .collect::<Vec<Value>>(),
+    });
+    for (key, value) in [
+        ("description", &recipie.description),
+        ("alternativeHeadline", &recipie.short_description),
+    ] {
+        if !value.is_empty() {
+            recipe[key] = json!(value);
+        }
+    }
+    recipe
+}
+
+pub fn write(recipie: &Recipie, out: impl Write) -> Result<(), JsonLdError> {
+    Ok(serde_json::to_writer_pretty(out, &to_json_ld(recipie))?)
+}
+
+/// Every `Recipe` in a JSON-LD document, whether it's the document itself, in an array, or
+/// in an `@graph` the way most sites publish them
+pub fn from_json_ld(document: &Value) -> Result<Vec<Recipie>, JsonLdError> {
+    let mut recipies = vec![];
+    find_recipes(document, &mut recipies);
+    if recipies.is_empty() {
+        return Err(JsonLdError::NoRecipe);
+    }
+    Ok(recipies)
+}
+
+pub fn read(input: impl Read) -> Result<Vec<Recipie>, JsonLdError> {
+    from_json_ld(&serde_json::from_reader(input)?)
+}
+
+fn find_recipes(value: &Value, recipies: &mut Vec<Recipie>) {
+    match value {
+        Value::Array(items) => items.iter().for_each(|item| find_recipes(item, recipies)),
+        Value::Object(object) if is_type(value, "Recipe") => recipies.push(Recipie {
+            name: text(object.get("name")).unwrap_or_default(),
+            description: text(object.get("description")).unwrap_or_default(),
+            short_description: text(object.get("alternativeHeadline")).unwrap_or_default(),
+            components: match object.get("recipeIngredient") {
+                Some(Value::Array(lines)) => lines
+                    .iter()
+                    .filter_map(|line| line.as_str())
+                    .filter(|line| !line.trim().is_empty())
+                    .map(ingredient_line::parse)
+                    .collect(),
+                Some(Value::String(line)) => vec![ingredient_line::parse(line)],
+                _ => vec![],
+            },
+            instructions: object
+                .get("recipeInstructions")
+                .map(instructions)
+                .unwrap_or_default(),
+            ..Default::default()
+        }),
+        Value::Object(object) => {
+            if let Some(graph) = object.get("@graph") {
+                find_recipes(graph, recipies);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// `@type` can be a single type or a list of them
+fn is_type(value: &Value, wanted: &str) -> bool {
+    match value.get("@type") {
+        Some(Value::String(t)) => t == wanted,
+        Some(Value::Array(types)) => types.iter().any(|t| t.as_str() == Some(wanted)),
+        _ => false,
+    }
+}
+
+fn text(value: Option<&Value>) -> Option<String> {
+    match value? {
+        Value::String(s) => Some(s.trim().to_string()),
+        Value::Array(items) => text(items.first()),
+        _ => None,
+    }
+}
+
+/// Instructions turn up as one block of text, a list of strings, or a list of `HowToStep`s,
+/// possibly grouped into `HowToSection`s
+fn instructions(value: &Value) -> Vec<String> {
+    match value {
+        Value::String(block) => block
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(String::from)
+            .collect(),
+        Value::Array(items) => items.iter().flat_map(instructions).collect(),
+        Value::Object(object) if is_type(value, "HowToSection") => object
+            .get("itemListElement")
+            .map(instructions)
+            .unwrap_or_default(),
+        Value::Object(object) => text(object.get("text").or(object.get("name")))
+            .filter(|step| !step.is_empty())
+            .into_iter()
+            .collect(),
+        _ => vec![],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{measure::Measure, test_dir::component};
+
+    fn assert_same(a: &Recipie, b: &Recipie) {
+        assert_eq!(a.name, b.name);
+        assert_eq!(a.description, b.description);
+        assert_eq!(a.short_description, b.short_description);
+        assert_eq!(a.instructions, b.instructions);
+        assert_eq!(a.components.len(), b.components.len());
+        for (a, b) in a.components.iter().zip(&b.components) {
+            assert_eq!(a.ingredient.name, b.ingredient.name);
+            assert_eq!(a.amount, b.amount);
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let negroni = Recipie {
+            name: "Negroni".into(),
+            description: "Equal parts, stirred.".into(),
+            short_description: "Bitter and strong".into(),
+            components: vec![
+                component("Gin", Measure::Oz(1.0)),
+                component("Campari", Measure::Ml(30.0)),
+                component("Sweet vermouth", Measure::Part(1.5)),
+                component("Orange bitters", Measure::Dash(2.0)),
+                component("Orange peel", Measure::Taste),
+            ],
+            instructions: vec!["Stir with ice".into(), "Strain over a big cube".into()],
+            ..Default::default()
+        };
+        let mut file = vec![];
+        write(&negroni, &mut file).unwrap();
+        let read = read(file.as_slice()).unwrap();
+        assert_eq!(read.len(), 1);
+        assert_same(&negroni, &read[0]);
+
+        // And back out again, unchanged
+        assert_eq!(to_json_ld(&read[0]), to_json_ld(&negroni));
+    }
+
+    #[test]
+    fn test_published_recipe() {
+        let page = r#"{
+            "@context": "https://schema.org",
+            "@graph": [
+                { "@type": "WebPage", "name": "Daiquiri | Some Blog" },
+                {
+                    "@type": ["Recipe", "NewsArticle"],
+                    "name": "Daiquiri",
+                    "description": "A classic sour.",
+                    "recipeIngredient": [
+                        "2 oz. white rum",
+                        "1 1/2 tsp simple syrup",
+                        "½ oz of lime juice",
+                        "1 lime wheel, for garnish",
+                        ""
+                    ],
+                    "recipeInstructions": [
+                        {
+                            "@type": "HowToSection",
+                            "name": "Make it",
+                            "itemListElement": [
+                                { "@type": "HowToStep", "text": "Shake with ice." },
+                                { "@type": "HowToStep", "text": "Strain into a coupe." }
+                            ]
+                        },
+                        "Garnish."
+                    ]
+                }
+            ]
+        }"#;
+        let read = read(page.as_bytes()).unwrap();
+        assert_same(
+            &read[0],
+            &Recipie {
+                name: "Daiquiri".into(),
+                description: "A classic sour.".into(),
+                components: vec![
+                    component("white rum", Measure::Oz(2.0)),
+                    component("simple syrup", Measure::Teaspoon(1.5)),
+                    component("lime juice", Measure::Oz(0.5)),
+                    component(
+                        "lime wheel, for garnish",
+                        Measure::Count {
+                            n: 1.0,
+                            unit: String::new(),
+                        },
+                    ),
+                ],
+                instructions: vec![
+                    "Shake with ice.".into(),
+                    "Strain into a coupe.".into(),
+                    "Garnish.".into(),
+                ],
+                ..Default::default()
+            },
+        );
+
+        let steps: Value = json!({ "@type": "Recipe", "recipeInstructions": "Stir.\n\nStrain." });
+        assert_eq!(
+            from_json_ld(&steps).unwrap()[0].instructions,
+            ["Stir.", "Strain."]
+        );
+        assert!(matches!(
+            from_json_ld(&json!({ "@type": "WebPage" })),
+            Err(JsonLdError::NoRecipe)
+        ));
+    }
+}
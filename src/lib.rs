@@ -0,0 +1,99 @@
+//! The data model and matching logic behind Drink Solver: ingredients, recipies, the
+//! stores they're kept in, and working out what can be made from what's behind the bar.
+//!
+//! The GUI lives behind the `gui` feature, the command line behind `cli`, and the HTTP
+//! API and guest menu behind `server`. All three are on by default. Turn default features
+//! off to use the library without pulling in egui or an HTTP server.
+//!
+//! ```
+//! use drink_solver::{
+//!     ingredient::Ingredient,
+//!     ingredient_store::{IngredientSelector, IngredientStore},
+//!     measure::Measure,
+//!     recipie::{Component, Recipie},
+//!     store::Store,
+//! };
+//!
+//! let mut bar = IngredientStore::default();
+//! bar.register(Ingredient {
+//!     name: "Gin".into(),
+//!     stock: 1,
+//!     is_liquor: true,
+//!     ..Default::default()
+//! });
+//! let martini = Recipie {
+//!     name: "Martini".into(),
+//!     components: vec![Component {
+//!         ingredient: IngredientSelector {
+//!             name: Some("Gin".into()),
+//!             ..Default::default()
+//!         },
+//!         amount: Measure::Oz(2.0),
+//!     }],
+//!     ..Default::default()
+//! };
+//! assert!(martini.can_make(&bar));
+//! ```
+
+/// Choosing between the JSON and SQLite stores
+pub mod backend;
+/// Editable stand-ins for model types
+pub mod builder;
+/// Scripting the stores from the terminal
+#[cfg(feature = "cli")]
+pub mod cli;
+/// Editable stand-in for a recipie component
+pub mod component_builder;
+/// Undo/redo for changes made to the stores
+pub mod history;
+/// Ingredients and what describes them
+pub mod ingredient;
+/// Editable stand-in for an ingredient, tags and all
+pub mod ingredient_builder;
+/// Components written out as a line of text, and read back in
+pub mod ingredient_line;
+/// Editable stand-in for picking ingredients out of the store
+pub mod ingredient_selector_builder;
+/// The ingredient store, and selecting ingredients from it
+pub mod ingredient_store;
+/// Spreadsheet import and export of the ingredient inventory
+pub mod inventory_csv;
+/// schema.org Recipe JSON-LD import and export
+pub mod json_ld;
+/// Recipies as Markdown notes, and keeping a folder of them in sync
+pub mod markdown;
+/// Amounts and units
+pub mod measure;
+/// The HTML menu guests see
+#[cfg(feature = "server")]
+pub mod menu;
+/// Drinks guests have ordered
+pub mod order;
+/// The order queue
+pub mod order_store;
+/// Settings shared by every profile
+pub mod preferences;
+/// Separate bars kept side by side in one data dir
+pub mod profile;
+/// Recipies and the components they're made of
+pub mod recipie;
+/// Editable stand-in for a recipie
+pub mod recipie_builder;
+/// The recipie store
+pub mod recipie_store;
+/// A JSON HTTP API over the stores
+#[cfg(feature = "server")]
+pub mod server;
+/// Stores kept in a SQLite database
+pub mod sqlite_store;
+/// What every store has in common: loading, saving, backups and the trash
+pub mod store;
+#[cfg(test)]
+mod test_dir;
+/// Deleted entries waiting to be restored or purged
+pub mod trash;
+/// Noticing when store files change on disk
+pub mod watcher;
+/// The egui app
+#[cfg(feature = "gui")]
+pub mod widgets;
@@ -1,24 +1,117 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")] // hide console window on Windows in release
 #![allow(rustdoc::missing_crate_level_docs)] // it's an example
 
-mod builder;
-mod component_builder;
-mod ingredient;
-mod ingredient_builder;
-mod ingredient_selector_builder;
-mod ingredient_store;
-mod measure;
-mod recipie;
-mod recipie_builder;
-mod recipie_store;
-mod store;
-mod widgets;
+use std::{path::PathBuf, process::ExitCode};
 
+#[cfg(feature = "gui")]
 use ::image::load_from_memory;
+#[cfg(feature = "gui")]
 use eframe::egui;
+#[cfg(feature = "gui")]
 use egui::IconData;
 
-fn main() -> eframe::Result {
+#[cfg(feature = "cli")]
+use drink_solver::cli::{self, CliError, USAGE};
+#[cfg(feature = "gui")]
+use drink_solver::widgets::main_widget::MyApp;
+use drink_solver::{backend::Backend, profile, sqlite_store};
+
+#[cfg(not(feature = "cli"))]
+const USAGE: &str = "Usage: drink_solver [--backend json|sqlite] [--data-dir DIR]";
+
+struct Args {
+    backend: Backend,
+    data_dir: PathBuf,
+    // Anything else is a command for the CLI, see cli::USAGE
+    command: Vec<String>,
+}
+
+// --backend json|sqlite picks the storage, --data-dir overrides where it lives, and
+// --import-json copies the default profile's JSON stores into sqlite first
+fn parse_args() -> Result<Args, String> {
+    let mut backend = None;
+    let mut data_dir = None;
+    let mut import_json = false;
+    let mut command = vec![];
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--backend" => backend = Some(flag_value(&arg, args.next())?),
+            "--data-dir" => data_dir = Some(PathBuf::from(flag_value(&arg, args.next())?)),
+            "--import-json" => import_json = true,
+            _ => command.push(arg),
+        }
+    }
+    let mut backend = match backend {
+        Some(backend) => backend.parse::<Backend>()?,
+        None => Backend::default(),
+    };
+    let data_dir = data_dir.unwrap_or_else(profile::default_data_dir);
+    if import_json {
+        let dir = profile::profile_dir(&data_dir, profile::DEFAULT_PROFILE);
+        match sqlite_store::import_json(&dir) {
+            Ok(()) => backend = Backend::Sqlite,
+            Err(err) => eprintln!("Import failed: {}", err),
+        }
+    }
+    Ok(Args {
+        backend,
+        data_dir,
+        command,
+    })
+}
+
+fn flag_value(flag: &str, value: Option<String>) -> Result<String, String> {
+    value.ok_or_else(|| format!("{} needs a value", flag))
+}
+
+fn main() -> ExitCode {
+    let args = match parse_args() {
+        Ok(args) => args,
+        Err(message) => {
+            eprintln!("{}\n\n{}", message, USAGE);
+            return ExitCode::from(2);
+        }
+    };
+    if args.command.is_empty() {
+        return run_gui(args);
+    }
+    run_command(args)
+}
+
+#[cfg(feature = "cli")]
+fn run_command(args: Args) -> ExitCode {
+    match cli::run(
+        args.backend,
+        &args.data_dir,
+        &args.command,
+        &mut std::io::stdout(),
+    ) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("{}", err);
+            match err {
+                CliError::Usage(_) => ExitCode::from(2),
+                _ => ExitCode::FAILURE,
+            }
+        }
+    }
+}
+
+#[cfg(not(feature = "cli"))]
+fn run_command(_args: Args) -> ExitCode {
+    eprintln!("Built without commands, only the app works\n\n{}", USAGE);
+    ExitCode::from(2)
+}
+
+#[cfg(not(feature = "gui"))]
+fn run_gui(_args: Args) -> ExitCode {
+    eprintln!("Built without the app, only commands work\n\n{}", USAGE);
+    ExitCode::from(2)
+}
+
+#[cfg(feature = "gui")]
+fn run_gui(args: Args) -> ExitCode {
     let icon_bytes = include_bytes!("../icon.png");
     let (rgba, width, height) = {
         let img = load_from_memory(icon_bytes).expect("Missing icon.png!");
@@ -38,12 +131,19 @@ fn main() -> eframe::Result {
         })),
         ..Default::default()
     };
-    eframe::run_native(
+    let result = eframe::run_native(
         "DrinkSolver",
         options,
         Box::new(|cc| {
             cc.egui_ctx.set_theme(egui::Theme::Dark);
-            Ok(Box::<crate::widgets::main_widget::MyApp>::default())
+            Ok(Box::new(MyApp::new(args.backend, args.data_dir)))
         }),
-    )
+    );
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("{}", err);
+            ExitCode::FAILURE
+        }
+    }
 }
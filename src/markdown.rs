@@ -0,0 +1,473 @@
+use std::{
+    collections::HashMap,
+    fmt::Write as _,
+    path::{Path, PathBuf},
+};
+
+use uuid::Uuid;
+
+use crate::{ingredient_line, recipie::Recipie, recipie_store::RecipieStore, store::Store};
+
+/// Where a synced folder remembers what every file looked like after the last sync, so it
+/// can tell which side changed since
+const SYNC_STATE_NAME: &str = ".drink_solver_sync.json";
+
+/// A recipie as a Markdown note: front matter with its id, name and short description, then
+/// the description, components, numbered instructions and notes
+pub fn to_markdown(id: Uuid, recipie: &Recipie) -> String {
+    let mut text = String::new();
+    let _ = writeln!(text, "---");
+    let _ = writeln!(text, "id: {}", id);
+    let _ = writeln!(text, "name: {}", front_matter_value(&recipie.name));
+    if !recipie.short_description.is_empty() {
+        let _ = writeln!(
+            text,
+            "short_description: {}",
+            front_matter_value(&recipie.short_description)
+        );
+    }
+    let _ = writeln!(text, "---\n\n# {}\n", recipie.name);
+    if !recipie.description.is_empty() {
+        let _ = writeln!(text, "{}\n", recipie.description.trim());
+    }
+    if !recipie.components.is_empty() {
+        let _ = writeln!(text, "## Components\n");
+    }
+    for component in &recipie.components {
+        let _ = writeln!(text, "- {}", ingredient_line::to_line(component));
+    }
+    if !recipie.instructions.is_empty() {
+        let _ = writeln!(text, "\n## Instructions\n");
+    }
+    for (step, instruction) in recipie.instructions.iter().enumerate() {
+        // Lines after the first are indented, to keep them in the same list item
+        let instruction = instruction.trim().replace('\n', "\n   ");
+        let _ = writeln!(text, "{}. {}", step + 1, instruction);
+    }
+    if !recipie.notes.is_empty() {
+        let _ = writeln!(text, "\n## Notes\n\n{}", recipie.notes.trim());
+    }
+    text
+}
+
+/// Reads back what `to_markdown` writes, along with the id if there is one. It's lenient
+/// about hand-written notes: "Ingredients" does for "Components", any list style does, and
+/// sections it doesn't know end up in the notes.
+pub fn from_markdown(text: &str) -> (Option<Uuid>, Recipie) {
+    let mut recipie = Recipie::default();
+    let mut id = None;
+    let mut body = text;
+    if let Some(rest) = text.strip_prefix("---")
+        && let Some((front_matter, rest)) = rest.split_once("\n---")
+    {
+        for line in front_matter.lines() {
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+            let value = unquote(value.trim());
+            match key.trim() {
+                "id" => id = value.parse().ok(),
+                "name" => recipie.name = value,
+                "short_description" => recipie.short_description = value,
+                _ => {}
+            }
+        }
+        body = rest;
+    }
+
+    let mut section = Section::Description;
+    let mut description = vec![];
+    let mut notes = vec![];
+    for line in body.lines() {
+        if let Some(title) = line.strip_prefix("# ") {
+            if recipie.name.is_empty() {
+                recipie.name = title.trim().to_string();
+            }
+            continue;
+        }
+        if let Some(heading) = line.strip_prefix("## ") {
+            section = match heading.trim().to_lowercase().as_str() {
+                "components" | "ingredients" => Section::Components,
+                "instructions" | "method" | "steps" | "directions" => Section::Instructions,
+                "notes" => Section::Notes,
+                _ => {
+                    notes.push(line);
+                    Section::Notes
+                }
+            };
+            continue;
+        }
+        match section {
+            Section::Description => description.push(line),
+            Section::Components => {
+                if let Some(item) = list_item(line) {
+                    recipie.components.push(ingredient_line::parse(item));
+                }
+            }
+            Section::Instructions => match list_item(line) {
+                Some(step) => recipie.instructions.push(step.to_string()),
+                None if line.trim().is_empty() => {}
+                None => match recipie.instructions.last_mut() {
+                    Some(step) => {
+                        step.push('\n');
+                        step.push_str(line.trim());
+                    }
+                    None => recipie.instructions.push(line.trim().to_string()),
+                },
+            },
+            Section::Notes => notes.push(line),
+        }
+    }
+    recipie.description = description.join("\n").trim().to_string();
+    recipie.notes = notes.join("\n").trim().to_string();
+    (id, recipie)
+}
+
+enum Section {
+    Description,
+    Components,
+    Instructions,
+    Notes,
+}
+
+/// The text of a "- ", "* " or "1. " list item
+fn list_item(line: &str) -> Option<&str> {
+    let line = line.trim();
+    let item = match line.strip_prefix("- ").or(line.strip_prefix("* ")) {
+        Some(item) => item,
+        None => {
+            let (number, item) = line.split_once(". ")?;
+            if number.is_empty() || !number.chars().all(|c| c.is_ascii_digit()) {
+                return None;
+            }
+            item
+        }
+    };
+    Some(item.trim())
+}
+
+/// Quoted when it'd otherwise trip up a YAML reader
+fn front_matter_value(value: &str) -> String {
+    let value = value.replace('\n', " ");
+    let plain = !value.is_empty()
+        && !value.contains([':', '#', '"'])
+        && !value.starts_with(|c: char| c.is_whitespace() || "'[]{}&*!|>%@`-?,".contains(c))
+        && !value.ends_with(char::is_whitespace);
+    if plain {
+        return value;
+    }
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn unquote(value: &str) -> String {
+    match value.strip_prefix('"').and_then(|v| v.strip_suffix('"')) {
+        Some(quoted) => quoted.replace("\\\"", "\"").replace("\\\\", "\\"),
+        None => value.to_string(),
+    }
+}
+
+/// A file name for a recipie that isn't taken by anything else in `dir`
+pub fn file_name(dir: &Path, id: Uuid, recipie: &Recipie) -> PathBuf {
+    let stem: String = recipie
+        .name
+        .chars()
+        .filter(|c| c.is_alphanumeric() || " -_'()&".contains(*c))
+        .collect();
+    let stem = match stem.trim() {
+        "" => id.to_string(),
+        stem => stem.to_string(),
+    };
+    let path = dir.join(format!("{}.md", stem));
+    if !path.exists() {
+        return path;
+    }
+    let short_id = id.simple().to_string();
+    dir.join(format!("{} {}.md", stem, &short_id[..8]))
+}
+
+/// What a sync did. Names are recipie names.
+#[derive(Default, Debug)]
+pub struct SyncReport {
+    /// Files written from the store
+    pub written: Vec<String>,
+    /// Recipies added or updated from files
+    pub imported: Vec<String>,
+    /// Recipies moved to the trash because their file was deleted
+    pub trashed: Vec<String>,
+    /// Files deleted because their recipie was
+    pub removed: Vec<String>,
+    /// Changed on both sides since the last sync, so left alone
+    pub conflicts: Vec<String>,
+}
+
+impl SyncReport {
+    /// True if the store was changed
+    pub fn changed_store(&self) -> bool {
+        !self.imported.is_empty() || !self.trashed.is_empty()
+    }
+
+    /// e.g. "Wrote 2 files, imported 1 recipie, 1 conflict"
+    pub fn summary(&self) -> String {
+        let parts: Vec<String> = [
+            ("wrote", self.written.len(), "file", "files"),
+            ("imported", self.imported.len(), "recipie", "recipies"),
+            ("trashed", self.trashed.len(), "recipie", "recipies"),
+            ("removed", self.removed.len(), "file", "files"),
+        ]
+        .into_iter()
+        .filter(|(_, count, _, _)| *count > 0)
+        .map(|(what, count, one, many)| {
+            format!("{} {} {}", what, count, if count == 1 { one } else { many })
+        })
+        .chain(match self.conflicts.len() {
+            0 => None,
+            1 => Some(format!("1 conflict ({})", self.conflicts[0])),
+            n => Some(format!("{} conflicts ({})", n, self.conflicts.join(", "))),
+        })
+        .collect();
+        if parts.is_empty() {
+            return "Already in sync".to_string();
+        }
+        let summary = parts.join(", ");
+        let mut chars = summary.chars();
+        chars
+            .next()
+            .map(|first| first.to_uppercase().chain(chars).collect())
+            .unwrap_or_default()
+    }
+}
+
+/// A recipie file found in a synced folder
+struct NoteFile {
+    path: PathBuf,
+    text: String,
+    recipie: Recipie,
+}
+
+/// Brings the store and a folder of Markdown notes in line with each other, matching them by
+/// the id in each note's front matter. Whichever side changed since the last sync wins; if
+/// both did, neither is touched and the recipie is reported as a conflict. New notes without
+/// an id are added to the store and get one written into them. Deleting a note moves its
+/// recipie to the trash, and trashing a recipie deletes its note, unless it was edited since.
+pub fn sync(store: &mut RecipieStore, dir: &Path) -> std::io::Result<SyncReport> {
+    std::fs::create_dir_all(dir)?;
+    let state_path = dir.join(SYNC_STATE_NAME);
+    let mut last_synced: HashMap<Uuid, String> = match std::fs::read_to_string(&state_path) {
+        Ok(text) => serde_json::from_str(&text)?,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => HashMap::new(),
+        Err(err) => return Err(err),
+    };
+    let mut report = SyncReport::default();
+
+    let mut files: HashMap<Uuid, NoteFile> = HashMap::new();
+    let mut paths: Vec<PathBuf> = std::fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.extension().is_some_and(|e| e == "md"))
+        .collect();
+    paths.sort();
+    for path in paths {
+        let text = std::fs::read_to_string(&path)?;
+        let (id, recipie) = from_markdown(&text);
+        match id {
+            Some(id) if !files.contains_key(&id) => {
+                files.insert(
+                    id,
+                    NoteFile {
+                        path,
+                        text,
+                        recipie,
+                    },
+                );
+            }
+            // A new note, or a copy of another one
+            _ => {
+                let id = store.register(recipie.clone());
+                let text = to_markdown(id, &recipie);
+                std::fs::write(&path, &text)?;
+                last_synced.insert(id, text.clone());
+                report.imported.push(recipie.name.clone());
+                files.insert(
+                    id,
+                    NoteFile {
+                        path,
+                        text,
+                        recipie,
+                    },
+                );
+            }
+        }
+    }
+
+    for (id, recipie) in store.get_entries_with_ids() {
+        let ours = to_markdown(id, &recipie);
+        let last = last_synced.get(&id);
+        match files.remove(&id) {
+            Some(file) if file.text == ours => {
+                last_synced.insert(id, ours);
+            }
+            Some(file) => {
+                let file_changed = last != Some(&file.text);
+                let store_changed = last != Some(&ours);
+                if file_changed && !store_changed {
+                    // Written back out, so the note is in the same shape as everything else
+                    let text = to_markdown(id, &file.recipie);
+                    std::fs::write(&file.path, &text)?;
+                    last_synced.insert(id, text);
+                    report.imported.push(file.recipie.name.clone());
+                    store.insert(id, file.recipie);
+                } else if store_changed && !file_changed {
+                    std::fs::write(&file.path, &ours)?;
+                    last_synced.insert(id, ours);
+                    report.written.push(recipie.name);
+                } else {
+                    report.conflicts.push(recipie.name);
+                }
+            }
+            None if last.is_some() => {
+                let _ = store.move_to_trash(id);
+                last_synced.remove(&id);
+                report.trashed.push(recipie.name);
+            }
+            None => {
+                std::fs::write(file_name(dir, id, &recipie), &ours)?;
+                last_synced.insert(id, ours);
+                report.written.push(recipie.name);
+            }
+        }
+    }
+
+    // Notes whose recipie isn't in the store
+    for (id, file) in files {
+        if last_synced.get(&id) == Some(&file.text) {
+            std::fs::remove_file(&file.path)?;
+            last_synced.remove(&id);
+            report.removed.push(file.recipie.name);
+        } else {
+            last_synced.insert(id, file.text);
+            report.imported.push(file.recipie.name.clone());
+            store.insert(id, file.recipie);
+        }
+    }
+
+    std::fs::write(state_path, serde_json::to_string(&last_synced)?)?;
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        ingredient_store::IngredientSelector, measure::Measure, recipie::Component,
+        test_dir::TestDir,
+    };
+
+    fn sour() -> Recipie {
+        Recipie {
+            name: "Whiskey Sour: the \"proper\" one".into(),
+            description: "Shaken hard.\n\nWith egg white.".into(),
+            short_description: "# Tart".into(),
+            notes: "Try it with rye".into(),
+            components: vec![
+                Component {
+                    ingredient: IngredientSelector {
+                        name: Some("Bourbon".into()),
+                        ..Default::default()
+                    },
+                    amount: Measure::Oz(2.0),
+                },
+                Component {
+                    ingredient: IngredientSelector {
+                        name: Some("Lemon juice".into()),
+                        ..Default::default()
+                    },
+                    amount: Measure::Oz(0.75),
+                },
+            ],
+            instructions: vec!["Dry shake".into(), "Shake with ice\nthen strain".into()],
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let id = Uuid::new_v4();
+        let text = to_markdown(id, &sour());
+        assert!(text.contains("- 0.75 oz Lemon juice\n"));
+        assert!(text.contains("2. Shake with ice\n   then strain\n"));
+        let (read_id, read) = from_markdown(&text);
+        assert_eq!(read_id, Some(id));
+        assert_eq!(to_markdown(id, &read), text);
+        assert_eq!(read.name, sour().name);
+        assert_eq!(read.short_description, sour().short_description);
+        assert_eq!(read.description, sour().description);
+        assert_eq!(read.instructions, sour().instructions);
+        assert_eq!(read.notes, sour().notes);
+        assert_eq!(read.components[1].amount, Measure::Oz(0.75));
+    }
+
+    #[test]
+    fn test_hand_written_note() {
+        let (id, read) = from_markdown(
+            "# Gimlet\n\nSimple.\n\n## Ingredients\n* 2 oz gin\n* 3/4 oz lime cordial\n\n\
+             ## Method\n- Shake\n- Strain\n\n## History\nNavy issue.",
+        );
+        assert_eq!(id, None);
+        assert_eq!(read.name, "Gimlet");
+        assert_eq!(read.description, "Simple.");
+        assert_eq!(read.components.len(), 2);
+        assert_eq!(read.components[1].amount, Measure::Oz(0.75));
+        assert_eq!(read.instructions, ["Shake", "Strain"]);
+        assert_eq!(read.notes, "## History\nNavy issue.");
+    }
+
+    #[test]
+    fn test_sync() {
+        let dir = TestDir::new();
+        let mut store = RecipieStore::default();
+        let sour_id = store.register(sour());
+        let gimlet_id = store.register(Recipie {
+            name: "Gimlet".into(),
+            ..Default::default()
+        });
+
+        let report = sync(&mut store, &dir).unwrap();
+        assert_eq!(report.written.len(), 2);
+        let gimlet_file = dir.join("Gimlet.md");
+        assert!(gimlet_file.exists());
+        assert_eq!(sync(&mut store, &dir).unwrap().summary(), "Already in sync");
+
+        // A new note, an edited note, and a recipie changed in the app
+        std::fs::write(
+            dir.join("Daiquiri.md"),
+            "# Daiquiri\n\n## Components\n- 2 oz rum\n",
+        )
+        .unwrap();
+        let edited = std::fs::read_to_string(&gimlet_file).unwrap() + "\n## Notes\n\nCordial!\n";
+        std::fs::write(&gimlet_file, edited).unwrap();
+        store.get_entry_mut(sour_id).unwrap().notes = "Use rye".into();
+        let report = sync(&mut store, &dir).unwrap();
+        assert!(report.changed_store());
+        assert_eq!(report.imported.len(), 2);
+        assert_eq!(report.written.len(), 1);
+        assert_eq!(store.get_entry(gimlet_id).unwrap().notes, "Cordial!");
+        assert_eq!(store.get_entries().len(), 3);
+        let daiquiri = std::fs::read_to_string(dir.join("Daiquiri.md")).unwrap();
+        assert!(from_markdown(&daiquiri).0.is_some());
+
+        // Both sides changed
+        store.get_entry_mut(gimlet_id).unwrap().notes = "Lime".into();
+        let edited = std::fs::read_to_string(&gimlet_file)
+            .unwrap()
+            .replace("Cordial!", "Rose's");
+        std::fs::write(&gimlet_file, edited).unwrap();
+        let report = sync(&mut store, &dir).unwrap();
+        assert_eq!(report.conflicts, ["Gimlet"]);
+
+        // Deletions go both ways
+        std::fs::remove_file(&gimlet_file).unwrap();
+        store.move_to_trash(sour_id).unwrap();
+        let report = sync(&mut store, &dir).unwrap();
+        assert_eq!(report.trashed, ["Gimlet"]);
+        assert_eq!(report.removed, [sour().name]);
+        assert_eq!(store.get_entries().len(), 1);
+    }
+}
@@ -1,53 +1,670 @@
-use std::fmt;
+use std::{fmt, mem::discriminant, str::FromStr};
 
 use serde::{Deserialize, Serialize};
 use strum::EnumIter;
 
-#[derive(Serialize, Default, Deserialize, PartialEq, Clone, EnumIter)]
+/// The fractions a bar measures in, and the characters they're written with
+const FRACTIONS: [(char, f32); 9] = [
+    ('⅛', 0.125),
+    ('¼', 0.25),
+    ('⅓', 1.0 / 3.0),
+    ('⅜', 0.375),
+    ('½', 0.5),
+    ('⅝', 0.625),
+    ('⅔', 2.0 / 3.0),
+    ('¾', 0.75),
+    ('⅞', 0.875),
+];
+
+/// How close an amount has to be to a fraction to be written as one
+const FRACTION_TOLERANCE: f32 = 0.01;
+
+const ML_PER_OZ: f32 = 29.5735;
+const ML_PER_SHOT: f32 = 1.5 * ML_PER_OZ;
+/// A 1.75 liter bottle
+const ML_PER_HANDLE: f32 = 1750.0;
+/// A thirty-second of an ounce
+const ML_PER_DASH: f32 = ML_PER_OZ / 32.0;
+const ML_PER_TEASPOON: f32 = ML_PER_OZ / 6.0;
+const ML_PER_BARSPOON: f32 = 5.0;
+const ML_PER_DROP: f32 = 0.05;
+/// A sixteenth of a teaspoon
+const ML_PER_PINCH: f32 = ML_PER_TEASPOON / 16.0;
+const ML_PER_CUP: f32 = 8.0 * ML_PER_OZ;
+
+/// What a counted amount can be counted in, as in "2 lime wedges" or "3 mint leaves"
+const COUNT_UNITS: [&str; 18] = [
+    "wedge", "wedges", "wheel", "wheels", "leaf", "leaves", "sprig", "sprigs", "slice", "slices",
+    "twist", "twists", "cube", "cubes", "piece", "pieces", "whole", "each",
+];
+
+/// Which units amounts are converted to when asked to show them in the user's units
+#[derive(Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub enum UnitSystem {
+    /// Ounces
+    #[default]
+    Imperial,
+    /// Milliliters, or liters for whole bottles
+    Metric,
+}
+
+/// How amounts are written out
+#[derive(Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub enum AmountStyle {
+    /// 0.75 oz
+    #[default]
+    Decimal,
+    /// ¾ oz, falling back to decimals for anything that isn't a bar fraction
+    Fraction,
+}
+
+/// How much of an ingredient goes into a recipie
+#[derive(Serialize, Default, Deserialize, PartialEq, Clone, Debug, EnumIter)]
 pub enum Measure {
     Oz(f32),
     Ml(f32),
+    Cl(f32),
     Shot(f32),
     Liter(f32),
     Handle(f32),
+    Cup(f32),
     Dash(f32),
+    Drop(f32),
+    Pinch(f32),
     Teaspoon(f32),
+    Barspoon(f32),
     Tablespoon(f32),
     Part(f32),
+    /// Whole things, like 2 lime wedges or 1 egg white. The unit is what's counted, and can
+    /// be left empty when the ingredient says it all.
+    Count {
+        n: f32,
+        unit: String,
+    },
+    /// Anywhere from `min` to `max`, as in "0.5–0.75 oz". `max` is in `min`'s units.
+    Range {
+        min: Box<Measure>,
+        max: f32,
+    },
     #[default]
     Taste,
+    /// Top the glass up, e.g. with soda
+    Top,
+    /// Coat the glass and pour the rest away
+    Rinse,
+    /// Floated on top
+    Float,
 }
 
 impl fmt::Display for Measure {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Measure::Oz(m) => write!(f, "{} oz", m),
-            Measure::Ml(m) => write!(f, "{} ml", m),
-            Measure::Shot(m) => write!(f, "{} shots", m),
-            Measure::Liter(m) => write!(f, "{} liters", m),
-            Measure::Handle(m) => write!(f, "{} handles", m),
-            Measure::Dash(m) => write!(f, "{} dashes", m),
-            Measure::Teaspoon(m) => write!(f, "{} teaspoons", m),
-            Measure::Tablespoon(m) => write!(f, "{} tablespoons", m),
-            Measure::Part(m) => write!(f, "{} parts", m),
-            Measure::Taste => write!(f, "to taste"),
+        write!(f, "{}", self.display(AmountStyle::Decimal))
+    }
+}
+
+/// Why some text couldn't be read as a `Measure`
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseMeasureError(String);
+
+impl fmt::Display for ParseMeasureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "'{}' isn't an amount, try something like 1 1/2 oz",
+            self.0
+        )
+    }
+}
+
+impl std::error::Error for ParseMeasureError {}
+
+/// Reads amounts like "3/4 oz", "1 1/2 oz", "1½ oz", "30ml", "2 dashes", "a pinch", "2 wedges"
+/// and "to taste". A number on its own is a count of whatever follows it.
+impl FromStr for Measure {
+    type Err = ParseMeasureError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let text = s.trim().to_lowercase();
+        match text.as_str() {
+            "to taste" | "taste" => return Ok(Measure::Taste),
+            "to top" | "top" | "top with" | "top up" | "top up with" => return Ok(Measure::Top),
+            "to rinse" | "rinse" | "rinse with" => return Ok(Measure::Rinse),
+            "to float" | "float" => return Ok(Measure::Float),
+            _ => {}
         }
+        let error = || ParseMeasureError(s.trim().to_string());
+        // The low end of a range only has a number, the unit comes after the high end
+        if let Some((low, high)) = ["–", "-", " to "]
+            .iter()
+            .find_map(|separator| text.split_once(separator))
+        {
+            let low = parse_amount(low).ok_or_else(error)?;
+            let high: Measure = high.parse().map_err(|_| error())?;
+            return match high.amount() {
+                Some(max) if low <= max => Ok(Measure::range(high, low, max)),
+                _ => Err(error()),
+            };
+        }
+        let (amount, unit) = match text.strip_prefix("a ").or_else(|| text.strip_prefix("an ")) {
+            Some(unit) => (1.0, unit),
+            None => {
+                // The number can run straight into the unit, as in "30ml"
+                let unit_at = text
+                    .find(|c: char| !is_number_char(c) && !c.is_whitespace())
+                    .unwrap_or(text.len());
+                let (number, unit) = text.split_at(unit_at);
+                (parse_amount(number).ok_or_else(error)?, unit)
+            }
+        };
+        let unit = unit.trim().trim_end_matches('.');
+        let unit = unit
+            .strip_prefix("fl")
+            .map(|u| u.trim_start_matches('.').trim_start())
+            .filter(|u| u.starts_with("oz") || u.starts_with("ounce"))
+            .unwrap_or(unit);
+        let measure = match unit {
+            "oz" | "ounce" | "ounces" => Measure::Oz(amount),
+            "ml" | "milliliter" | "milliliters" | "millilitre" | "millilitres" => {
+                Measure::Ml(amount)
+            }
+            "cl" | "centiliter" | "centiliters" | "centilitre" | "centilitres" => {
+                Measure::Cl(amount)
+            }
+            "l" | "liter" | "liters" | "litre" | "litres" => Measure::Liter(amount),
+            "shot" | "shots" => Measure::Shot(amount),
+            "handle" | "handles" => Measure::Handle(amount),
+            "cup" | "cups" => Measure::Cup(amount),
+            "dash" | "dashes" => Measure::Dash(amount),
+            "drop" | "drops" => Measure::Drop(amount),
+            "pinch" | "pinches" => Measure::Pinch(amount),
+            "tsp" | "teaspoon" | "teaspoons" => Measure::Teaspoon(amount),
+            "barspoon" | "barspoons" | "bar spoon" | "bar spoons" | "bsp" => {
+                Measure::Barspoon(amount)
+            }
+            "tbsp" | "tablespoon" | "tablespoons" => Measure::Tablespoon(amount),
+            "part" | "parts" => Measure::Part(amount),
+            "" => Measure::Count {
+                n: amount,
+                unit: String::new(),
+            },
+            unit if COUNT_UNITS.contains(&unit) => Measure::Count {
+                n: amount,
+                unit: unit.to_string(),
+            },
+            _ => return Err(error()),
+        };
+        Ok(measure)
     }
 }
 
 impl Measure {
-    pub fn to_oz(self) -> Measure {
+    /// `unit` from `min` to `max`, e.g. `range(Measure::Oz(0.0), 0.5, 0.75)`
+    pub fn range(unit: Measure, min: f32, max: f32) -> Measure {
+        Measure::Range {
+            min: Box::new(unit.with_amount(min)),
+            max,
+        }
+    }
+
+    /// The same units with a different number
+    pub fn with_amount(&self, amount: f32) -> Measure {
+        let mut measure = self.clone();
+        if let Some(m) = measure.amount_mut() {
+            *m = amount;
+        }
+        measure
+    }
+
+    /// Written out in the given style, e.g. "1½ oz" or "1.5 oz"
+    pub fn display(&self, style: AmountStyle) -> String {
+        match self {
+            Measure::Taste | Measure::Top | Measure::Rinse | Measure::Float => {
+                self.unit().to_string()
+            }
+            Measure::Count { n, unit } if unit.is_empty() => format_amount(*n, style),
+            Measure::Range { min, max } => {
+                let low = format_amount(min.amount().unwrap_or_default(), style);
+                let high = min.with_amount(*max);
+                format!("{}–{}", low, high.display(style))
+            }
+            measure => format!(
+                "{} {}",
+                format_amount(measure.amount().unwrap_or_default(), style),
+                measure.unit()
+            ),
+        }
+    }
+
+    /// What the amount is measured in, as written after the number
+    pub fn unit(&self) -> &str {
+        match self {
+            Measure::Oz(_) => "oz",
+            Measure::Ml(_) => "ml",
+            Measure::Cl(_) => "cl",
+            Measure::Shot(_) => "shots",
+            Measure::Liter(_) => "liters",
+            Measure::Handle(_) => "handles",
+            Measure::Cup(_) => "cups",
+            Measure::Dash(_) => "dashes",
+            Measure::Drop(_) => "drops",
+            Measure::Pinch(_) => "pinches",
+            Measure::Teaspoon(_) => "teaspoons",
+            Measure::Barspoon(_) => "barspoons",
+            Measure::Tablespoon(_) => "tablespoons",
+            Measure::Part(_) => "parts",
+            Measure::Count { unit, .. } => unit,
+            Measure::Range { min, .. } => min.unit(),
+            Measure::Taste => "to taste",
+            Measure::Top => "to top",
+            Measure::Rinse => "to rinse",
+            Measure::Float => "to float",
+        }
+    }
+
+    /// The number in front of the unit, if there is one
+    pub fn amount(&self) -> Option<f32> {
+        self.clone().amount_mut().copied()
+    }
+
+    pub fn amount_mut(&mut self) -> Option<&mut f32> {
         match self {
-            Measure::Oz(m) => Measure::Oz(m),
-            Measure::Ml(m) => Measure::Oz(0.033814 * m),
-            Measure::Shot(m) => Measure::Oz(1.5 * m),
-            Measure::Liter(m) => Measure::Oz(33.814 * m),
-            Measure::Handle(m) => Measure::Liter(1.5 * m).to_oz(),
-            Measure::Dash(m) => Measure::Oz(0.1),
-            Measure::Teaspoon(m) => Measure::Ml(4.92892 * m).to_oz(),
-            Measure::Tablespoon(m) => Measure::Teaspoon(3.0 * m).to_oz(),
-            Measure::Part(m) => Measure::Oz(m),
-            Measure::Taste => Measure::Oz(0.1),
+            Measure::Oz(m)
+            | Measure::Ml(m)
+            | Measure::Cl(m)
+            | Measure::Shot(m)
+            | Measure::Liter(m)
+            | Measure::Handle(m)
+            | Measure::Cup(m)
+            | Measure::Dash(m)
+            | Measure::Drop(m)
+            | Measure::Pinch(m)
+            | Measure::Teaspoon(m)
+            | Measure::Barspoon(m)
+            | Measure::Tablespoon(m)
+            | Measure::Part(m)
+            | Measure::Count { n: m, .. } => Some(m),
+            Measure::Range { min, .. } => min.amount_mut(),
+            Measure::Taste | Measure::Top | Measure::Rinse | Measure::Float => None,
         }
     }
+
+    /// How much actually gets poured, in ml, taking the middle of a range. Parts only mean
+    /// something next to each other, counted things aren't poured, a rinse is poured away
+    /// and the rest are anyone's guess, so those come to 0.
+    pub fn to_ml(&self) -> f32 {
+        match self {
+            Measure::Oz(m) => m * ML_PER_OZ,
+            Measure::Ml(m) => *m,
+            Measure::Cl(m) => m * 10.0,
+            Measure::Shot(m) => m * ML_PER_SHOT,
+            Measure::Liter(m) => m * 1000.0,
+            Measure::Handle(m) => m * ML_PER_HANDLE,
+            Measure::Cup(m) => m * ML_PER_CUP,
+            Measure::Dash(m) => m * ML_PER_DASH,
+            Measure::Drop(m) => m * ML_PER_DROP,
+            Measure::Pinch(m) => m * ML_PER_PINCH,
+            Measure::Teaspoon(m) => m * ML_PER_TEASPOON,
+            Measure::Barspoon(m) => m * ML_PER_BARSPOON,
+            Measure::Tablespoon(m) => m * 3.0 * ML_PER_TEASPOON,
+            Measure::Range { min, max } => {
+                let middle = (min.amount().unwrap_or_default() + max) / 2.0;
+                min.with_amount(middle).to_ml()
+            }
+            Measure::Part(_)
+            | Measure::Count { .. }
+            | Measure::Taste
+            | Measure::Top
+            | Measure::Rinse
+            | Measure::Float => 0.0,
+        }
+    }
+
+    /// The same volume in ounces, 0 when there isn't one
+    pub fn to_oz(&self) -> f32 {
+        self.to_ml() / ML_PER_OZ
+    }
+
+    /// The same amount in the given units, rounded the way a bartender would measure it.
+    /// Dashes, drops, pinches and spoons read the same everywhere, and parts, counts and "to
+    /// taste" have no volume, so those are left alone.
+    pub fn in_units(&self, units: UnitSystem) -> Measure {
+        if let Measure::Range { min, max } = self {
+            let low = min.in_units(units);
+            let high = min.with_amount(*max).in_units(units);
+            // Both ends have to land in the same units, liters and ml can't share a range
+            return match high.amount() {
+                Some(max) if discriminant(&low) == discriminant(&high) => Measure::Range {
+                    min: Box::new(low),
+                    max,
+                },
+                _ => self.clone(),
+            };
+        }
+        let ml = match self {
+            Measure::Oz(_)
+            | Measure::Ml(_)
+            | Measure::Cl(_)
+            | Measure::Shot(_)
+            | Measure::Liter(_)
+            | Measure::Handle(_)
+            | Measure::Cup(_) => self.to_ml(),
+            _ => return self.clone(),
+        };
+        match units {
+            UnitSystem::Metric if ml >= 1000.0 => Measure::Liter((ml / 10.0).round() / 100.0),
+            UnitSystem::Metric => Measure::Ml(ml.round()),
+            UnitSystem::Imperial => {
+                let oz = ml / ML_PER_OZ;
+                let eighths = (oz * 8.0).round() / 8.0;
+                // Too little to measure in eighths, so it stays as it is
+                if eighths == 0.0 {
+                    Measure::Oz((oz * 100.0).round() / 100.0)
+                } else {
+                    Measure::Oz(eighths)
+                }
+            }
+        }
+    }
+}
+
+/// A number in the given style: "1½" as a fraction, "1.5" as a decimal
+pub fn format_amount(amount: f32, style: AmountStyle) -> String {
+    if style == AmountStyle::Decimal || amount < 0.0 {
+        return amount.to_string();
+    }
+    let whole = amount.trunc();
+    let part = amount - whole;
+    if part < FRACTION_TOLERANCE {
+        return whole.to_string();
+    }
+    if part > 1.0 - FRACTION_TOLERANCE {
+        return (whole + 1.0).to_string();
+    }
+    match FRACTIONS
+        .iter()
+        .find(|(_, value)| (part - value).abs() < FRACTION_TOLERANCE)
+    {
+        Some((c, _)) if whole == 0.0 => c.to_string(),
+        Some((c, _)) => format!("{}{}", whole, c),
+        None => amount.to_string(),
+    }
+}
+
+/// Reads a number the way recipies write them: "2", "0.75", "3/4", "1 1/2", "1½" or "¾"
+pub fn parse_amount(text: &str) -> Option<f32> {
+    let mut words = text.split_whitespace();
+    let mut amount = parse_number(words.next()?)?;
+    if let Some(fraction) = words.next() {
+        // Only a fraction can follow a whole number
+        if amount.fract() != 0.0 || !fraction.contains(|c| c == '/' || is_fraction_char(c)) {
+            return None;
+        }
+        amount += parse_number(fraction)?;
+    }
+    match words.next() {
+        Some(_) => None,
+        None => Some(amount),
+    }
+}
+
+fn parse_number(word: &str) -> Option<f32> {
+    if let Some(last) = word.chars().last()
+        && let Some((_, value)) = FRACTIONS.iter().find(|(c, _)| *c == last)
+    {
+        return match &word[..word.len() - last.len_utf8()] {
+            "" => Some(*value),
+            whole => whole.parse::<u32>().ok().map(|w| w as f32 + value),
+        };
+    }
+    match word.split_once('/') {
+        Some((top, bottom)) => {
+            let bottom: f32 = bottom.parse().ok()?;
+            (bottom != 0.0).then_some(top.parse::<f32>().ok()? / bottom)
+        }
+        None => word.parse().ok().filter(|n: &f32| n.is_finite()),
+    }
+}
+
+fn is_fraction_char(c: char) -> bool {
+    FRACTIONS.iter().any(|(f, _)| *f == c)
+}
+
+fn is_number_char(c: char) -> bool {
+    c.is_ascii_digit() || c == '.' || c == '/' || is_fraction_char(c)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_str() {
+        for (text, measure) in [
+            ("3/4 oz", Measure::Oz(0.75)),
+            ("1 1/2 oz", Measure::Oz(1.5)),
+            ("1½ oz", Measure::Oz(1.5)),
+            ("¾ fl. oz", Measure::Oz(0.75)),
+            ("30ml", Measure::Ml(30.0)),
+            ("3 cl", Measure::Cl(3.0)),
+            ("2 dashes", Measure::Dash(2.0)),
+            ("1 Barspoon", Measure::Barspoon(1.0)),
+            ("2 tsp", Measure::Teaspoon(2.0)),
+            ("3 drops", Measure::Drop(3.0)),
+            ("a pinch", Measure::Pinch(1.0)),
+            ("1 cup", Measure::Cup(1.0)),
+            (
+                "2 wedges",
+                Measure::Count {
+                    n: 2.0,
+                    unit: "wedges".into(),
+                },
+            ),
+            (
+                "1",
+                Measure::Count {
+                    n: 1.0,
+                    unit: String::new(),
+                },
+            ),
+            ("0.5 Tbsp.", Measure::Tablespoon(0.5)),
+            ("To taste", Measure::Taste),
+            ("0.5–0.75 oz", Measure::range(Measure::Oz(0.0), 0.5, 0.75)),
+            ("1/2 - 3/4 oz", Measure::range(Measure::Oz(0.0), 0.5, 0.75)),
+            (
+                "1 to 2 dashes",
+                Measure::range(Measure::Dash(0.0), 1.0, 2.0),
+            ),
+            ("Top with", Measure::Top),
+            ("rinse", Measure::Rinse),
+            ("to float", Measure::Float),
+        ] {
+            assert_eq!(text.parse::<Measure>(), Ok(measure), "{}", text);
+        }
+        for text in [
+            "",
+            "oz",
+            "a",
+            "2 splashes",
+            "1/0 oz",
+            "1.5 1/2 oz",
+            "1 2 oz",
+            "2-1 oz",
+            "1-2 splashes",
+            "1 to taste",
+        ] {
+            assert!(text.parse::<Measure>().is_err(), "{}", text);
+        }
+    }
+
+    fn assert_near(a: f32, b: f32) {
+        assert!((a - b).abs() < 0.01, "{} != {}", a, b);
+    }
+
+    #[test]
+    fn test_to_ml() {
+        assert_near(Measure::Oz(2.0).to_ml(), 59.147);
+        assert_near(Measure::Ml(30.0).to_ml(), 30.0);
+        assert_near(Measure::Shot(1.0).to_ml(), 44.36);
+        assert_near(Measure::Liter(0.75).to_ml(), 750.0);
+        assert_near(Measure::Handle(2.0).to_ml(), 3500.0);
+        assert_near(Measure::Dash(4.0).to_ml(), 3.697);
+        assert_near(Measure::Teaspoon(1.0).to_ml(), 4.929);
+        assert_near(Measure::Tablespoon(1.0).to_ml(), 14.787);
+        assert_near(Measure::Cl(4.0).to_ml(), 40.0);
+        assert_near(Measure::Cup(1.0).to_ml(), 236.588);
+        assert_near(Measure::Barspoon(1.0).to_ml(), 5.0);
+        assert_near(Measure::Drop(3.0).to_ml(), 0.15);
+        assert_near(Measure::Pinch(1.0).to_ml(), 0.308);
+        assert_eq!(
+            Measure::Count {
+                n: 1.0,
+                unit: String::new()
+            }
+            .to_ml(),
+            0.0
+        );
+        assert_eq!(Measure::Part(1.0).to_ml(), 0.0);
+        assert_eq!(Measure::Taste.to_ml(), 0.0);
+        // Ranges pour the middle
+        assert_near(Measure::range(Measure::Oz(0.0), 0.5, 1.0).to_ml(), 22.18);
+        assert_near(Measure::range(Measure::Ml(0.0), 10.0, 20.0).to_ml(), 15.0);
+        assert_eq!(Measure::range(Measure::Part(0.0), 1.0, 2.0).to_ml(), 0.0);
+        // Poured away, so none of it ends up in the drink
+        assert_eq!(Measure::Rinse.to_ml(), 0.0);
+        assert_eq!(Measure::Top.to_ml(), 0.0);
+        assert_eq!(Measure::Float.to_ml(), 0.0);
+    }
+
+    #[test]
+    fn test_to_oz() {
+        assert_near(Measure::Oz(0.75).to_oz(), 0.75);
+        assert_near(Measure::Ml(30.0).to_oz(), 1.014);
+        assert_near(Measure::Shot(2.0).to_oz(), 3.0);
+        assert_near(Measure::Liter(1.0).to_oz(), 33.814);
+        assert_near(Measure::Handle(1.0).to_oz(), 59.175);
+        // Every dash counts
+        assert_near(Measure::Dash(8.0).to_oz(), 0.25);
+        assert_near(Measure::Teaspoon(3.0).to_oz(), 0.5);
+        assert_near(Measure::Tablespoon(2.0).to_oz(), 1.0);
+        assert_near(Measure::Cup(0.5).to_oz(), 4.0);
+        assert_eq!(Measure::Part(2.0).to_oz(), 0.0);
+        assert_eq!(Measure::Taste.to_oz(), 0.0);
+    }
+
+    #[test]
+    fn test_in_units() {
+        for (measure, metric, imperial) in [
+            (Measure::Oz(1.5), Measure::Ml(44.0), Measure::Oz(1.5)),
+            (Measure::Ml(20.0), Measure::Ml(20.0), Measure::Oz(0.625)),
+            (Measure::Ml(1.0), Measure::Ml(1.0), Measure::Oz(0.03)),
+            (Measure::Shot(1.0), Measure::Ml(44.0), Measure::Oz(1.5)),
+            (
+                Measure::Handle(1.0),
+                Measure::Liter(1.75),
+                Measure::Oz(59.125),
+            ),
+            (Measure::Liter(0.5), Measure::Ml(500.0), Measure::Oz(16.875)),
+            (Measure::Cl(4.5), Measure::Ml(45.0), Measure::Oz(1.5)),
+            (Measure::Cup(1.0), Measure::Ml(237.0), Measure::Oz(8.0)),
+            (Measure::Dash(2.0), Measure::Dash(2.0), Measure::Dash(2.0)),
+            (Measure::Drop(3.0), Measure::Drop(3.0), Measure::Drop(3.0)),
+            (
+                Measure::Teaspoon(1.0),
+                Measure::Teaspoon(1.0),
+                Measure::Teaspoon(1.0),
+            ),
+            (Measure::Part(1.0), Measure::Part(1.0), Measure::Part(1.0)),
+            (Measure::Taste, Measure::Taste, Measure::Taste),
+            (Measure::Top, Measure::Top, Measure::Top),
+            (
+                Measure::range(Measure::Oz(0.0), 0.5, 0.75),
+                Measure::range(Measure::Ml(0.0), 15.0, 22.0),
+                Measure::range(Measure::Oz(0.0), 0.5, 0.75),
+            ),
+            (
+                Measure::range(Measure::Ml(0.0), 500.0, 1500.0),
+                Measure::range(Measure::Ml(0.0), 500.0, 1500.0),
+                Measure::range(Measure::Oz(0.0), 16.875, 50.75),
+            ),
+        ] {
+            assert_eq!(measure.in_units(UnitSystem::Metric), metric, "{}", measure);
+            assert_eq!(
+                measure.in_units(UnitSystem::Imperial),
+                imperial,
+                "{}",
+                measure
+            );
+        }
+    }
+
+    #[test]
+    fn test_display_round_trip() {
+        for measure in [
+            Measure::Oz(0.75),
+            Measure::Oz(1.5),
+            Measure::Oz(2.0),
+            Measure::Teaspoon(1.0 / 3.0),
+            Measure::Ml(12.5),
+            Measure::Dash(3.0),
+            Measure::Part(0.125),
+            Measure::Barspoon(0.5),
+            Measure::Cl(4.0),
+            Measure::Drop(3.0),
+            Measure::Pinch(1.0),
+            Measure::Cup(0.25),
+            Measure::Count {
+                n: 3.0,
+                unit: "leaves".into(),
+            },
+            Measure::Count {
+                n: 1.0,
+                unit: String::new(),
+            },
+            Measure::range(Measure::Oz(0.0), 0.5, 0.75),
+            Measure::range(
+                Measure::Count {
+                    n: 0.0,
+                    unit: String::new(),
+                },
+                1.0,
+                2.0,
+            ),
+            Measure::Taste,
+            Measure::Top,
+            Measure::Rinse,
+            Measure::Float,
+        ] {
+            for style in [AmountStyle::Decimal, AmountStyle::Fraction] {
+                let text = measure.display(style);
+                let read: Measure = text.parse().unwrap();
+                assert_eq!(read.display(style), text);
+            }
+        }
+        assert_eq!(Measure::Oz(0.75).display(AmountStyle::Fraction), "¾ oz");
+        assert_eq!(Measure::Oz(1.5).display(AmountStyle::Fraction), "1½ oz");
+        assert_eq!(Measure::Oz(1.5).to_string(), "1.5 oz");
+        assert_eq!(Measure::Ml(12.3).display(AmountStyle::Fraction), "12.3 ml");
+        assert_eq!(format_amount(1.999, AmountStyle::Fraction), "2");
+        let range = Measure::range(Measure::Oz(0.0), 0.5, 0.75);
+        assert_eq!(range.display(AmountStyle::Fraction), "½–¾ oz");
+        assert_eq!(range.to_string(), "0.5–0.75 oz");
+        assert_eq!(Measure::Top.to_string(), "to top");
+    }
+
+    #[test]
+    fn test_old_files_still_load() {
+        let read: Vec<Measure> =
+            serde_json::from_str(r#"[{"Oz":1.5},{"Teaspoon":1.0},"Taste"]"#).unwrap();
+        assert_eq!(
+            read,
+            [Measure::Oz(1.5), Measure::Teaspoon(1.0), Measure::Taste]
+        );
+        let count = Measure::Count {
+            n: 2.0,
+            unit: "wedges".into(),
+        };
+        let json = serde_json::to_string(&count).unwrap();
+        assert_eq!(json, r#"{"Count":{"n":2.0,"unit":"wedges"}}"#);
+        assert_eq!(serde_json::from_str::<Measure>(&json).unwrap(), count);
+    }
 }
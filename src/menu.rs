@@ -0,0 +1,342 @@
+use std::{
+    hash::{DefaultHasher, Hash, Hasher},
+    net::{SocketAddr, UdpSocket},
+    path::PathBuf,
+    sync::{
+        Arc,
+        mpsc::{Receiver, channel},
+    },
+    thread::JoinHandle,
+};
+
+use tiny_http::{Header, Method, Request, Response};
+
+use crate::{
+    backend::Backend,
+    ingredient_store::IngredientStore,
+    recipie::Component,
+    recipie_store::RecipieStore,
+    store::{Store, StoreError},
+};
+
+/// Where the guest menu listens unless told otherwise: every network this machine is on,
+/// so phones on the same wifi can reach it
+pub const DEFAULT_ADDRESS: &str = "0.0.0.0:8081";
+
+/// How often an open menu page checks whether it's out of date, in milliseconds
+const POLL_INTERVAL_MS: u32 = 5000;
+
+const STYLE: &str = "
+body { font-family: sans-serif; margin: 0 auto; max-width: 40em; padding: 1em;
+       background: #1b1b1b; color: #eee; }
+h1 { text-align: center; }
+section { border-bottom: 1px solid #444; padding: 0.5em 0; }
+h2 { margin: 0.2em 0; }
+p { margin: 0.2em 0; color: #bbb; }
+ul { margin: 0.4em 0; padding-left: 1.2em; }
+";
+
+/// Every recipie we can make right now, as a standalone HTML page for guests
+pub fn render(recipies: &RecipieStore, ingredients: &IngredientStore) -> String {
+    let makeable = recipies.makeable(ingredients);
+    let mut html = String::new();
+    html.push_str("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n");
+    html.push_str("<meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n");
+    html.push_str("<title>Menu</title>\n");
+    html.push_str(&format!("<style>{}</style>\n", STYLE));
+    html.push_str("</head>\n<body>\n<h1>Menu</h1>\n");
+    if makeable.is_empty() {
+        html.push_str("<p>Nothing on the menu right now, check back soon.</p>\n");
+    }
+    for (_, recipie) in &makeable {
+        html.push_str("<section>\n");
+        html.push_str(&format!("<h2>{}</h2>\n", escape(&recipie.name)));
+        if !recipie.short_description.is_empty() {
+            html.push_str(&format!("<p>{}</p>\n", escape(&recipie.short_description)));
+        }
+        html.push_str("<ul>\n");
+        for component in &recipie.components {
+            html.push_str(&format!(
+                "<li>{}</li>\n",
+                escape(&component_line(component, ingredients))
+            ));
+        }
+        html.push_str("</ul>\n</section>\n");
+    }
+    html.push_str(&format!(
+        "<script>{}</script>\n",
+        reload_script(&fingerprint(&html))
+    ));
+    html.push_str("</body>\n</html>\n");
+    html
+}
+
+/// Guests would rather read "Lime juice" than whatever the recipie's selector asks for, so
+/// this names the bottle that'll actually be poured
+fn component_line(component: &Component, ingredients: &IngredientStore) -> String {
+    let name = match ingredients.pick(&component.ingredient) {
+        Some((_, ingredient)) => ingredient.name,
+        None => component.ingredient.to_string(),
+    };
+    format!("{}, {}", name, component.amount)
+}
+
+// Reloads the page once the menu it shows is out of date. Polling a fingerprint rather
+// than reloading on a timer keeps guests' scroll position until something changes.
+fn reload_script(fingerprint: &str) -> String {
+    format!(
+        "setInterval(function () {{
+  fetch('/fingerprint').then(function (r) {{ return r.text(); }}).then(function (f) {{
+    if (f !== '{}') {{ location.reload(); }}
+  }}).catch(function () {{}});
+}}, {});",
+        fingerprint, POLL_INTERVAL_MS
+    )
+}
+
+fn fingerprint(html: &str) -> String {
+    let mut hasher = DefaultHasher::new();
+    html.hash(&mut hasher);
+    format!("{:x}", hasher.finish())
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+/// Serves the menu. It only ever reads the store files, so it runs alongside the app or
+/// the API without getting in their way, and picks up their changes as they're saved.
+pub struct MenuServer {
+    http: Arc<tiny_http::Server>,
+    backend: Backend,
+    config_dir: PathBuf,
+    // Loaded on the first request, and reloaded whenever their files change
+    stores: Option<(RecipieStore, IngredientStore)>,
+    // Rendered from the stores as they are now
+    page: Option<String>,
+}
+
+impl MenuServer {
+    /// `address` is e.g. "0.0.0.0:8081". Port 0 picks a free one, see `address`.
+    pub fn bind(backend: Backend, config_dir: PathBuf, address: &str) -> std::io::Result<Self> {
+        let http = tiny_http::Server::http(address).map_err(std::io::Error::other)?;
+        Ok(MenuServer {
+            http: Arc::new(http),
+            backend,
+            config_dir,
+            stores: None,
+            page: None,
+        })
+    }
+
+    pub fn address(&self) -> Option<SocketAddr> {
+        self.http.server_addr().to_ip()
+    }
+
+    /// Where guests can reach the menu from other devices, when it's listening on all of
+    /// this machine's addresses
+    pub fn lan_url(&self) -> Option<String> {
+        let address = self.address()?;
+        let ip = if address.ip().is_unspecified() {
+            lan_ip()?
+        } else {
+            address.ip()
+        };
+        Some(format!("http://{}", SocketAddr::new(ip, address.port())))
+    }
+
+    /// Serves requests one at a time until the server is stopped. Anything that goes wrong
+    /// along the way is passed to `on_error`.
+    pub fn run(&mut self, mut on_error: impl FnMut(String)) {
+        let http = self.http.clone();
+        for request in http.incoming_requests() {
+            self.respond(request, &mut on_error);
+        }
+    }
+
+    /// Serves from a background thread until the handle is dropped. `on_error` is called
+    /// from that thread whenever there's a new error waiting in `MenuHandle::errors`.
+    pub fn spawn(mut self, on_error: impl Fn() + Send + 'static) -> MenuHandle {
+        let http = self.http.clone();
+        let (sender, errors) = channel();
+        let thread = std::thread::spawn(move || {
+            self.run(|err| {
+                let _ = sender.send(err);
+                on_error();
+            })
+        });
+        MenuHandle {
+            http,
+            thread: Some(thread),
+            errors,
+        }
+    }
+
+    fn page(&mut self) -> Result<String, StoreError> {
+        let reloaded = match &mut self.stores {
+            Some((recipies, ingredients)) => {
+                reload_if_changed(recipies)? | reload_if_changed(ingredients)?
+            }
+            None => {
+                self.stores = Some((
+                    self.backend
+                        .load_recipies_read_only(self.config_dir.clone())?,
+                    self.backend
+                        .load_ingredients_read_only(self.config_dir.clone())?,
+                ));
+                true
+            }
+        };
+        if reloaded {
+            self.page = None;
+        }
+        let (recipies, ingredients) = self.stores.as_ref().expect("loaded above");
+        Ok(self
+            .page
+            .get_or_insert_with(|| render(recipies, ingredients))
+            .clone())
+    }
+
+    fn respond(&mut self, request: Request, on_error: &mut impl FnMut(String)) {
+        let path = request.url().split('?').next().unwrap_or_default();
+        let response = match (request.method(), path, self.page()) {
+            (Method::Get, "/", Ok(page)) => html_response(page),
+            (Method::Get, "/fingerprint", Ok(page)) => {
+                Response::from_string(fingerprint(&page_without_script(&page)))
+            }
+            (Method::Get, "/" | "/fingerprint", Err(err)) => {
+                on_error(format!("Couldn't render the menu: {}", err));
+                Response::from_string("The menu isn't available right now").with_status_code(503)
+            }
+            _ => Response::from_string("Not found").with_status_code(404),
+        };
+        if let Err(err) = request.respond(response) {
+            on_error(format!("Couldn't answer a request: {}", err));
+        }
+    }
+}
+
+// True if the store had to be reloaded
+fn reload_if_changed<T, S: Store<T>>(store: &mut S) -> Result<bool, StoreError> {
+    if !store.changed_on_disk() {
+        return Ok(false);
+    }
+    store.reload_from_disk()?;
+    Ok(true)
+}
+
+// The fingerprint covers the page as it was before its script was added
+fn page_without_script(page: &str) -> String {
+    match page.rfind("<script>") {
+        Some(i) => page[..i].to_string(),
+        None => page.to_string(),
+    }
+}
+
+fn html_response(page: String) -> Response<std::io::Cursor<Vec<u8>>> {
+    let content_type =
+        Header::from_bytes("Content-Type", "text/html; charset=utf-8").expect("a valid header");
+    Response::from_string(page).with_header(content_type)
+}
+
+// The address other machines on the LAN see us at. Connecting a UDP socket doesn't send
+// anything, it just makes the OS pick the interface it would route through.
+fn lan_ip() -> Option<std::net::IpAddr> {
+    let socket = UdpSocket::bind("0.0.0.0:0").ok()?;
+    socket.connect("192.0.2.1:80").ok()?;
+    Some(socket.local_addr().ok()?.ip())
+}
+
+/// A menu server running in the background
+pub struct MenuHandle {
+    http: Arc<tiny_http::Server>,
+    thread: Option<JoinHandle<()>>,
+    errors: Receiver<String>,
+}
+
+impl MenuHandle {
+    /// What went wrong since the last call
+    pub fn errors(&self) -> Vec<String> {
+        self.errors.try_iter().collect()
+    }
+}
+
+impl Drop for MenuHandle {
+    fn drop(&mut self) {
+        self.http.unblock();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        ingredient::Ingredient,
+        measure::Measure,
+        recipie::Recipie,
+        test_dir::{TestDir, component},
+    };
+
+    #[test]
+    fn test_render_only_makeable() {
+        let mut ingredients = IngredientStore::default();
+        ingredients.register(Ingredient {
+            name: "Gin".into(),
+            stock: 1,
+            ..Default::default()
+        });
+        let mut recipies = RecipieStore::default();
+        recipies.register(Recipie {
+            name: "Gin & <Tonic>".into(),
+            short_description: "Tall and fizzy".into(),
+            components: vec![component("Gin", Measure::Oz(2.0))],
+            ..Default::default()
+        });
+        recipies.register(Recipie {
+            name: "Daiquiri".into(),
+            components: vec![component("Rum", Measure::Oz(2.0))],
+            ..Default::default()
+        });
+        let page = render(&recipies, &ingredients);
+        assert!(page.contains("<h2>Gin &amp; &lt;Tonic&gt;</h2>"));
+        assert!(page.contains("<p>Tall and fizzy</p>"));
+        assert!(page.contains("<li>Gin, 2 oz</li>"));
+        assert!(!page.contains("Daiquiri"));
+        // The page knows its own fingerprint, to tell when it's stale
+        assert!(page.contains(&fingerprint(&page_without_script(&page))));
+    }
+
+    #[test]
+    fn test_page_follows_store_changes() {
+        let dir = TestDir::new();
+        let mut ingredients = IngredientStore::from_config(dir.to_path_buf()).unwrap();
+        let mut recipies = RecipieStore::from_config(dir.to_path_buf()).unwrap();
+        let gin = ingredients.register(Ingredient {
+            name: "Gin".into(),
+            ..Default::default()
+        });
+        recipies.register(Recipie {
+            name: "Martini".into(),
+            components: vec![component("Gin", Measure::Oz(2.0))],
+            ..Default::default()
+        });
+        ingredients.save().unwrap();
+        recipies.save().unwrap();
+
+        let mut server = MenuServer::bind(Backend::Json, dir.to_path_buf(), "127.0.0.1:0").unwrap();
+        assert!(!server.page().unwrap().contains("Martini"));
+        let cached = server.page.clone();
+        assert_eq!(server.page().ok(), cached);
+
+        ingredients.get_entry_mut(gin).unwrap().stock = 1;
+        ingredients.save().unwrap();
+        assert!(server.page().unwrap().contains("Martini"));
+    }
+}
@@ -0,0 +1,246 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{
+    history::History, ingredient_store::IngredientStore, recipie::Recipie, store::StoreError,
+};
+
+/// A drink a guest asked for
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Order {
+    pub recipie: Uuid,
+    /// Kept so the order still reads right if the recipie's renamed or deleted later
+    pub recipie_name: String,
+    /// Whoever ordered it. May be empty.
+    pub guest: String,
+    /// Unix seconds
+    pub placed_at: i64,
+    /// Unix seconds, once it's been made
+    pub done_at: Option<i64>,
+    /// What went into it, filled in once it's been made
+    pub poured: Vec<Pour>,
+}
+
+/// How much of one ingredient went into a drink
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Pour {
+    pub ingredient: String,
+    pub ml: f32,
+    /// The ingredient has no bottle size, so its stock was left alone
+    #[serde(default)]
+    pub untracked: bool,
+}
+
+impl Order {
+    pub fn new(recipie: Uuid, recipie_name: &str, guest: &str) -> Order {
+        Order {
+            recipie,
+            recipie_name: recipie_name.to_string(),
+            guest: guest.trim().to_string(),
+            placed_at: chrono::Utc::now().timestamp(),
+            ..Default::default()
+        }
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.done_at.is_some()
+    }
+}
+
+/// Takes one of `recipie` out of stock, picking bottles the same way the guest menu names
+/// them. Components we've run out of, and ones without a volume like parts, "to taste" or
+/// a rinse, are skipped. Ranges pour their middle. Stock changes go through `history`, so
+/// they can be undone like any other edit.
+pub fn pour(
+    recipie: &Recipie,
+    ingredients: &mut IngredientStore,
+    history: &mut History,
+) -> Result<Vec<Pour>, StoreError> {
+    let mut poured = vec![];
+    for component in &recipie.components {
+        let ml = component.amount.to_ml();
+        if ml <= 0.0 {
+            continue;
+        }
+        let Some((id, mut ingredient)) = ingredients.pick(&component.ingredient) else {
+            continue;
+        };
+        let tracked = ingredient.pour(ml);
+        poured.push(Pour {
+            ingredient: ingredient.name.clone(),
+            ml,
+            untracked: !tracked,
+        });
+        if tracked {
+            history.update(ingredients, id, ingredient)?;
+        }
+    }
+    Ok(poured)
+}
+
+/// How a night went, from the orders that were made
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Summary {
+    pub served: usize,
+    /// Still in the queue
+    pub waiting: usize,
+    /// Drinks by how many were made, most first
+    pub drinks: Vec<(String, usize)>,
+    /// Guests by how many drinks they had, most first. Orders without a name aren't counted.
+    pub guests: Vec<(String, usize)>,
+    /// Ingredients by ml poured, most first
+    pub poured: Vec<(String, f32)>,
+    /// Unix seconds
+    pub first_order: Option<i64>,
+    /// Unix seconds
+    pub last_served: Option<i64>,
+}
+
+impl Summary {
+    pub fn of(orders: &[Order]) -> Summary {
+        let mut drinks: HashMap<&str, usize> = HashMap::new();
+        let mut guests: HashMap<&str, usize> = HashMap::new();
+        let mut poured: HashMap<&str, f32> = HashMap::new();
+        let mut summary = Summary::default();
+        for order in orders {
+            summary.first_order = Some(match summary.first_order {
+                Some(first) => first.min(order.placed_at),
+                None => order.placed_at,
+            });
+            let Some(done_at) = order.done_at else {
+                summary.waiting += 1;
+                continue;
+            };
+            summary.served += 1;
+            summary.last_served = summary.last_served.max(Some(done_at));
+            *drinks.entry(&order.recipie_name).or_default() += 1;
+            if !order.guest.is_empty() {
+                *guests.entry(&order.guest).or_default() += 1;
+            }
+            for pour in &order.poured {
+                *poured.entry(&pour.ingredient).or_default() += pour.ml;
+            }
+        }
+        summary.drinks = most_first(drinks);
+        summary.guests = most_first(guests);
+        let mut poured: Vec<(String, f32)> = poured
+            .into_iter()
+            .map(|(k, v)| (k.to_string(), v))
+            .collect();
+        poured.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        summary.poured = poured;
+        summary
+    }
+}
+
+fn most_first(counts: HashMap<&str, usize>) -> Vec<(String, usize)> {
+    let mut counts: Vec<(String, usize)> = counts
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v))
+        .collect();
+    counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    counts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        ingredient::Ingredient, measure::Measure, recipie_store::RecipieStore, store::Store,
+        test_dir::component,
+    };
+
+    #[test]
+    fn test_pour_takes_from_stock() {
+        let mut ingredients = IngredientStore::default();
+        let gin = ingredients.register(Ingredient {
+            name: "Gin".into(),
+            stock: 1,
+            bottle_ml: Some(100),
+            ..Default::default()
+        });
+        let syrup = ingredients.register(Ingredient {
+            name: "Syrup".into(),
+            stock: 1,
+            ..Default::default()
+        });
+        let gimlet = Recipie {
+            name: "Gimlet".into(),
+            components: vec![
+                component("Gin", Measure::Ml(60.0)),
+                component("Lime juice", Measure::Ml(20.0)),
+                component("Syrup", Measure::Ml(10.0)),
+                component("Gin", Measure::Taste),
+                component("Gin", Measure::Rinse),
+            ],
+            ..Default::default()
+        };
+        let mut history = History::default();
+        let poured = pour(&gimlet, &mut ingredients, &mut history).unwrap();
+        // No lime juice to pour, nobody can measure "to taste" and a rinse is poured away
+        assert_eq!(
+            poured,
+            vec![
+                Pour {
+                    ingredient: "Gin".into(),
+                    ml: 60.0,
+                    untracked: false,
+                },
+                Pour {
+                    ingredient: "Syrup".into(),
+                    ml: 10.0,
+                    untracked: true,
+                }
+            ]
+        );
+        assert_eq!(
+            ingredients.get_entry(gin).unwrap().left_in_bottle_ml(),
+            Some(40)
+        );
+        // Without a bottle size there's nothing to take out
+        assert_eq!(ingredients.get_entry(syrup).unwrap().stock, 1);
+        pour(&gimlet, &mut ingredients, &mut history).unwrap();
+        assert_eq!(ingredients.get_entry(gin).unwrap().stock, 0);
+
+        let mut recipies = RecipieStore::default();
+        while history.undo(&mut recipies, &mut ingredients).unwrap() {}
+        assert_eq!(ingredients.get_entry(gin).unwrap().stock, 1);
+        assert_eq!(ingredients.get_entry(gin).unwrap().poured_ml, 0);
+    }
+
+    #[test]
+    fn test_summary() {
+        let order = |name: &str, guest: &str, done_at: Option<i64>| Order {
+            recipie_name: name.into(),
+            guest: guest.into(),
+            placed_at: 10,
+            done_at,
+            poured: vec![Pour {
+                ingredient: "Gin".into(),
+                ml: 50.0,
+                untracked: false,
+            }],
+            ..Default::default()
+        };
+        let summary = Summary::of(&[
+            order("Martini", "Ann", Some(20)),
+            order("Gimlet", "Ann", Some(40)),
+            order("Martini", "", Some(30)),
+            order("Martini", "Bo", None),
+        ]);
+        assert_eq!(summary.served, 3);
+        assert_eq!(summary.waiting, 1);
+        assert_eq!(
+            summary.drinks,
+            vec![("Martini".into(), 2), ("Gimlet".into(), 1)]
+        );
+        assert_eq!(summary.guests, vec![("Ann".into(), 2)]);
+        assert_eq!(summary.poured, vec![("Gin".into(), 150.0)]);
+        assert_eq!(
+            (summary.first_order, summary.last_served),
+            (Some(10), Some(40))
+        );
+    }
+}
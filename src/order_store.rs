@@ -0,0 +1,234 @@
+use std::{collections::HashMap, path::PathBuf};
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{
+    history::History,
+    ingredient_store::IngredientStore,
+    order::{Order, Summary, pour},
+    recipie_store::RecipieStore,
+    store::{DirtyState, DiskState, Migration, Store, StoreError},
+    trash::TrashBin,
+};
+
+/// Tonight's drink orders, waiting and made. Cancelled orders go to the trash, so a
+/// mis-tap can be undone.
+#[derive(Serialize, Deserialize, Default)]
+pub struct OrderStore {
+    orders: HashMap<Uuid, Order>,
+    config: Option<PathBuf>,
+    #[serde(default)]
+    trash: TrashBin<Order>,
+    #[serde(skip)]
+    dirty: DirtyState,
+    #[serde(skip)]
+    disk: DiskState,
+}
+
+impl OrderStore {
+    /// Orders still to be made, oldest first
+    pub fn queue(&self) -> Vec<(Uuid, Order)> {
+        let mut queue: Vec<(Uuid, Order)> = self
+            .get_entries_with_ids()
+            .into_iter()
+            .filter(|(_, o)| !o.is_done())
+            .collect();
+        queue.sort_by_key(|(_, o)| o.placed_at);
+        queue
+    }
+
+    /// Orders that have been made, most recent first
+    pub fn finished(&self) -> Vec<(Uuid, Order)> {
+        let mut finished: Vec<(Uuid, Order)> = self
+            .get_entries_with_ids()
+            .into_iter()
+            .filter(|(_, o)| o.is_done())
+            .collect();
+        finished.sort_by_key(|(_, o)| std::cmp::Reverse(o.done_at));
+        finished
+    }
+
+    /// Marks an order made, taking what went into it out of `ingredients`. If its recipie
+    /// has been deleted since, there's no knowing what that was, so stock is left alone.
+    pub fn complete(
+        &mut self,
+        id: Uuid,
+        recipies: &RecipieStore,
+        ingredients: &mut IngredientStore,
+        history: &mut History,
+    ) -> Result<(), StoreError> {
+        let mut order = self.get_entry(id).ok_or(StoreError::UnknownId(id))?;
+        if order.is_done() {
+            return Ok(());
+        }
+        if let Some(recipie) = recipies.get_entry(order.recipie) {
+            order.poured = pour(&recipie, ingredients, history)?;
+        }
+        order.done_at = Some(chrono::Utc::now().timestamp());
+        self.insert(id, order);
+        Ok(())
+    }
+
+    pub fn summary(&self) -> Summary {
+        Summary::of(&self.get_entries())
+    }
+
+    /// Clears out everything that's been made or cancelled, ready for the next night.
+    /// Anything still waiting stays in the queue.
+    pub fn end_night(&mut self) {
+        for (id, _) in self.finished() {
+            let _ = self.deregister(id);
+        }
+        for (id, _) in self.trash.entries() {
+            let _ = self.purge(id);
+        }
+    }
+}
+
+impl Store<Order> for OrderStore {
+    const SCHEMA_VERSION: u32 = 1;
+
+    fn get_json_name() -> PathBuf {
+        "orders.json".into()
+    }
+
+    fn migrations() -> Vec<Migration> {
+        vec![]
+    }
+
+    fn set_config_path(&mut self, path: PathBuf) {
+        self.config = Some(path)
+    }
+
+    fn get_config_path(&self) -> Option<PathBuf> {
+        self.config.clone()
+    }
+
+    fn dirty(&self) -> &DirtyState {
+        &self.dirty
+    }
+
+    fn dirty_mut(&mut self) -> &mut DirtyState {
+        &mut self.dirty
+    }
+
+    fn disk(&self) -> &DiskState {
+        &self.disk
+    }
+
+    fn disk_mut(&mut self) -> &mut DiskState {
+        &mut self.disk
+    }
+
+    fn trash_bin(&self) -> &TrashBin<Order> {
+        &self.trash
+    }
+
+    fn trash_bin_mut(&mut self) -> &mut TrashBin<Order> {
+        &mut self.trash
+    }
+
+    fn register(&mut self, entry: Order) -> Uuid {
+        let id = Uuid::new_v4();
+        self.insert(id, entry);
+        id
+    }
+
+    fn insert(&mut self, id: Uuid, entry: Order) {
+        self.dirty.touch_id(id);
+        self.orders.insert(id, entry);
+    }
+
+    fn deregister(&mut self, id: Uuid) -> Result<(), StoreError> {
+        self.orders.remove(&id).ok_or(StoreError::UnknownId(id))?;
+        self.dirty.touch_id(id);
+        Ok(())
+    }
+
+    fn get_entries(&self) -> Vec<Order> {
+        self.orders.values().cloned().collect()
+    }
+
+    fn get_entries_mut(&mut self) -> Vec<&mut Order> {
+        self.dirty.touch();
+        self.orders.values_mut().collect()
+    }
+
+    fn get_entries_with_ids(&self) -> Vec<(Uuid, Order)> {
+        self.orders.iter().map(|(id, o)| (*id, o.clone())).collect()
+    }
+
+    fn get_entry(&self, id: Uuid) -> Option<Order> {
+        self.orders.get(&id).cloned()
+    }
+
+    fn get_entry_mut(&mut self, id: Uuid) -> Option<&mut Order> {
+        let entry = self.orders.get_mut(&id);
+        if entry.is_some() {
+            self.dirty.touch_id(id);
+        }
+        entry
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        ingredient::Ingredient,
+        ingredient_store::IngredientSelector,
+        measure::Measure,
+        recipie::{Component, Recipie},
+        test_dir::TestDir,
+    };
+
+    #[test]
+    fn test_queue_survives_restart() {
+        let dir = TestDir::new();
+        let mut recipies = RecipieStore::default();
+        let martini = recipies.register(Recipie {
+            name: "Martini".into(),
+            components: vec![Component {
+                ingredient: IngredientSelector {
+                    name: Some("Gin".into()),
+                    ..Default::default()
+                },
+                amount: Measure::Ml(60.0),
+            }],
+            ..Default::default()
+        });
+        let mut ingredients = IngredientStore::default();
+        let gin = ingredients.register(Ingredient {
+            name: "Gin".into(),
+            stock: 2,
+            bottle_ml: Some(700),
+            ..Default::default()
+        });
+
+        let mut orders = OrderStore::from_config(dir.to_path_buf()).unwrap();
+        let first = orders.register(Order::new(martini, "Martini", "Ann"));
+        let mut second = Order::new(martini, "Martini", "Bo");
+        second.placed_at += 1;
+        let second = orders.register(second);
+        assert!(orders.save().is_ok());
+        drop(orders);
+
+        let mut orders = OrderStore::from_config(dir.to_path_buf()).unwrap();
+        let queue: Vec<Uuid> = orders.queue().into_iter().map(|(id, _)| id).collect();
+        assert_eq!(queue, vec![first, second]);
+        orders
+            .complete(first, &recipies, &mut ingredients, &mut History::default())
+            .unwrap();
+        assert_eq!(orders.queue().len(), 1);
+        assert_eq!(orders.finished()[0].0, first);
+        assert_eq!(ingredients.get_entry(gin).unwrap().poured_ml, 60);
+        assert_eq!(orders.summary().served, 1);
+
+        orders.move_to_trash(second).unwrap();
+        orders.end_night();
+        assert!(orders.queue().is_empty());
+        assert!(orders.trash_bin().is_empty());
+        assert!(orders.finished().is_empty());
+    }
+}
@@ -0,0 +1,136 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::measure::{AmountStyle, UnitSystem};
+
+/// Kept at the root of the data dir, shared by every profile
+const PREFERENCES_NAME: &str = "preferences.json";
+
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// Kiosk PINs are all digits, at least this many
+pub const MIN_PIN_LENGTH: usize = 4;
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(default)]
+pub struct Preferences {
+    /// Trashed entries are purged once they're this many days old. None keeps them forever.
+    pub trash_retention_days: Option<u32>,
+    /// Set while the app is locked to the kiosk screen, and needed to unlock it. It keeps
+    /// guests out of the editing screens, it's not meant as a password.
+    pub kiosk_pin: Option<String>,
+    /// Folders of Markdown recipies kept in sync with each profile, by profile name
+    pub recipie_folders: BTreeMap<String, PathBuf>,
+    /// Whether amounts read 0.75 oz or ¾ oz
+    pub amount_style: AmountStyle,
+    /// What recipies are converted to when showing them in the user's units
+    pub unit_system: UnitSystem,
+}
+
+impl Default for Preferences {
+    fn default() -> Self {
+        Preferences {
+            trash_retention_days: Some(30),
+            kiosk_pin: None,
+            recipie_folders: BTreeMap::new(),
+            amount_style: AmountStyle::Decimal,
+            unit_system: UnitSystem::Imperial,
+        }
+    }
+}
+
+impl Preferences {
+    /// The defaults when there's no preferences file yet. A file that can't be read or
+    /// parsed is an error, so the caller can tell the user before falling back.
+    pub fn load(data_dir: &Path) -> std::io::Result<Preferences> {
+        let text = match std::fs::read_to_string(preferences_path(data_dir)) {
+            Ok(text) => text,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                return Ok(Preferences::default());
+            }
+            Err(err) => return Err(err),
+        };
+        Ok(serde_json::from_str(&text)?)
+    }
+
+    pub fn save(&self, data_dir: &Path) -> std::io::Result<()> {
+        std::fs::create_dir_all(data_dir)?;
+        let text = serde_json::to_string_pretty(self)?;
+        std::fs::write(preferences_path(data_dir), text)
+    }
+
+    pub fn trash_retention(&self) -> Option<Duration> {
+        self.trash_retention_days
+            .map(|days| Duration::from_secs(days as u64 * SECONDS_PER_DAY))
+    }
+
+    pub fn is_kiosk_locked(&self) -> bool {
+        self.kiosk_pin.is_some()
+    }
+
+    /// True if `pin` unlocks the kiosk, or it isn't locked
+    pub fn unlocks(&self, pin: &str) -> bool {
+        match &self.kiosk_pin {
+            Some(kiosk_pin) => kiosk_pin == pin.trim(),
+            None => true,
+        }
+    }
+}
+
+pub fn is_valid_pin(pin: &str) -> bool {
+    pin.len() >= MIN_PIN_LENGTH && pin.chars().all(|c| c.is_ascii_digit())
+}
+
+fn preferences_path(data_dir: &Path) -> PathBuf {
+    data_dir.join(PREFERENCES_NAME)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_dir::TestDir;
+
+    #[test]
+    fn test_preferences_round_trip() {
+        let data_dir = TestDir::new();
+        assert_eq!(
+            Preferences::load(&data_dir).unwrap(),
+            Preferences::default()
+        );
+        let preferences = Preferences {
+            trash_retention_days: None,
+            kiosk_pin: Some("1234".into()),
+            recipie_folders: [("default".to_string(), PathBuf::from("/notes/bar"))].into(),
+            amount_style: AmountStyle::Fraction,
+            unit_system: UnitSystem::Metric,
+        };
+        preferences.save(&data_dir).unwrap();
+        assert_eq!(Preferences::load(&data_dir).unwrap(), preferences);
+        // Settings missing from the file keep their defaults
+        std::fs::write(data_dir.join(PREFERENCES_NAME), "{}").unwrap();
+        assert_eq!(
+            Preferences::load(&data_dir).unwrap(),
+            Preferences::default()
+        );
+        // A broken file is reported rather than quietly replaced by the defaults
+        std::fs::write(data_dir.join(PREFERENCES_NAME), "{").unwrap();
+        assert!(Preferences::load(&data_dir).is_err());
+    }
+
+    #[test]
+    fn test_kiosk_pin() {
+        assert!(is_valid_pin("0451"));
+        assert!(!is_valid_pin("123"));
+        assert!(!is_valid_pin("12a4"));
+        let mut preferences = Preferences::default();
+        assert!(preferences.unlocks(""));
+        preferences.kiosk_pin = Some("0451".into());
+        assert!(preferences.unlocks("0451 "));
+        assert!(!preferences.unlocks("1234"));
+    }
+}
@@ -0,0 +1,90 @@
+use std::path::{Path, PathBuf};
+
+/// Overrides where the data lives, below the --data-dir flag and above the platform default
+pub const DATA_DIR_ENV: &str = "DRINK_SOLVER_DATA";
+
+/// The default profile lives at the root of the data dir, so data from before profiles
+/// existed is picked up as-is. Every other profile gets its own folder under profiles/.
+pub const DEFAULT_PROFILE: &str = "default";
+
+const PROFILES_DIR: &str = "profiles";
+
+/// Where the stores live when nobody says otherwise
+pub fn default_data_dir() -> PathBuf {
+    match std::env::var_os(DATA_DIR_ENV) {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => dirs::config_local_dir()
+            .map(|dir| dir.join("drink_solver"))
+            .unwrap_or(PathBuf::from("./")),
+    }
+}
+
+/// Directory a profile's stores are kept in
+pub fn profile_dir(data_dir: &Path, profile: &str) -> PathBuf {
+    if profile == DEFAULT_PROFILE {
+        data_dir.to_path_buf()
+    } else {
+        data_dir.join(PROFILES_DIR).join(profile)
+    }
+}
+
+/// Every profile in the data dir, the default first and the rest alphabetically
+pub fn list_profiles(data_dir: &Path) -> Vec<String> {
+    let mut profiles: Vec<String> = match std::fs::read_dir(data_dir.join(PROFILES_DIR)) {
+        Ok(entries) => entries
+            .filter_map(|e| e.ok())
+            .filter(|e| e.path().is_dir())
+            .map(|e| e.file_name().to_string_lossy().to_string())
+            .filter(|name| name != DEFAULT_PROFILE)
+            .collect(),
+        Err(_) => vec![],
+    };
+    profiles.sort_by_key(|p| p.to_lowercase());
+    profiles.insert(0, DEFAULT_PROFILE.to_string());
+    profiles
+}
+
+/// Profile names become folder names, so keep them to something every filesystem accepts
+pub fn is_valid_profile_name(name: &str) -> bool {
+    let name = name.trim();
+    !name.is_empty()
+        && name != "."
+        && name != ".."
+        && !name
+            .chars()
+            .any(|c| matches!(c, '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|'))
+}
+
+pub fn create_profile(data_dir: &Path, profile: &str) -> std::io::Result<PathBuf> {
+    if !is_valid_profile_name(profile) {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!("'{}' is not a valid profile name", profile),
+        ));
+    }
+    let dir = profile_dir(data_dir, profile.trim());
+    std::fs::create_dir_all(&dir)?;
+    Ok(dir)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_dir::TestDir;
+
+    #[test]
+    fn test_profiles() {
+        let data_dir = TestDir::new();
+        assert_eq!(list_profiles(&data_dir), vec![DEFAULT_PROFILE]);
+        assert_eq!(profile_dir(&data_dir, DEFAULT_PROFILE), *data_dir);
+        let work = create_profile(&data_dir, "work bar").unwrap();
+        create_profile(&data_dir, "Home bar").unwrap();
+        assert!(work.is_dir());
+        assert_eq!(
+            list_profiles(&data_dir),
+            vec![DEFAULT_PROFILE, "Home bar", "work bar"]
+        );
+        assert!(create_profile(&data_dir, "../escape").is_err());
+        assert!(create_profile(&data_dir, " ").is_err());
+    }
+}
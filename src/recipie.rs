@@ -1,18 +1,17 @@
-use std::{cell::RefCell, rc::Rc};
-
 use crate::{
     ingredient_store::{IngredientSelector, IngredientStore},
     measure::Measure,
 };
 use serde::{Deserialize, Serialize};
 
-#[derive(Serialize, Deserialize, Clone)]
+/// One line of a recipie: how much of whichever ingredient matches the selector
+#[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct Component {
     pub ingredient: IngredientSelector,
     pub amount: Measure,
 }
 
-#[derive(Serialize, Deserialize, Default, Clone)]
+#[derive(Serialize, Deserialize, Default, Clone, Debug)]
 pub struct Recipie {
     pub name: String,
     pub description: String,
@@ -23,10 +22,11 @@ pub struct Recipie {
 }
 
 impl Recipie {
-    pub fn can_make(&self, store: Rc<RefCell<IngredientStore>>) -> bool {
+    /// True when every component matches at least one ingredient that's in stock
+    pub fn can_make(&self, store: &IngredientStore) -> bool {
         self.components.iter().cloned().all(|mut c| {
             c.ingredient.in_stock = Some(true);
-            !store.borrow().select(&c.ingredient).is_empty()
+            !store.select(&c.ingredient).is_empty()
         })
     }
 }
@@ -1,13 +1,28 @@
-use std::{collections::HashMap, path::PathBuf};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
 
 use serde::{Deserialize, Serialize};
 
-use crate::{recipie::Recipie, store::Store};
+use crate::{
+    ingredient_store::IngredientStore,
+    recipie::Recipie,
+    sqlite_store,
+    store::{self, DirtyState, DiskState, FileStamp, Migration, Store, StoreError, StoreLoadError},
+    trash::TrashBin,
+};
 
 #[derive(Serialize, Deserialize, Default)]
 pub struct RecipieStore {
     recipies: HashMap<uuid::Uuid, Recipie>,
     config: Option<PathBuf>,
+    #[serde(default)]
+    trash: TrashBin<Recipie>,
+    #[serde(skip)]
+    dirty: DirtyState,
+    #[serde(skip)]
+    disk: DiskState,
 }
 
 impl RecipieStore {
@@ -17,13 +32,70 @@ impl RecipieStore {
             .map(|(u, r)| (u.clone(), r.clone()))
             .collect()
     }
+
+    /// Every recipie we have everything in stock for, by name
+    pub fn makeable(&self, ingredients: &IngredientStore) -> Vec<(uuid::Uuid, Recipie)> {
+        let mut makeable: Vec<(uuid::Uuid, Recipie)> = self
+            .get_recipie_entries()
+            .into_iter()
+            .filter(|(_, r)| r.can_make(ingredients))
+            .collect();
+        makeable.sort_by_key(|(_, r)| r.name.to_lowercase());
+        makeable
+    }
 }
 
 impl Store<Recipie> for RecipieStore {
+    const SCHEMA_VERSION: u32 = 1;
+
     fn get_json_name() -> PathBuf {
         "recipie.json".into()
     }
 
+    fn migrations() -> Vec<Migration> {
+        vec![
+            // v1 only introduced the schema_version envelope
+            Migration {
+                from: 0,
+                migrate: Ok,
+            },
+        ]
+    }
+
+    // Opened through the SQLite backend, the config path is the database rather than a JSON file
+    fn load_from(path: &Path) -> Result<Self, StoreLoadError> {
+        if sqlite_store::is_database(path) {
+            sqlite_store::load_recipies(path)
+        } else {
+            store::load_json(path)
+        }
+    }
+
+    // The database is shared, so each store locks the file its JSON would be in
+    fn lock_path(path: &Path) -> PathBuf {
+        if sqlite_store::is_database(path) {
+            path.with_file_name(Self::get_json_name())
+        } else {
+            path.to_path_buf()
+        }
+    }
+
+    fn stamp(path: &Path) -> Option<FileStamp> {
+        if sqlite_store::is_database(path) {
+            sqlite_store::recipies_stamp(path)
+        } else {
+            FileStamp::of(path)
+        }
+    }
+
+    fn save_to(&self, path: &Path) -> Result<(), StoreError> {
+        if sqlite_store::is_database(path) {
+            sqlite_store::save_recipies(self, path)
+        } else {
+            store::save_json(self, path)
+        }
+    }
+
     fn set_config_path(&mut self, path: PathBuf) {
         self.config = Some(path)
     }
@@ -32,14 +104,45 @@ impl Store<Recipie> for RecipieStore {
         self.config.clone()
     }
 
+    fn dirty(&self) -> &DirtyState {
+        &self.dirty
+    }
+
+    fn dirty_mut(&mut self) -> &mut DirtyState {
+        &mut self.dirty
+    }
+
+    fn disk(&self) -> &DiskState {
+        &self.disk
+    }
+
+    fn disk_mut(&mut self) -> &mut DiskState {
+        &mut self.disk
+    }
+
+    fn trash_bin(&self) -> &TrashBin<Recipie> {
+        &self.trash
+    }
+
+    fn trash_bin_mut(&mut self) -> &mut TrashBin<Recipie> {
+        &mut self.trash
+    }
+
     fn register(&mut self, entry: Recipie) -> uuid::Uuid {
         let id = uuid::Uuid::new_v4();
-        self.recipies.insert(id, entry);
+        self.insert(id, entry);
         id
     }
 
-    fn deregister(&mut self, id: uuid::Uuid) -> bool {
-        self.recipies.remove(&id).is_some()
+    fn insert(&mut self, id: uuid::Uuid, entry: Recipie) {
+        self.dirty.touch_id(id);
+        self.recipies.insert(id, entry);
+    }
+
+    fn deregister(&mut self, id: uuid::Uuid) -> Result<(), StoreError> {
+        self.recipies.remove(&id).ok_or(StoreError::UnknownId(id))?;
+        self.dirty.touch_id(id);
+        Ok(())
     }
 
     fn get_entries(&self) -> Vec<Recipie> {
@@ -47,9 +150,14 @@ impl Store<Recipie> for RecipieStore {
     }
 
     fn get_entries_mut(&mut self) -> Vec<&mut Recipie> {
+        self.dirty.touch();
         self.recipies.values_mut().collect()
     }
 
+    fn get_entries_with_ids(&self) -> Vec<(uuid::Uuid, Recipie)> {
+        self.get_recipie_entries()
+    }
+
     fn get_entry(&self, id: uuid::Uuid) -> Option<Recipie> {
         match self.recipies.get(&id) {
             Some(r) => Some(r.clone()),
@@ -58,7 +166,11 @@ impl Store<Recipie> for RecipieStore {
     }
 
     fn get_entry_mut(&mut self, id: uuid::Uuid) -> Option<&mut Recipie> {
-        self.recipies.get_mut(&id)
+        let entry = self.recipies.get_mut(&id);
+        if entry.is_some() {
+            self.dirty.touch_id(id);
+        }
+        entry
     }
 }
 
@@ -88,4 +200,25 @@ mod tests {
         assert_ne!(r.short_description, new_r.short_description);
         assert_ne!(r.notes, new_r.notes);
     }
+
+    #[test]
+    fn test_recipie_dirty() {
+        let mut store = RecipieStore::default();
+        assert!(!store.is_dirty());
+        let id = store.register(Recipie::default());
+        assert!(store.is_dirty());
+        store.dirty_mut().clear();
+        assert!(store.get_entry(id).is_some());
+        assert!(!store.is_dirty());
+        assert!(matches!(
+            store.deregister(uuid::Uuid::new_v4()),
+            Err(StoreError::UnknownId(_))
+        ));
+        assert!(!store.is_dirty());
+        assert!(store.get_entry_mut(id).is_some());
+        assert!(store.is_dirty());
+        store.dirty_mut().clear();
+        assert!(store.deregister(id).is_ok());
+        assert!(store.is_dirty());
+    }
 }
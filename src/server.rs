@@ -0,0 +1,284 @@
+use std::{net::SocketAddr, path::PathBuf};
+
+use serde::{Serialize, de::DeserializeOwned};
+use serde_json::{Value, json};
+use tiny_http::{Header, Method, Request, Response};
+use uuid::Uuid;
+
+use crate::{
+    backend::Backend,
+    ingredient_store::{IngredientSelector, IngredientStore},
+    recipie_store::RecipieStore,
+    store::{Store, StoreError, StoreLoadError},
+};
+
+/// Where `serve` listens unless told otherwise. Only this machine can reach it; listen on
+/// 0.0.0.0 to let the rest of the LAN in.
+pub const DEFAULT_ADDRESS: &str = "127.0.0.1:8080";
+
+/// The JSON API over a profile's stores. It keeps them open, so the app can only open
+/// them read-only while it runs, and saves after every change.
+///
+/// | Method | Path | |
+/// |---|---|---|
+/// | GET, POST | `/ingredients`, `/recipes` | list, or add the entry in the body |
+/// | GET, PUT, DELETE | `/ingredients/{id}`, `/recipes/{id}` | fetch, replace or trash one |
+/// | POST | `/ingredients/select` | ingredients matching the `IngredientSelector` in the body |
+/// | GET | `/recipes/makeable` | recipies everything's in stock for |
+///
+/// Entries come back with their `id` alongside their fields. Errors come back as
+/// `{"error": "..."}`.
+pub struct Api {
+    backend: Backend,
+    recipies: RecipieStore,
+    ingredients: IngredientStore,
+}
+
+/// A response, before it's been sent
+#[derive(Debug)]
+pub struct Reply {
+    pub status: u16,
+    pub body: Value,
+}
+
+impl Reply {
+    fn ok(body: Value) -> Reply {
+        Reply { status: 200, body }
+    }
+
+    fn error(status: u16, message: impl ToString) -> Reply {
+        Reply {
+            status,
+            body: json!({ "error": message.to_string() }),
+        }
+    }
+
+    fn from_store_error(err: StoreError) -> Reply {
+        let status = match err {
+            StoreError::UnknownId(_) => 404,
+            StoreError::ReadOnly(_) | StoreError::ChangedOnDisk(_) => 409,
+            _ => 500,
+        };
+        Reply::error(status, err)
+    }
+}
+
+impl Api {
+    pub fn open(backend: Backend, config_dir: PathBuf) -> Result<Api, StoreLoadError> {
+        Ok(Api {
+            backend,
+            recipies: backend.load_recipies(config_dir.clone())?,
+            ingredients: backend.load_ingredients(config_dir)?,
+        })
+    }
+
+    /// Answers one request. `path` may carry a query string, which is ignored.
+    pub fn handle(&mut self, method: &Method, path: &str, body: &str) -> Reply {
+        // Better to turn the request away than to answer it from stale stores
+        if let Err(err) = self.pick_up_outside_changes() {
+            return Reply::from_store_error(err);
+        }
+        let path = path.split('?').next().unwrap_or_default();
+        let parts: Vec<&str> = path.split('/').filter(|p| !p.is_empty()).collect();
+        let (reply, changed) = match parts.as_slice() {
+            ["ingredients", "select"] if *method == Method::Post => {
+                (self.select(body), Changed::Nothing)
+            }
+            ["ingredients", rest @ ..] => (
+                entries(&mut self.ingredients, method, rest, body),
+                Changed::Ingredients,
+            ),
+            ["recipes", "makeable"] if *method == Method::Get => {
+                (self.makeable(), Changed::Nothing)
+            }
+            ["recipes", rest @ ..] => (
+                entries(&mut self.recipies, method, rest, body),
+                Changed::Recipies,
+            ),
+            _ => (Reply::error(404, "No such endpoint"), Changed::Nothing),
+        };
+        // Reads leave the stores clean, so there's nothing to save for them
+        let saved = match changed {
+            Changed::Ingredients if self.ingredients.is_dirty() => {
+                self.backend.save_ingredients(&mut self.ingredients)
+            }
+            Changed::Recipies if self.recipies.is_dirty() => {
+                self.backend.save_recipies(&mut self.recipies)
+            }
+            _ => Ok(()),
+        };
+        let Err(err) = saved else {
+            return reply;
+        };
+        // The change never made it to disk. Dropping it leaves us serving what's in the
+        // file, and lets the next request save again rather than being turned away too.
+        let reloaded = match changed {
+            Changed::Ingredients => self.ingredients.reload_from_disk(),
+            Changed::Recipies => self.recipies.reload_from_disk(),
+            Changed::Nothing => Ok(()),
+        };
+        Reply::from_store_error(reloaded.err().unwrap_or(err))
+    }
+
+    // Someone may have edited the files since, e.g. with the CLI. We save after every
+    // change, so there's nothing of ours to lose by reloading.
+    fn pick_up_outside_changes(&mut self) -> Result<(), StoreError> {
+        reload_if_changed(&mut self.ingredients)?;
+        reload_if_changed(&mut self.recipies)
+    }
+
+    fn select(&self, body: &str) -> Reply {
+        let selector: IngredientSelector = match serde_json::from_str(body) {
+            Ok(selector) => selector,
+            Err(err) => return Reply::error(400, err),
+        };
+        let matches = self.ingredients.select_with_ids(&selector);
+        Reply::ok(to_json(&with_ids(&matches)))
+    }
+
+    fn makeable(&self) -> Reply {
+        let makeable = self.recipies.makeable(&self.ingredients);
+        Reply::ok(to_json(&with_ids(&makeable)))
+    }
+}
+
+enum Changed {
+    Nothing,
+    Ingredients,
+    Recipies,
+}
+
+// Anything unsaved is left over from a write that was turned away, and goes too
+fn reload_if_changed<T, S: Store<T>>(store: &mut S) -> Result<(), StoreError> {
+    if store.changed_on_disk() || store.is_dirty() {
+        store.reload_from_disk()?;
+    }
+    Ok(())
+}
+
+/// CRUD under `/ingredients` or `/recipes`, with `rest` being what follows
+fn entries<T: Serialize + DeserializeOwned, S: Store<T>>(
+    store: &mut S,
+    method: &Method,
+    rest: &[&str],
+    body: &str,
+) -> Reply {
+    let id = match rest {
+        [] => None,
+        [id] => match Uuid::parse_str(id) {
+            Ok(id) => Some(id),
+            Err(_) => return Reply::error(404, format!("'{}' isn't an id", id)),
+        },
+        _ => return Reply::error(404, "No such endpoint"),
+    };
+    match (method, id) {
+        (Method::Get, None) => Reply::ok(to_json(&with_ids(&store.get_entries_with_ids()))),
+        (Method::Post, None) => match serde_json::from_str::<T>(body) {
+            Ok(entry) => {
+                let json = to_json(&entry);
+                let id = store.register(entry);
+                Reply {
+                    status: 201,
+                    body: with_id(id, json),
+                }
+            }
+            Err(err) => Reply::error(400, err),
+        },
+        (Method::Get, Some(id)) => match store.get_entry(id) {
+            Some(entry) => Reply::ok(with_id(id, to_json(&entry))),
+            None => Reply::from_store_error(StoreError::UnknownId(id)),
+        },
+        (Method::Put, Some(id)) => match serde_json::from_str::<T>(body) {
+            Ok(_) if store.get_entry(id).is_none() => {
+                Reply::from_store_error(StoreError::UnknownId(id))
+            }
+            Ok(entry) => {
+                let json = to_json(&entry);
+                store.insert(id, entry);
+                Reply::ok(with_id(id, json))
+            }
+            Err(err) => Reply::error(400, err),
+        },
+        // Trashed rather than gone, so it can still be restored from the app
+        (Method::Delete, Some(id)) => match store.move_to_trash(id) {
+            Ok(()) => Reply {
+                status: 204,
+                body: Value::Null,
+            },
+            Err(err) => Reply::from_store_error(err),
+        },
+        _ => Reply::error(405, format!("{} isn't allowed here", method)),
+    }
+}
+
+fn to_json<T: Serialize + ?Sized>(value: &T) -> Value {
+    serde_json::to_value(value).unwrap_or(Value::Null)
+}
+
+/// Entries are written out with their ids, so scripts can refer back to them
+#[derive(Serialize)]
+pub(crate) struct WithId<'a, T> {
+    pub id: Uuid,
+    #[serde(flatten)]
+    pub entry: &'a T,
+}
+
+pub(crate) fn with_ids<T>(entries: &[(Uuid, T)]) -> Vec<WithId<'_, T>> {
+    entries
+        .iter()
+        .map(|(id, entry)| WithId { id: *id, entry })
+        .collect()
+}
+
+fn with_id(id: Uuid, mut entry: Value) -> Value {
+    if let Value::Object(fields) = &mut entry {
+        fields.insert("id".to_string(), json!(id));
+    }
+    entry
+}
+
+/// The API listening for HTTP requests
+pub struct Server {
+    http: tiny_http::Server,
+    api: Api,
+}
+
+impl Server {
+    /// `address` is e.g. "127.0.0.1:8080". Port 0 picks a free one, see `address`.
+    pub fn bind(api: Api, address: &str) -> std::io::Result<Server> {
+        let http = tiny_http::Server::http(address).map_err(std::io::Error::other)?;
+        Ok(Server { http, api })
+    }
+
+    pub fn address(&self) -> Option<SocketAddr> {
+        self.http.server_addr().to_ip()
+    }
+
+    /// Serves requests one at a time, forever. Requests that couldn't be answered are
+    /// passed to `on_error`.
+    pub fn run(mut self, mut on_error: impl FnMut(String)) {
+        for request in self.http.incoming_requests() {
+            if let Err(err) = respond(&mut self.api, request) {
+                on_error(format!("Couldn't answer a request: {}", err));
+            }
+        }
+    }
+}
+
+fn respond(api: &mut Api, mut request: Request) -> std::io::Result<()> {
+    let mut body = String::new();
+    let reply = match request.as_reader().read_to_string(&mut body) {
+        Ok(_) => api.handle(request.method(), request.url(), &body),
+        Err(err) => Reply::error(400, err),
+    };
+    let text = match reply.body {
+        Value::Null => String::new(),
+        body => body.to_string(),
+    };
+    let content_type =
+        Header::from_bytes("Content-Type", "application/json").expect("a valid header");
+    let response = Response::from_string(text)
+        .with_status_code(reply.status)
+        .with_header(content_type);
+    request.respond(response)
+}
@@ -0,0 +1,1080 @@
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::Duration,
+};
+
+use rusqlite::{Connection, OpenFlags, OptionalExtension, Transaction, params, types::Type};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use uuid::Uuid;
+
+use crate::{
+    ingredient::{Ingredient, IngredientTag},
+    ingredient_store::{IngredientSelector, IngredientStore},
+    recipie::{Component, Recipie},
+    recipie_store::RecipieStore,
+    store::{
+        DirtyState, DiskState, FileStamp, Migration, Store, StoreError, StoreLoadError,
+        lock_store_file,
+    },
+    trash::TrashBin,
+};
+
+/// Both sqlite stores share one database file
+const DB_NAME: &str = "drink_solver.sqlite";
+
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Names the stores go by in the versions table
+const INGREDIENTS: &str = "ingredients";
+const RECIPIES: &str = "recipies";
+
+const SCHEMA: &str = "
+    PRAGMA foreign_keys = ON;
+    CREATE TABLE IF NOT EXISTS tags (
+        id INTEGER PRIMARY KEY,
+        value TEXT NOT NULL UNIQUE
+    );
+    CREATE TABLE IF NOT EXISTS ingredients (
+        id TEXT PRIMARY KEY,
+        name TEXT NOT NULL,
+        quality TEXT NOT NULL,
+        stock INTEGER NOT NULL,
+        is_liquor INTEGER NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS ingredient_tags (
+        ingredient_id TEXT NOT NULL REFERENCES ingredients(id) ON DELETE CASCADE,
+        position INTEGER NOT NULL,
+        tag_id INTEGER NOT NULL REFERENCES tags(id),
+        PRIMARY KEY (ingredient_id, position)
+    );
+    CREATE TABLE IF NOT EXISTS recipies (
+        id TEXT PRIMARY KEY,
+        name TEXT NOT NULL,
+        description TEXT NOT NULL,
+        short_description TEXT NOT NULL,
+        notes TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS components (
+        recipie_id TEXT NOT NULL REFERENCES recipies(id) ON DELETE CASCADE,
+        position INTEGER NOT NULL,
+        name TEXT,
+        quality TEXT,
+        in_stock INTEGER,
+        is_liquor INTEGER,
+        amount TEXT NOT NULL,
+        PRIMARY KEY (recipie_id, position)
+    );
+    CREATE TABLE IF NOT EXISTS component_tags (
+        recipie_id TEXT NOT NULL,
+        component INTEGER NOT NULL,
+        position INTEGER NOT NULL,
+        tag_id INTEGER NOT NULL REFERENCES tags(id),
+        PRIMARY KEY (recipie_id, component, position),
+        FOREIGN KEY (recipie_id, component) REFERENCES components(recipie_id, position) ON DELETE CASCADE
+    );
+    CREATE TABLE IF NOT EXISTS instructions (
+        recipie_id TEXT NOT NULL REFERENCES recipies(id) ON DELETE CASCADE,
+        position INTEGER NOT NULL,
+        step TEXT NOT NULL,
+        PRIMARY KEY (recipie_id, position)
+    );
+    CREATE TABLE IF NOT EXISTS ingredient_trash (
+        id TEXT PRIMARY KEY,
+        deleted_at INTEGER NOT NULL,
+        entry TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS recipie_trash (
+        id TEXT PRIMARY KEY,
+        deleted_at INTEGER NOT NULL,
+        entry TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS versions (
+        store TEXT PRIMARY KEY,
+        version INTEGER NOT NULL
+    );
+";
+
+/// Columns added since their table was first created, which older databases lack, with
+/// what to read in their place until a save adds them
+const ADDED_COLUMNS: &[(&str, &str, &str, &str)] = &[
+    ("ingredients", "bottle_ml", "INTEGER", "NULL"),
+    (
+        "ingredients",
+        "poured_ml",
+        "INTEGER NOT NULL DEFAULT 0",
+        "0",
+    ),
+    ("ingredients", "extra", "TEXT NOT NULL DEFAULT '{}'", "'{}'"),
+];
+
+/// Ingredient store kept in a SQLite database instead of ingredient.json
+#[derive(Serialize, Deserialize, Default)]
+pub struct SqliteIngredientStore {
+    store: IngredientStore,
+    #[serde(skip)]
+    db_path: Option<PathBuf>,
+}
+
+/// Recipie store kept in a SQLite database instead of recipie.json
+#[derive(Serialize, Deserialize, Default)]
+pub struct SqliteRecipieStore {
+    store: RecipieStore,
+    #[serde(skip)]
+    db_path: Option<PathBuf>,
+}
+
+impl SqliteIngredientStore {
+    /// The in-memory store the rest of the app works with. Its config path points at the
+    /// database, which it loads from and saves to on its own.
+    pub fn into_inner(self) -> IngredientStore {
+        let mut store = self.store;
+        if let Some(path) = self.db_path {
+            store.set_config_path(path);
+        }
+        store
+    }
+
+    /// Writes the ingredients in `changed`, deleting rows for the ones that are gone, or
+    /// every ingredient when `changed` is None
+    pub fn write(
+        store: &IngredientStore,
+        path: &Path,
+        changed: Option<&HashSet<Uuid>>,
+    ) -> rusqlite::Result<()> {
+        let mut conn = open(path)?;
+        let tx = conn.transaction()?;
+        let ids = match changed {
+            Some(ids) => ids.clone(),
+            None => all_ids(&tx, "ingredients", "ingredient_trash", store)?,
+        };
+        for id in ids {
+            let key = id.to_string();
+            tx.execute(
+                "DELETE FROM ingredient_tags WHERE ingredient_id = ?1",
+                [&key],
+            )?;
+            let Some(ingredient) = store.get_entry(id) else {
+                tx.execute("DELETE FROM ingredients WHERE id = ?1", [&key])?;
+                write_trashed(&tx, "ingredient_trash", id, store.trash_bin())?;
+                continue;
+            };
+            let extra = serde_json::to_string(&ingredient.extra)
+                .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+            tx.execute(
+                "INSERT INTO ingredients
+                 (id, name, quality, stock, is_liquor, bottle_ml, poured_ml, extra)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+                 ON CONFLICT (id) DO UPDATE SET
+                 name = ?2, quality = ?3, stock = ?4, is_liquor = ?5, bottle_ml = ?6,
+                 poured_ml = ?7, extra = ?8",
+                params![
+                    key,
+                    ingredient.name,
+                    ingredient.quality.to_string(),
+                    ingredient.stock,
+                    ingredient.is_liquor,
+                    ingredient.bottle_ml,
+                    ingredient.poured_ml,
+                    extra
+                ],
+            )?;
+            for (position, tag) in ingredient.tags.iter().enumerate() {
+                let tag_id = tag_id(&tx, &tag.value)?;
+                tx.execute(
+                    "INSERT INTO ingredient_tags (ingredient_id, position, tag_id)
+                     VALUES (?1, ?2, ?3)",
+                    params![key, position, tag_id],
+                )?;
+            }
+            write_trashed(&tx, "ingredient_trash", id, store.trash_bin())?;
+        }
+        bump_version(&tx, INGREDIENTS)?;
+        tx.commit()
+    }
+
+    fn read(path: &Path) -> rusqlite::Result<IngredientStore> {
+        let mut store = IngredientStore::default();
+        let Some(conn) = open_read_only(path, "ingredients")? else {
+            return Ok(store);
+        };
+        let mut tags = conn.prepare(
+            "SELECT tags.value FROM ingredient_tags
+             JOIN tags ON tags.id = ingredient_tags.tag_id
+             WHERE ingredient_id = ?1 ORDER BY position",
+        )?;
+        let mut stmt = conn.prepare(&format!(
+            "SELECT id, name, quality, stock, is_liquor, {}, {}, {} FROM ingredients",
+            column(&conn, "ingredients", "bottle_ml")?,
+            column(&conn, "ingredients", "poured_ml")?,
+            column(&conn, "ingredients", "extra")?,
+        ))?;
+        let mut rows = stmt.query([])?;
+        while let Some(row) = rows.next()? {
+            let id: String = row.get(0)?;
+            let ingredient = Ingredient {
+                name: row.get(1)?,
+                quality: parse(2, row.get(2)?)?,
+                tags: tags
+                    .query_map([&id], |r| Ok(IngredientTag { value: r.get(0)? }))?
+                    .collect::<rusqlite::Result<_>>()?,
+                stock: row.get(3)?,
+                is_liquor: row.get(4)?,
+                bottle_ml: row.get(5)?,
+                poured_ml: row.get(6)?,
+                extra: serde_json::from_str(&row.get::<_, String>(7)?).map_err(|e| {
+                    rusqlite::Error::FromSqlConversionFailure(7, Type::Text, Box::new(e))
+                })?,
+            };
+            store.insert(parse(0, id)?, ingredient);
+        }
+        *store.trash_bin_mut() = read_trash(&conn, "ingredient_trash")?;
+        // Straight from the database, so there's nothing to save yet
+        store.dirty_mut().clear();
+        Ok(store)
+    }
+}
+
+impl SqliteRecipieStore {
+    /// The in-memory store the rest of the app works with. Its config path points at the
+    /// database, which it loads from and saves to on its own.
+    pub fn into_inner(self) -> RecipieStore {
+        let mut store = self.store;
+        if let Some(path) = self.db_path {
+            store.set_config_path(path);
+        }
+        store
+    }
+
+    /// Writes the recipies in `changed`, deleting rows for the ones that are gone, or
+    /// every recipie when `changed` is None
+    pub fn write(
+        store: &RecipieStore,
+        path: &Path,
+        changed: Option<&HashSet<Uuid>>,
+    ) -> rusqlite::Result<()> {
+        let mut conn = open(path)?;
+        let tx = conn.transaction()?;
+        let ids = match changed {
+            Some(ids) => ids.clone(),
+            None => all_ids(&tx, "recipies", "recipie_trash", store)?,
+        };
+        for id in ids {
+            let key = id.to_string();
+            // Component tags go with their components
+            tx.execute("DELETE FROM components WHERE recipie_id = ?1", [&key])?;
+            tx.execute("DELETE FROM instructions WHERE recipie_id = ?1", [&key])?;
+            let Some(recipie) = store.get_entry(id) else {
+                tx.execute("DELETE FROM recipies WHERE id = ?1", [&key])?;
+                write_trashed(&tx, "recipie_trash", id, store.trash_bin())?;
+                continue;
+            };
+            tx.execute(
+                "INSERT INTO recipies (id, name, description, short_description, notes)
+                 VALUES (?1, ?2, ?3, ?4, ?5)
+                 ON CONFLICT (id) DO UPDATE SET
+                 name = ?2, description = ?3, short_description = ?4, notes = ?5",
+                params![
+                    key,
+                    recipie.name,
+                    recipie.description,
+                    recipie.short_description,
+                    recipie.notes
+                ],
+            )?;
+            for (position, component) in recipie.components.iter().enumerate() {
+                let selector = &component.ingredient;
+                let amount = serde_json::to_string(&component.amount)
+                    .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+                tx.execute(
+                    "INSERT INTO components
+                     (recipie_id, position, name, quality, in_stock, is_liquor, amount)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                    params![
+                        key,
+                        position,
+                        selector.name,
+                        selector.quality.map(|q| q.to_string()),
+                        selector.in_stock,
+                        selector.is_liquor,
+                        amount
+                    ],
+                )?;
+                for (tag_position, tag) in selector.tags.iter().flatten().enumerate() {
+                    let tag_id = tag_id(&tx, &tag.value)?;
+                    tx.execute(
+                        "INSERT INTO component_tags (recipie_id, component, position, tag_id)
+                         VALUES (?1, ?2, ?3, ?4)",
+                        params![key, position, tag_position, tag_id],
+                    )?;
+                }
+            }
+            for (position, step) in recipie.instructions.iter().enumerate() {
+                tx.execute(
+                    "INSERT INTO instructions (recipie_id, position, step) VALUES (?1, ?2, ?3)",
+                    params![key, position, step],
+                )?;
+            }
+            write_trashed(&tx, "recipie_trash", id, store.trash_bin())?;
+        }
+        bump_version(&tx, RECIPIES)?;
+        tx.commit()
+    }
+
+    fn read(path: &Path) -> rusqlite::Result<RecipieStore> {
+        let mut store = RecipieStore::default();
+        let Some(conn) = open_read_only(path, "recipies")? else {
+            return Ok(store);
+        };
+        let mut components = conn.prepare(
+            "SELECT position, name, quality, in_stock, is_liquor, amount FROM components
+             WHERE recipie_id = ?1 ORDER BY position",
+        )?;
+        let mut component_tags = conn.prepare(
+            "SELECT tags.value FROM component_tags
+             JOIN tags ON tags.id = component_tags.tag_id
+             WHERE recipie_id = ?1 AND component = ?2 ORDER BY position",
+        )?;
+        let mut instructions =
+            conn.prepare("SELECT step FROM instructions WHERE recipie_id = ?1 ORDER BY position")?;
+        let mut stmt =
+            conn.prepare("SELECT id, name, description, short_description, notes FROM recipies")?;
+        let mut rows = stmt.query([])?;
+        while let Some(row) = rows.next()? {
+            let id: String = row.get(0)?;
+            let mut recipie = Recipie {
+                name: row.get(1)?,
+                description: row.get(2)?,
+                short_description: row.get(3)?,
+                notes: row.get(4)?,
+                instructions: instructions
+                    .query_map([&id], |r| r.get(0))?
+                    .collect::<rusqlite::Result<_>>()?,
+                ..Default::default()
+            };
+            let mut component_rows = components.query([&id])?;
+            while let Some(c) = component_rows.next()? {
+                let position: i64 = c.get(0)?;
+                let tags: Vec<IngredientTag> = component_tags
+                    .query_map(params![id, position], |r| {
+                        Ok(IngredientTag { value: r.get(0)? })
+                    })?
+                    .collect::<rusqlite::Result<_>>()?;
+                let quality: Option<String> = c.get(2)?;
+                let amount: String = c.get(5)?;
+                recipie.components.push(Component {
+                    ingredient: IngredientSelector {
+                        name: c.get(1)?,
+                        quality: quality.map(|q| parse(2, q)).transpose()?,
+                        tags: if tags.is_empty() { None } else { Some(tags) },
+                        in_stock: c.get(3)?,
+                        is_liquor: c.get(4)?,
+                    },
+                    amount: serde_json::from_str(&amount).map_err(|e| {
+                        rusqlite::Error::FromSqlConversionFailure(5, Type::Text, Box::new(e))
+                    })?,
+                });
+            }
+            store.insert(parse(0, id)?, recipie);
+        }
+        *store.trash_bin_mut() = read_trash(&conn, "recipie_trash")?;
+        // Straight from the database, so there's nothing to save yet
+        store.dirty_mut().clear();
+        Ok(store)
+    }
+}
+
+/// True for a store opened through the SQLite backend, whose config path is the database
+pub fn is_database(path: &Path) -> bool {
+    path.file_name() == Some(DB_NAME.as_ref())
+}
+
+pub fn load_ingredients(path: &Path) -> Result<IngredientStore, StoreLoadError> {
+    SqliteIngredientStore::read(path).map_err(|e| database_error(path, e))
+}
+
+pub fn save_ingredients(store: &IngredientStore, path: &Path) -> Result<(), StoreError> {
+    SqliteIngredientStore::write(store, path, store.dirty().changed_ids())
+        .map_err(|e| write_error(path, e))
+}
+
+pub fn ingredients_stamp(path: &Path) -> Option<FileStamp> {
+    version(path, INGREDIENTS)
+}
+
+pub fn load_recipies(path: &Path) -> Result<RecipieStore, StoreLoadError> {
+    SqliteRecipieStore::read(path).map_err(|e| database_error(path, e))
+}
+
+pub fn save_recipies(store: &RecipieStore, path: &Path) -> Result<(), StoreError> {
+    SqliteRecipieStore::write(store, path, store.dirty().changed_ids())
+        .map_err(|e| write_error(path, e))
+}
+
+pub fn recipies_stamp(path: &Path) -> Option<FileStamp> {
+    version(path, RECIPIES)
+}
+
+fn open(path: &Path) -> rusqlite::Result<Connection> {
+    let conn = Connection::open(path)?;
+    // Another instance may be halfway through a save
+    conn.busy_timeout(BUSY_TIMEOUT)?;
+    conn.execute_batch(SCHEMA)?;
+    for (table, column, definition, _) in ADDED_COLUMNS {
+        if !has_column(&conn, table, column)? {
+            conn.execute(
+                &format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition),
+                [],
+            )?;
+        }
+    }
+    Ok(conn)
+}
+
+/// Opens the database just to read `table`, without creating or upgrading anything, so
+/// looking never touches the disk. None when there's nothing to read yet.
+fn open_read_only(path: &Path, table: &str) -> rusqlite::Result<Option<Connection>> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+    conn.busy_timeout(BUSY_TIMEOUT)?;
+    if !has_table(&conn, table)? {
+        return Ok(None);
+    }
+    Ok(Some(conn))
+}
+
+fn has_table(conn: &Connection, table: &str) -> rusqlite::Result<bool> {
+    conn.query_row(
+        "SELECT COUNT(*) > 0 FROM sqlite_master WHERE type = 'table' AND name = ?1",
+        [table],
+        |r| r.get(0),
+    )
+}
+
+fn has_column(conn: &Connection, table: &str, column: &str) -> rusqlite::Result<bool> {
+    conn.query_row(
+        &format!(
+            "SELECT COUNT(*) > 0 FROM pragma_table_info('{}') WHERE name = ?1",
+            table
+        ),
+        [column],
+        |r| r.get(0),
+    )
+}
+
+/// An added column to select, or its stand-in when the database predates it
+fn column(conn: &Connection, table: &str, column: &str) -> rusqlite::Result<String> {
+    if has_column(conn, table, column)? {
+        return Ok(column.to_string());
+    }
+    Ok(ADDED_COLUMNS
+        .iter()
+        .find(|(t, c, _, _)| *t == table && *c == column)
+        .map_or("NULL", |(_, _, _, default)| default)
+        .to_string())
+}
+
+/// Trashed entries are only ever restored whole, so they're kept as JSON
+fn write_trashed<T: Serialize>(
+    tx: &Transaction,
+    table: &str,
+    id: Uuid,
+    trash: &TrashBin<T>,
+) -> rusqlite::Result<()> {
+    let Some(trashed) = trash.get(id) else {
+        tx.execute(
+            &format!("DELETE FROM {} WHERE id = ?1", table),
+            [id.to_string()],
+        )?;
+        return Ok(());
+    };
+    let entry = serde_json::to_string(&trashed.entry)
+        .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+    tx.execute(
+        &format!(
+            "INSERT OR REPLACE INTO {} (id, deleted_at, entry) VALUES (?1, ?2, ?3)",
+            table
+        ),
+        params![id.to_string(), trashed.deleted_at, entry],
+    )?;
+    Ok(())
+}
+
+/// Every id in the database or the store, live or trashed, for writing the whole store
+fn all_ids<T: Clone, S: Store<T>>(
+    tx: &Transaction,
+    table: &str,
+    trash_table: &str,
+    store: &S,
+) -> rusqlite::Result<HashSet<Uuid>> {
+    let mut stmt = tx.prepare(&format!(
+        "SELECT id FROM {} UNION SELECT id FROM {}",
+        table, trash_table
+    ))?;
+    let mut ids = stmt
+        .query_map([], |r| r.get(0))?
+        .map(|id| parse(0, id?))
+        .collect::<rusqlite::Result<HashSet<Uuid>>>()?;
+    ids.extend(store.get_entries_with_ids().into_iter().map(|(id, _)| id));
+    ids.extend(store.trash_bin().entries().into_iter().map(|(id, _)| id));
+    Ok(ids)
+}
+
+fn read_trash<T: DeserializeOwned>(
+    conn: &Connection,
+    table: &str,
+) -> rusqlite::Result<TrashBin<T>> {
+    let mut trash = TrashBin::default();
+    if !has_table(conn, table)? {
+        return Ok(trash);
+    }
+    let mut stmt = conn.prepare(&format!("SELECT id, deleted_at, entry FROM {}", table))?;
+    let mut rows = stmt.query([])?;
+    while let Some(row) = rows.next()? {
+        let entry: String = row.get(2)?;
+        let entry = serde_json::from_str(&entry)
+            .map_err(|e| rusqlite::Error::FromSqlConversionFailure(2, Type::Text, Box::new(e)))?;
+        trash.put(parse(0, row.get(0)?)?, entry, row.get(1)?);
+    }
+    Ok(trash)
+}
+
+/// Every save of a store counts up its version, so each of the stores sharing the database
+/// can tell when someone else wrote to it
+fn bump_version(tx: &Transaction, store: &str) -> rusqlite::Result<()> {
+    tx.execute(
+        "INSERT INTO versions (store, version) VALUES (?1, 1)
+         ON CONFLICT (store) DO UPDATE SET version = version + 1",
+        [store],
+    )?;
+    Ok(())
+}
+
+fn version(path: &Path, store: &str) -> Option<FileStamp> {
+    // Opening a connection would create a missing database
+    if !path.exists() {
+        return None;
+    }
+    let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY).ok()?;
+    conn.busy_timeout(BUSY_TIMEOUT).ok()?;
+    let version = conn
+        .query_row(
+            "SELECT version FROM versions WHERE store = ?1",
+            [store],
+            |r| r.get(0),
+        )
+        .optional()
+        .ok()?;
+    Some(FileStamp::Version(version.unwrap_or(0)))
+}
+
+/// Id of a tag's row, adding it if we haven't seen it before
+fn tag_id(tx: &Transaction, value: &str) -> rusqlite::Result<i64> {
+    let existing = tx
+        .query_row("SELECT id FROM tags WHERE value = ?1", [value], |r| {
+            r.get(0)
+        })
+        .optional()?;
+    match existing {
+        Some(id) => Ok(id),
+        None => {
+            tx.execute("INSERT INTO tags (value) VALUES (?1)", [value])?;
+            Ok(tx.last_insert_rowid())
+        }
+    }
+}
+
+fn parse<T: FromStr>(column: usize, value: String) -> rusqlite::Result<T>
+where
+    T::Err: std::error::Error + Send + Sync + 'static,
+{
+    value
+        .parse()
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(column, Type::Text, Box::new(e)))
+}
+
+pub fn write_error(path: &Path, err: rusqlite::Error) -> StoreError {
+    StoreError::Database {
+        path: path.to_path_buf(),
+        message: err.to_string(),
+    }
+}
+
+fn database_error(path: &Path, err: rusqlite::Error) -> StoreLoadError {
+    StoreLoadError::Database {
+        path: path.to_path_buf(),
+        message: err.to_string(),
+    }
+}
+
+impl Store<Ingredient> for SqliteIngredientStore {
+    /// The database has its own tables rather than a JSON envelope
+    const SCHEMA_VERSION: u32 = 1;
+
+    fn get_json_name() -> PathBuf {
+        DB_NAME.into()
+    }
+
+    fn migrations() -> Vec<Migration> {
+        vec![]
+    }
+
+    fn set_config_path(&mut self, path: PathBuf) {
+        self.db_path = Some(path)
+    }
+
+    fn get_config_path(&self) -> Option<PathBuf> {
+        self.db_path.clone()
+    }
+
+    fn dirty(&self) -> &DirtyState {
+        self.store.dirty()
+    }
+
+    fn dirty_mut(&mut self) -> &mut DirtyState {
+        self.store.dirty_mut()
+    }
+
+    fn disk(&self) -> &DiskState {
+        self.store.disk()
+    }
+
+    fn disk_mut(&mut self) -> &mut DiskState {
+        self.store.disk_mut()
+    }
+
+    fn trash_bin(&self) -> &TrashBin<Ingredient> {
+        self.store.trash_bin()
+    }
+
+    fn trash_bin_mut(&mut self) -> &mut TrashBin<Ingredient> {
+        self.store.trash_bin_mut()
+    }
+
+    fn register(&mut self, entry: Ingredient) -> uuid::Uuid {
+        self.store.register(entry)
+    }
+
+    fn insert(&mut self, id: uuid::Uuid, entry: Ingredient) {
+        self.store.insert(id, entry)
+    }
+
+    fn deregister(&mut self, id: uuid::Uuid) -> Result<(), StoreError> {
+        self.store.deregister(id)
+    }
+
+    fn get_entries(&self) -> Vec<Ingredient> {
+        self.store.get_entries()
+    }
+
+    fn get_entries_mut(&mut self) -> Vec<&mut Ingredient> {
+        self.store.get_entries_mut()
+    }
+
+    fn get_entries_with_ids(&self) -> Vec<(uuid::Uuid, Ingredient)> {
+        self.store.get_entries_with_ids()
+    }
+
+    fn get_entry(&self, id: uuid::Uuid) -> Option<Ingredient> {
+        self.store.get_entry(id)
+    }
+
+    fn get_entry_mut(&mut self, id: uuid::Uuid) -> Option<&mut Ingredient> {
+        self.store.get_entry_mut(id)
+    }
+
+    /// Takes the same lock as ingredient.json, so a second instance can't open the store
+    /// whichever backend either of them uses
+    fn from_config(config_dir: PathBuf) -> Result<Self, StoreLoadError> {
+        let path = config_dir.join(Self::get_json_name());
+        std::fs::create_dir_all(&config_dir).map_err(|source| StoreLoadError::Unreadable {
+            path: path.clone(),
+            source,
+        })?;
+        let lock = lock_store_file(&config_dir.join(IngredientStore::get_json_name()))?;
+        // Ours to write, so create the database or bring its tables up to date now
+        open(&path).map_err(|e| database_error(&path, e))?;
+        let mut store = Self::load_from(&path)?;
+        *store.disk_mut() = DiskState::new(lock, false, Self::stamp(&path));
+        store.set_config_path(path);
+        Ok(store)
+    }
+
+    fn load_from(path: &Path) -> Result<Self, StoreLoadError> {
+        Ok(SqliteIngredientStore {
+            store: load_ingredients(path)?,
+            db_path: None,
+        })
+    }
+
+    fn stamp(path: &Path) -> Option<FileStamp> {
+        ingredients_stamp(path)
+    }
+
+    fn save_to(&self, path: &Path) -> Result<(), StoreError> {
+        save_ingredients(&self.store, path)
+    }
+}
+
+impl Store<Recipie> for SqliteRecipieStore {
+    /// The database has its own tables rather than a JSON envelope
+    const SCHEMA_VERSION: u32 = 1;
+
+    fn get_json_name() -> PathBuf {
+        DB_NAME.into()
+    }
+
+    fn migrations() -> Vec<Migration> {
+        vec![]
+    }
+
+    fn set_config_path(&mut self, path: PathBuf) {
+        self.db_path = Some(path)
+    }
+
+    fn get_config_path(&self) -> Option<PathBuf> {
+        self.db_path.clone()
+    }
+
+    fn dirty(&self) -> &DirtyState {
+        self.store.dirty()
+    }
+
+    fn dirty_mut(&mut self) -> &mut DirtyState {
+        self.store.dirty_mut()
+    }
+
+    fn disk(&self) -> &DiskState {
+        self.store.disk()
+    }
+
+    fn disk_mut(&mut self) -> &mut DiskState {
+        self.store.disk_mut()
+    }
+
+    fn trash_bin(&self) -> &TrashBin<Recipie> {
+        self.store.trash_bin()
+    }
+
+    fn trash_bin_mut(&mut self) -> &mut TrashBin<Recipie> {
+        self.store.trash_bin_mut()
+    }
+
+    fn register(&mut self, entry: Recipie) -> uuid::Uuid {
+        self.store.register(entry)
+    }
+
+    fn insert(&mut self, id: uuid::Uuid, entry: Recipie) {
+        self.store.insert(id, entry)
+    }
+
+    fn deregister(&mut self, id: uuid::Uuid) -> Result<(), StoreError> {
+        self.store.deregister(id)
+    }
+
+    fn get_entries(&self) -> Vec<Recipie> {
+        self.store.get_entries()
+    }
+
+    fn get_entries_mut(&mut self) -> Vec<&mut Recipie> {
+        self.store.get_entries_mut()
+    }
+
+    fn get_entries_with_ids(&self) -> Vec<(uuid::Uuid, Recipie)> {
+        self.store.get_entries_with_ids()
+    }
+
+    fn get_entry(&self, id: uuid::Uuid) -> Option<Recipie> {
+        self.store.get_entry(id)
+    }
+
+    fn get_entry_mut(&mut self, id: uuid::Uuid) -> Option<&mut Recipie> {
+        self.store.get_entry_mut(id)
+    }
+
+    /// Takes the same lock as recipie.json, so a second instance can't open the store
+    /// whichever backend either of them uses
+    fn from_config(config_dir: PathBuf) -> Result<Self, StoreLoadError> {
+        let path = config_dir.join(Self::get_json_name());
+        std::fs::create_dir_all(&config_dir).map_err(|source| StoreLoadError::Unreadable {
+            path: path.clone(),
+            source,
+        })?;
+        let lock = lock_store_file(&config_dir.join(RecipieStore::get_json_name()))?;
+        // Ours to write, so create the database or bring its tables up to date now
+        open(&path).map_err(|e| database_error(&path, e))?;
+        let mut store = Self::load_from(&path)?;
+        *store.disk_mut() = DiskState::new(lock, false, Self::stamp(&path));
+        store.set_config_path(path);
+        Ok(store)
+    }
+
+    fn load_from(path: &Path) -> Result<Self, StoreLoadError> {
+        Ok(SqliteRecipieStore {
+            store: load_recipies(path)?,
+            db_path: None,
+        })
+    }
+
+    fn stamp(path: &Path) -> Option<FileStamp> {
+        recipies_stamp(path)
+    }
+
+    fn save_to(&self, path: &Path) -> Result<(), StoreError> {
+        save_recipies(&self.store, path)
+    }
+}
+
+/// One-shot copy of recipie.json and ingredient.json into the database
+pub fn import_json(config_dir: &Path) -> Result<(), StoreLoadError> {
+    let db = config_dir.join(DB_NAME);
+    let ingredient_json = config_dir.join(IngredientStore::get_json_name());
+    if ingredient_json.exists() {
+        let ingredients = IngredientStore::load_from(&ingredient_json)?;
+        SqliteIngredientStore::write(&ingredients, &db, None)
+            .map_err(|e| database_error(&db, e))?;
+    }
+    let recipie_json = config_dir.join(RecipieStore::get_json_name());
+    if recipie_json.exists() {
+        let recipies = RecipieStore::load_from(&recipie_json)?;
+        SqliteRecipieStore::write(&recipies, &db, None).map_err(|e| database_error(&db, e))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ingredient::Quality, measure::Measure, test_dir::TestDir};
+
+    fn gimlet() -> Recipie {
+        Recipie {
+            name: "Gimlet".into(),
+            short_description: "Gin and lime".into(),
+            components: vec![
+                Component {
+                    ingredient: IngredientSelector {
+                        tags: Some(vec![IngredientTag {
+                            value: "Gin".into(),
+                        }]),
+                        quality: Some(Quality::High),
+                        ..Default::default()
+                    },
+                    amount: Measure::Oz(2.0),
+                },
+                Component {
+                    ingredient: IngredientSelector {
+                        name: Some("Lime juice".into()),
+                        ..Default::default()
+                    },
+                    amount: Measure::Oz(0.75),
+                },
+            ],
+            instructions: vec!["Shake with ice".into(), "Strain".into()],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_recipie_round_trip() {
+        let dir = TestDir::new();
+        let mut store = SqliteRecipieStore::from_config(dir.to_path_buf()).unwrap();
+        let id = store.register(gimlet());
+        assert!(store.save().is_ok());
+        drop(store);
+        let loaded = SqliteRecipieStore::from_config(dir.to_path_buf()).unwrap();
+        let recipie = loaded.get_entry(id).unwrap();
+        assert_eq!(recipie.name, "Gimlet");
+        assert_eq!(recipie.instructions, vec!["Shake with ice", "Strain"]);
+        assert_eq!(recipie.components.len(), 2);
+        let gin = &recipie.components[0];
+        assert_eq!(gin.ingredient.tags.as_ref().unwrap()[0].value, "Gin");
+        assert_eq!(gin.ingredient.quality, Some(Quality::High));
+        assert!(gin.amount == Measure::Oz(2.0));
+        assert_eq!(
+            recipie.components[1].ingredient.name.as_deref(),
+            Some("Lime juice")
+        );
+    }
+
+    #[test]
+    fn test_trash_round_trip() {
+        let dir = TestDir::new();
+        let mut store = SqliteRecipieStore::from_config(dir.to_path_buf()).unwrap();
+        let id = store.register(gimlet());
+        assert!(store.move_to_trash(id).is_ok());
+        assert!(store.save().is_ok());
+        drop(store);
+        let mut loaded = SqliteRecipieStore::from_config(dir.to_path_buf()).unwrap();
+        assert!(loaded.get_entry(id).is_none());
+        assert!(loaded.restore_from_trash(id).is_ok());
+        assert_eq!(loaded.get_entry(id).unwrap().components.len(), 2);
+    }
+
+    #[test]
+    fn test_ingredient_round_trip_and_delete() {
+        let dir = TestDir::new();
+        let mut store = SqliteIngredientStore::from_config(dir.to_path_buf()).unwrap();
+        let gin = store.register(Ingredient {
+            name: "Plymouth".into(),
+            quality: Quality::High,
+            tags: vec![IngredientTag {
+                value: "Gin".into(),
+            }],
+            stock: 2,
+            is_liquor: true,
+            bottle_ml: Some(750),
+            poured_ml: 120,
+            extra: [("Supplier".to_string(), "Bob's".to_string())].into(),
+        });
+        let lime = store.register(Ingredient {
+            name: "Lime".into(),
+            ..Default::default()
+        });
+        assert!(store.save().is_ok());
+        store.deregister(lime).unwrap();
+        assert!(store.save().is_ok());
+        let loaded = SqliteIngredientStore::from_config_read_only(dir.to_path_buf()).unwrap();
+        assert_eq!(loaded.get_entries().len(), 1);
+        assert_eq!(loaded.get_entry(gin), store.get_entry(gin));
+    }
+
+    #[test]
+    fn test_save_writes_only_changed_rows() {
+        let dir = TestDir::new();
+        let db = dir.join(DB_NAME);
+        let mut store = IngredientStore::default();
+        let gin = store.register(Ingredient {
+            name: "Gin".into(),
+            ..Default::default()
+        });
+        let lime = store.register(Ingredient {
+            name: "Lime".into(),
+            ..Default::default()
+        });
+        SqliteIngredientStore::write(&store, &db, None).unwrap();
+
+        // Someone else restocks the gin
+        let mut theirs = SqliteIngredientStore::read(&db).unwrap();
+        theirs.dirty_mut().clear();
+        theirs.get_entry_mut(gin).unwrap().stock = 3;
+        SqliteIngredientStore::write(&theirs, &db, theirs.dirty().changed_ids()).unwrap();
+
+        store.dirty_mut().clear();
+        store.get_entry_mut(lime).unwrap().stock = 5;
+        SqliteIngredientStore::write(&store, &db, store.dirty().changed_ids()).unwrap();
+        let loaded = SqliteIngredientStore::read(&db).unwrap();
+        assert_eq!(loaded.get_entry(gin).unwrap().stock, 3);
+        assert_eq!(loaded.get_entry(lime).unwrap().stock, 5);
+
+        store.move_to_trash(lime).unwrap();
+        SqliteIngredientStore::write(&store, &db, store.dirty().changed_ids()).unwrap();
+        let loaded = SqliteIngredientStore::read(&db).unwrap();
+        assert!(loaded.get_entry(lime).is_none());
+        assert!(loaded.trash_bin().get(lime).is_some());
+        assert_eq!(loaded.get_entry(gin).unwrap().stock, 3);
+    }
+
+    #[test]
+    fn test_locked_and_changed_on_disk() {
+        let dir = TestDir::new();
+        let mut ingredients = SqliteIngredientStore::from_config(dir.to_path_buf())
+            .unwrap()
+            .into_inner();
+        assert!(matches!(
+            SqliteIngredientStore::from_config(dir.to_path_buf()),
+            Err(StoreLoadError::Locked(_))
+        ));
+        assert!(matches!(
+            IngredientStore::from_config(dir.to_path_buf()),
+            Err(StoreLoadError::Locked(_))
+        ));
+        let mut recipies = SqliteRecipieStore::from_config(dir.to_path_buf())
+            .unwrap()
+            .into_inner();
+        ingredients.register(Ingredient::default());
+        assert!(ingredients.save().is_ok());
+        recipies.register(gimlet());
+        assert!(recipies.save().is_ok());
+        // The other store writing to the same database isn't a conflict
+        assert!(!ingredients.changed_on_disk());
+
+        let mut theirs = SqliteIngredientStore::from_config_read_only(dir.to_path_buf())
+            .unwrap()
+            .into_inner();
+        assert!(theirs.is_read_only());
+        theirs.register(Ingredient::default());
+        SqliteIngredientStore::write(&theirs, &dir.join(DB_NAME), None).unwrap();
+        assert!(ingredients.changed_on_disk());
+        ingredients.register(Ingredient::default());
+        assert!(matches!(
+            ingredients.save(),
+            Err(StoreError::ChangedOnDisk(_))
+        ));
+        assert!(ingredients.merge_from_disk().is_ok());
+        assert!(ingredients.save().is_ok());
+        assert_eq!(ingredients.get_entries().len(), 3);
+        assert!(ingredients.reload_from_disk().is_ok());
+        assert_eq!(ingredients.get_entries().len(), 3);
+    }
+
+    #[test]
+    fn test_import_json() {
+        let dir = TestDir::new();
+        let mut recipies = RecipieStore::from_config(dir.to_path_buf()).unwrap();
+        let id = recipies.register(gimlet());
+        assert!(recipies.save().is_ok());
+        let mut ingredients = IngredientStore::from_config(dir.to_path_buf()).unwrap();
+        ingredients.register(Ingredient {
+            name: "Lime juice".into(),
+            stock: 1,
+            ..Default::default()
+        });
+        assert!(ingredients.save().is_ok());
+        drop((recipies, ingredients));
+        import_json(&dir).unwrap();
+        let recipies = SqliteRecipieStore::from_config(dir.to_path_buf()).unwrap();
+        assert_eq!(recipies.get_entry(id).unwrap().name, "Gimlet");
+        let ingredients = SqliteIngredientStore::from_config(dir.to_path_buf()).unwrap();
+        assert_eq!(ingredients.get_entries().len(), 1);
+        // Nothing's been edited since loading
+        assert!(!recipies.is_dirty());
+        assert!(!ingredients.is_dirty());
+    }
+
+    #[test]
+    fn test_read_only_leaves_disk_alone() {
+        let dir = TestDir::new();
+        let ingredients = SqliteIngredientStore::from_config_read_only(dir.to_path_buf()).unwrap();
+        assert!(ingredients.get_entries().is_empty());
+        let recipies = SqliteRecipieStore::from_config_read_only(dir.to_path_buf()).unwrap();
+        assert!(recipies.get_entries().is_empty());
+        assert!(!dir.join(DB_NAME).exists());
+
+        // A database from before the added columns reads without being upgraded
+        let conn = Connection::open(dir.join(DB_NAME)).unwrap();
+        conn.execute_batch(
+            "CREATE TABLE ingredients (
+                id TEXT PRIMARY KEY, name TEXT NOT NULL, quality TEXT NOT NULL,
+                stock INTEGER NOT NULL, is_liquor INTEGER NOT NULL
+            );
+            CREATE TABLE tags (id INTEGER PRIMARY KEY, value TEXT NOT NULL UNIQUE);
+            CREATE TABLE ingredient_tags (
+                ingredient_id TEXT NOT NULL, position INTEGER NOT NULL, tag_id INTEGER NOT NULL
+            );",
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO ingredients VALUES (?1, 'Gin', ?2, 1, 1)",
+            params![Uuid::new_v4().to_string(), Quality::default().to_string()],
+        )
+        .unwrap();
+        let ingredients = SqliteIngredientStore::from_config_read_only(dir.to_path_buf()).unwrap();
+        assert_eq!(ingredients.get_entries()[0].name, "Gin");
+        assert!(!has_column(&conn, "ingredients", "extra").unwrap());
+        assert!(!has_table(&conn, "versions").unwrap());
+    }
+}
@@ -1,24 +1,71 @@
-use std::{fs::File, io::BufReader, path::PathBuf};
+use std::{
+    collections::HashSet,
+    fmt,
+    fs::File,
+    io::{BufReader, BufWriter, Read, Write},
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime},
+};
 
-use serde::{Serialize, de::DeserializeOwned};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use serde_json::Value;
 
-use crate::builder::Builder;
+use crate::{builder::Builder, trash::TrashBin};
 
+/// How many previous generations of a store file we keep around
+pub const BACKUP_GENERATIONS: usize = 5;
+
+const BACKUP_TIMESTAMP_FORMAT: &str = "%Y%m%d-%H%M%S%3f";
+
+/// Files written before the schema_version envelope existed are treated as version 0
+pub const LEGACY_SCHEMA_VERSION: u32 = 0;
+
+/// One step in a store's migration registry, upgrading the `data` of a version `from` file
+/// to version `from + 1`
+pub struct Migration {
+    pub from: u32,
+    pub migrate: fn(Value) -> Result<Value, String>,
+}
+
+/// What actually lands on disk
+#[derive(Serialize)]
+struct Envelope<'a, S> {
+    schema_version: u32,
+    data: &'a S,
+}
+
+#[derive(Deserialize)]
+struct OwnedEnvelope<S> {
+    data: S,
+}
+
+/// A collection of `T`s keyed by uuid, persisted to a JSON file in a data directory
 pub trait Store<T>: Serialize + DeserializeOwned + Default {
+    /// Version of the on-disk layout this build reads and writes
+    const SCHEMA_VERSION: u32;
+
     fn get_json_name() -> PathBuf;
 
+    /// Steps upgrading older files, one per version up to SCHEMA_VERSION
+    fn migrations() -> Vec<Migration>;
+
     fn set_config_path(&mut self, path: PathBuf);
 
     fn get_config_path(&self) -> Option<PathBuf>;
 
     fn register(&mut self, entry: T) -> uuid::Uuid;
 
-    fn deregister(&mut self, id: uuid::Uuid) -> bool;
+    /// Adds an entry under a known id, replacing whatever was there
+    fn insert(&mut self, id: uuid::Uuid, entry: T);
+
+    fn deregister(&mut self, id: uuid::Uuid) -> Result<(), StoreError>;
 
     fn get_entries(&self) -> Vec<T>;
 
     fn get_entries_mut(&mut self) -> Vec<&mut T>;
 
+    fn get_entries_with_ids(&self) -> Vec<(uuid::Uuid, T)>;
+
     fn get_entry(&self, id: uuid::Uuid) -> Option<T>;
 
     fn get_entry_mut(&mut self, id: uuid::Uuid) -> Option<&mut T>;
@@ -27,42 +74,1060 @@ pub trait Store<T>: Serialize + DeserializeOwned + Default {
         self.register(builder.build())
     }
 
-    fn from_config(config_dir: PathBuf) -> Result<Self, Box<dyn std::error::Error>> {
+    /// Opens the store for editing. Fails with `StoreLoadError::Locked` while another
+    /// window or process has the same store open.
+    fn from_config(config_dir: PathBuf) -> Result<Self, StoreLoadError> {
         let path = config_dir.join(Self::get_json_name());
-        if std::fs::exists(&path)? {
-            let cfg_file = File::open(&path)?;
-            let cfg = BufReader::new(cfg_file);
-            let mut store: Self = serde_json::from_reader(cfg)?;
-            store.set_config_path(path);
-            Ok(store)
+        std::fs::create_dir_all(&config_dir).map_err(|e| StoreLoadError::unreadable(&path, e))?;
+        let lock = lock_store_file(&path)?;
+        let exists = std::fs::exists(&path).map_err(|e| StoreLoadError::unreadable(&path, e))?;
+        if !exists {
+            std::fs::File::create_new(&path).map_err(|e| StoreLoadError::unreadable(&path, e))?;
+        }
+        let is_empty = std::fs::metadata(&path)
+            .map_err(|e| StoreLoadError::unreadable(&path, e))?
+            .len()
+            == 0;
+        // A freshly created (or never saved) store file is empty rather than corrupt
+        let mut store = if is_empty {
+            Self::default()
         } else {
-            std::fs::File::create_new(&path)?;
-            let mut store = Self::default();
-            store.set_config_path(path);
-            Ok(store)
+            let text = read_store_file(&path)?;
+            let version = schema_version(&path, &text).map_err(|e| e.quarantine())?;
+            if version < Self::SCHEMA_VERSION {
+                backup_pre_migration(&path, version);
+            }
+            Self::load_versioned(&path, &text, version).map_err(|e| e.quarantine())?
+        };
+        *store.disk_mut() = DiskState {
+            _lock: lock,
+            read_only: false,
+            stamp: Self::stamp(&path),
+        };
+        store.set_config_path(path);
+        Ok(store)
+    }
+
+    /// Opens a store someone else has locked, just to look at. It is never saved, and
+    /// nothing on disk is touched: no migration backups, no quarantining.
+    fn from_config_read_only(config_dir: PathBuf) -> Result<Self, StoreLoadError> {
+        let path = config_dir.join(Self::get_json_name());
+        let mut store = load_or_empty::<T, Self>(&path)?;
+        *store.disk_mut() = DiskState {
+            _lock: None,
+            read_only: true,
+            stamp: Self::stamp(&path),
+        };
+        store.set_config_path(path);
+        Ok(store)
+    }
+
+    fn load_from(path: &Path) -> Result<Self, StoreLoadError> {
+        load_json(path)
+    }
+
+    /// Takes over the store file at `path` for a store that didn't come from `from_config`,
+    /// like one recovered after a failed load: locks it and notes what it looks like now.
+    /// Falls back to read-only while someone else has it open.
+    fn claim(&mut self, path: PathBuf) -> Result<(), StoreLoadError> {
+        let stamp = Self::stamp(&path);
+        *self.disk_mut() = match lock_store_file(&Self::lock_path(&path)) {
+            Ok(lock) => DiskState::new(lock, false, stamp),
+            Err(StoreLoadError::Locked(_)) => DiskState::new(None, true, stamp),
+            Err(err) => return Err(err),
+        };
+        self.set_config_path(path);
+        Ok(())
+    }
+
+    /// The file `lock_store_file` locks for the store file at `path`
+    fn lock_path(path: &Path) -> PathBuf {
+        path.to_path_buf()
+    }
+
+    /// What the store file looks like now, compared against to notice someone else's writes
+    fn stamp(path: &Path) -> Option<FileStamp> {
+        FileStamp::of(path)
+    }
+
+    /// Deserializes a store file, running it through the migration registry if it is old
+    fn load_versioned(path: &Path, text: &str, version: u32) -> Result<Self, StoreLoadError> {
+        if version > Self::SCHEMA_VERSION {
+            return Err(StoreLoadError::UnsupportedVersion {
+                path: path.to_path_buf(),
+                found: version,
+                supported: Self::SCHEMA_VERSION,
+            });
+        }
+        if version == Self::SCHEMA_VERSION {
+            // Straight from the text, so errors keep their line and column
+            return serde_json::from_str::<OwnedEnvelope<Self>>(text)
+                .map(|envelope| envelope.data)
+                .map_err(|e| StoreLoadError::from_json(path, e));
+        }
+        let mut data: Value =
+            serde_json::from_str(text).map_err(|e| StoreLoadError::from_json(path, e))?;
+        if version != LEGACY_SCHEMA_VERSION {
+            data = data["data"].take();
         }
+        let migrations = Self::migrations();
+        for from in version..Self::SCHEMA_VERSION {
+            let step = migrations.iter().find(|m| m.from == from);
+            data = match step {
+                Some(step) => (step.migrate)(data),
+                None => Err(format!("No migration from schema version {}", from)),
+            }
+            .map_err(|message| StoreLoadError::SchemaMismatch {
+                path: path.to_path_buf(),
+                line: 0,
+                column: 0,
+                message,
+                quarantined: None,
+            })?;
+        }
+        serde_json::from_value(data).map_err(|e| StoreLoadError::from_json(path, e))
+    }
+
+    /// Unsaved changes since the store was loaded or last saved
+    fn dirty(&self) -> &DirtyState;
+
+    fn dirty_mut(&mut self) -> &mut DirtyState;
+
+    fn is_dirty(&self) -> bool {
+        self.dirty().is_dirty()
     }
 
-    fn new() -> Self {
-        //let cfg_dir = dirs::config_local_dir().unwrap_or(PathBuf::from("."));
-        let cfg_dir = PathBuf::from("./");
-        match Self::from_config(cfg_dir) {
-            Ok(store) => store,
-            Err(_) => Self::default(),
+    /// What we know about the store file: our lock on it and what it looked like when we
+    /// last loaded or saved it
+    fn disk(&self) -> &DiskState;
+
+    fn disk_mut(&mut self) -> &mut DiskState;
+
+    fn is_read_only(&self) -> bool {
+        self.disk().read_only
+    }
+
+    /// True when someone else has written the store file since we loaded or saved it
+    fn changed_on_disk(&self) -> bool {
+        match (self.get_config_path(), self.disk().stamp) {
+            (Some(path), Some(stamp)) => Self::stamp(&path) != Some(stamp),
+            _ => false,
         }
     }
 
-    fn save(&self) -> bool {
+    /// Refuses to save read-only stores, and stores whose file changed under us; use
+    /// `reload_from_disk`, `merge_from_disk` or `overwrite_disk` to settle the latter
+    fn save(&mut self) -> Result<(), StoreError> {
+        let path = self.get_config_path().ok_or(StoreError::NotLoaded)?;
+        if self.is_read_only() {
+            return Err(StoreError::ReadOnly(path));
+        }
+        if self.changed_on_disk() {
+            return Err(StoreError::ChangedOnDisk(path));
+        }
+        self.save_to(&path)?;
+        self.dirty_mut().clear();
+        self.disk_mut().stamp = Self::stamp(&path);
+        Ok(())
+    }
+
+    /// Throws away unsaved changes in favour of what's on disk now
+    fn reload_from_disk(&mut self) -> Result<(), StoreError> {
+        let Some(path) = self.get_config_path() else {
+            return Ok(());
+        };
+        let stamp = Self::stamp(&path);
+        let mut store = load_or_empty::<T, Self>(&path)?;
+        store.set_config_path(path);
+        *store.disk_mut() = std::mem::take(self.disk_mut());
+        *self = store;
+        self.disk_mut().stamp = stamp;
+        Ok(())
+    }
+
+    /// Keeps our version of everything we have, and picks up entries that only exist on
+    /// disk. Entries we removed but are still on disk come back.
+    fn merge_from_disk(&mut self) -> Result<(), StoreError> {
+        let Some(path) = self.get_config_path() else {
+            return Ok(());
+        };
+        let stamp = Self::stamp(&path);
+        let theirs = load_or_empty::<T, Self>(&path)?;
+        for (id, entry) in theirs.get_entries_with_ids() {
+            if self.get_entry(id).is_none() {
+                self.insert(id, entry);
+            }
+        }
+        self.disk_mut().stamp = stamp;
+        self.dirty_mut().touch();
+        Ok(())
+    }
+
+    /// Saves over whatever someone else wrote to the store file
+    fn overwrite_disk(&mut self) -> Result<(), StoreError> {
+        self.disk_mut().stamp = self.get_config_path().and_then(|p| Self::stamp(&p));
+        self.save()
+    }
+
+    /// Parks our unsaved version next to the store file when it can't go in the file itself
+    fn save_conflict_copy(&self) -> Result<PathBuf, StoreError> {
+        let path = self.get_config_path().ok_or(StoreError::NotLoaded)?;
+        let timestamp = chrono::Local::now().format(BACKUP_TIMESTAMP_FORMAT);
+        // Named after the store rather than its file, which may be shared with other stores
+        let name = Self::get_json_name();
+        let stem = name.file_stem().unwrap_or_default().to_string_lossy();
+        let copy = path.with_file_name(format!("{}.conflict-{}.json", stem, timestamp));
+        self.save_to(&copy)?;
+        Ok(copy)
+    }
+
+    fn save_to(&self, path: &Path) -> Result<(), StoreError> {
+        save_json(self, path)
+    }
+
+    /// Backups of this store's file, newest first
+    fn list_backups(&self) -> Vec<PathBuf> {
         match self.get_config_path() {
-            Some(path) => self.save_to(&path),
-            None => false,
+            Some(path) => list_backups(&path),
+            None => vec![],
         }
     }
 
-    fn save_to(&self, path: &PathBuf) -> bool {
-        match File::create(path) {
-            Ok(file) => serde_json::to_writer(file, self).is_ok(),
-            _ => false,
+    /// Deleted entries that can still be restored
+    fn trash_bin(&self) -> &TrashBin<T>;
+
+    fn trash_bin_mut(&mut self) -> &mut TrashBin<T>;
+
+    /// Deletes an entry, keeping it and its id in the trash bin so it can be restored
+    fn move_to_trash(&mut self, id: uuid::Uuid) -> Result<(), StoreError> {
+        let entry = self.get_entry(id).ok_or(StoreError::UnknownId(id))?;
+        self.deregister(id)?;
+        let now = chrono::Utc::now().timestamp();
+        self.trash_bin_mut().put(id, entry, now);
+        Ok(())
+    }
+
+    fn restore_from_trash(&mut self, id: uuid::Uuid) -> Result<(), StoreError> {
+        let trashed = self
+            .trash_bin_mut()
+            .take(id)
+            .ok_or(StoreError::UnknownId(id))?;
+        self.insert(id, trashed.entry);
+        Ok(())
+    }
+
+    /// Deletes a trashed entry for good
+    fn purge(&mut self, id: uuid::Uuid) -> Result<(), StoreError> {
+        self.trash_bin_mut()
+            .take(id)
+            .ok_or(StoreError::UnknownId(id))?;
+        self.dirty_mut().touch_id(id);
+        Ok(())
+    }
+
+    /// Purges everything that's been in the trash for longer than `max_age`
+    fn purge_older_than(&mut self, max_age: Duration) -> usize {
+        let expired = self
+            .trash_bin()
+            .expired(max_age, chrono::Utc::now().timestamp());
+        for id in &expired {
+            let _ = self.purge(*id);
+        }
+        expired.len()
+    }
+
+    /// Replaces the contents of this store with a backup, keeping the current config path
+    fn restore_backup(&mut self, backup: &Path) -> Result<(), StoreError> {
+        let mut store = Self::load_from(backup)?;
+        if let Some(path) = self.get_config_path() {
+            store.set_config_path(path);
+        }
+        *store.disk_mut() = std::mem::take(self.disk_mut());
+        *self = store;
+        // What's in memory no longer matches the store file
+        self.dirty_mut().touch();
+        Ok(())
+    }
+}
+
+/// Tracks when a store picked up changes that haven't been saved yet, and which entries
+/// they were to, so a database only has to write those rows
+#[derive(Clone, Default)]
+pub struct DirtyState {
+    first_change: Option<Instant>,
+    last_change: Option<Instant>,
+    changed: HashSet<uuid::Uuid>,
+    // Set by changes that can't be pinned to particular entries
+    changed_all: bool,
+}
+
+impl DirtyState {
+    /// A change that could have touched any entry
+    pub fn touch(&mut self) {
+        self.changed_all = true;
+        self.mark_changed();
+    }
+
+    /// A change to the entry with `id`, including adding, removing or trashing it
+    pub fn touch_id(&mut self, id: uuid::Uuid) {
+        self.changed.insert(id);
+        self.mark_changed();
+    }
+
+    fn mark_changed(&mut self) {
+        let now = Instant::now();
+        self.first_change.get_or_insert(now);
+        self.last_change = Some(now);
+    }
+
+    /// Ids changed since the last save, or None when anything might have
+    pub fn changed_ids(&self) -> Option<&HashSet<uuid::Uuid>> {
+        (!self.changed_all).then_some(&self.changed)
+    }
+
+    pub fn clear(&mut self) {
+        *self = DirtyState::default();
+    }
+
+    /// Puts off the next autosave by a full debounce, e.g. after a save failed
+    pub fn postpone(&mut self) {
+        if self.is_dirty() {
+            let now = Instant::now();
+            self.first_change = Some(now);
+            self.last_change = Some(now);
+        }
+    }
+
+    pub fn is_dirty(&self) -> bool {
+        self.last_change.is_some()
+    }
+
+    /// How long until an autosave is due: once changes have settled for `debounce`, or
+    /// once they've been piling up for `max_delay` without settling
+    pub fn save_due_in(&self, debounce: Duration, max_delay: Duration) -> Option<Duration> {
+        let (first, last) = (self.first_change?, self.last_change?);
+        let settled = (last + debounce).saturating_duration_since(Instant::now());
+        let overdue = (first + max_delay).saturating_duration_since(Instant::now());
+        Some(settled.min(overdue))
+    }
+}
+
+/// What a store file looked like, to notice when someone else wrote to it
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum FileStamp {
+    /// Size and modification time of the file
+    File {
+        modified: Option<SystemTime>,
+        len: u64,
+    },
+    /// How many times the store was written, for stores sharing a database file
+    Version(i64),
+}
+
+impl FileStamp {
+    pub fn of(path: &Path) -> Option<FileStamp> {
+        let metadata = std::fs::metadata(path).ok()?;
+        Some(FileStamp::File {
+            modified: metadata.modified().ok(),
+            len: metadata.len(),
+        })
+    }
+}
+
+/// A store's relationship with its file on disk
+#[derive(Default)]
+pub struct DiskState {
+    // Held for as long as the store is open; dropping it releases the lock
+    _lock: Option<File>,
+    read_only: bool,
+    stamp: Option<FileStamp>,
+}
+
+impl DiskState {
+    /// For stores that open their files themselves. The lock is released when the store
+    /// is dropped.
+    pub fn new(lock: Option<File>, read_only: bool, stamp: Option<FileStamp>) -> Self {
+        DiskState {
+            _lock: lock,
+            read_only,
+            stamp,
+        }
+    }
+}
+
+/// Why something done to an open store didn't work
+#[derive(Debug)]
+pub enum StoreError {
+    // The store never loaded, so there's no file to save it to
+    NotLoaded,
+    ReadOnly(PathBuf),
+    // Someone else wrote to the store file since we loaded it
+    ChangedOnDisk(PathBuf),
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    Database {
+        path: PathBuf,
+        message: String,
+    },
+    UnknownId(uuid::Uuid),
+    Load(StoreLoadError),
+}
+
+impl From<StoreLoadError> for StoreError {
+    fn from(err: StoreLoadError) -> Self {
+        StoreError::Load(err)
+    }
+}
+
+impl fmt::Display for StoreError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StoreError::NotLoaded => write!(f, "the store was never loaded, so it can't be saved"),
+            StoreError::ReadOnly(path) => write!(f, "{} was opened read-only", path.display()),
+            StoreError::ChangedOnDisk(path) => write!(
+                f,
+                "{} was changed somewhere else since it was loaded",
+                path.display()
+            ),
+            StoreError::Io { path, source } => {
+                write!(f, "couldn't write {}: {}", path.display(), source)
+            }
+            StoreError::Database { path, message } => {
+                write!(f, "couldn't write {}: {}", path.display(), message)
+            }
+            StoreError::UnknownId(id) => write!(f, "no entry with id {}", id),
+            StoreError::Load(err) => err.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for StoreError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            StoreError::Io { source, .. } => Some(source),
+            StoreError::Load(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum StoreLoadError {
+    Missing(PathBuf),
+    Unreadable {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    Malformed {
+        path: PathBuf,
+        line: usize,
+        column: usize,
+        message: String,
+        quarantined: Option<PathBuf>,
+    },
+    // Valid JSON, but not the shape of the store we expected
+    SchemaMismatch {
+        path: PathBuf,
+        line: usize,
+        column: usize,
+        message: String,
+        quarantined: Option<PathBuf>,
+    },
+    // The storage backend itself failed, e.g. a broken SQLite database
+    Database {
+        path: PathBuf,
+        message: String,
+    },
+    // Written by a newer version of the app than this one
+    UnsupportedVersion {
+        path: PathBuf,
+        found: u32,
+        supported: u32,
+    },
+    // Another window or process has the store open
+    Locked(PathBuf),
+}
+
+impl StoreLoadError {
+    fn unreadable(path: &Path, source: std::io::Error) -> StoreLoadError {
+        match source.kind() {
+            std::io::ErrorKind::NotFound => StoreLoadError::Missing(path.to_path_buf()),
+            _ => StoreLoadError::Unreadable {
+                path: path.to_path_buf(),
+                source,
+            },
+        }
+    }
+
+    fn from_json(path: &Path, err: serde_json::Error) -> StoreLoadError {
+        let path = path.to_path_buf();
+        let (line, column) = (err.line(), err.column());
+        // serde_json tacks the position onto its message, we report it separately
+        let message = err.to_string();
+        let message = message
+            .strip_suffix(&format!(" at line {} column {}", line, column))
+            .unwrap_or(&message)
+            .to_string();
+        match err.classify() {
+            serde_json::error::Category::Io => StoreLoadError::Unreadable {
+                path,
+                source: err.into(),
+            },
+            serde_json::error::Category::Data => StoreLoadError::SchemaMismatch {
+                path,
+                line,
+                column,
+                message,
+                quarantined: None,
+            },
+            _ => StoreLoadError::Malformed {
+                path,
+                line,
+                column,
+                message,
+                quarantined: None,
+            },
+        }
+    }
+
+    /// The store file this error is about
+    pub fn path(&self) -> &PathBuf {
+        match self {
+            StoreLoadError::Missing(path) => path,
+            StoreLoadError::Unreadable { path, .. } => path,
+            StoreLoadError::Malformed { path, .. } => path,
+            StoreLoadError::SchemaMismatch { path, .. } => path,
+            StoreLoadError::Database { path, .. } => path,
+            StoreLoadError::UnsupportedVersion { path, .. } => path,
+            StoreLoadError::Locked(path) => path,
+        }
+    }
+
+    /// Where the broken file was moved to, if it was
+    pub fn quarantined(&self) -> Option<&PathBuf> {
+        match self {
+            StoreLoadError::Malformed { quarantined, .. } => quarantined.as_ref(),
+            StoreLoadError::SchemaMismatch { quarantined, .. } => quarantined.as_ref(),
+            _ => None,
+        }
+    }
+
+    /// Moves a file we couldn't parse out of the way, so that nothing saves over it
+    fn quarantine(mut self) -> StoreLoadError {
+        if let StoreLoadError::Malformed {
+            path, quarantined, ..
+        }
+        | StoreLoadError::SchemaMismatch {
+            path, quarantined, ..
+        } = &mut self
+        {
+            let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+            let timestamp = chrono::Local::now().format(BACKUP_TIMESTAMP_FORMAT);
+            let target = path.with_file_name(format!("{}.corrupt-{}.json", stem, timestamp));
+            if std::fs::rename(&path, &target).is_ok() {
+                *quarantined = Some(target);
+            }
+        }
+        self
+    }
+}
+
+impl fmt::Display for StoreLoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StoreLoadError::Missing(path) => write!(f, "{} does not exist", path.display()),
+            StoreLoadError::Unreadable { path, source } => {
+                write!(f, "{} could not be read: {}", path.display(), source)
+            }
+            StoreLoadError::Malformed {
+                path,
+                line,
+                column,
+                message,
+                ..
+            } => write!(
+                f,
+                "{} is not valid JSON (line {}, column {}): {}",
+                path.display(),
+                line,
+                column,
+                message
+            ),
+            StoreLoadError::SchemaMismatch {
+                path,
+                line: 0,
+                message,
+                ..
+            } => write!(
+                f,
+                "{} does not contain the expected data: {}",
+                path.display(),
+                message
+            ),
+            StoreLoadError::SchemaMismatch {
+                path,
+                line,
+                column,
+                message,
+                ..
+            } => write!(
+                f,
+                "{} does not contain the expected data (line {}, column {}): {}",
+                path.display(),
+                line,
+                column,
+                message
+            ),
+            StoreLoadError::Database { path, message } => {
+                write!(f, "{} could not be opened: {}", path.display(), message)
+            }
+            StoreLoadError::UnsupportedVersion {
+                path,
+                found,
+                supported,
+            } => write!(
+                f,
+                "{} uses schema version {}, but this version of the app only understands up to {}",
+                path.display(),
+                found,
+                supported
+            ),
+            StoreLoadError::Locked(path) => write!(
+                f,
+                "{} is already open in another window or on another computer",
+                path.display()
+            ),
         }
     }
 }
+
+impl std::error::Error for StoreLoadError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            StoreLoadError::Unreadable { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+/// Takes an advisory lock on a `.lock` file next to the store. The store file itself can't
+/// carry the lock, since saving renames a new file over it. Filesystems without locking
+/// support just go without.
+pub fn lock_store_file(path: &Path) -> Result<Option<File>, StoreLoadError> {
+    let lock_path = path.with_extension("lock");
+    let file = File::options()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(&lock_path)
+        .map_err(|e| StoreLoadError::unreadable(&lock_path, e))?;
+    match file.try_lock() {
+        Ok(()) => Ok(Some(file)),
+        Err(std::fs::TryLockError::WouldBlock) => Err(StoreLoadError::Locked(path.to_path_buf())),
+        Err(std::fs::TryLockError::Error(_)) => Ok(None),
+    }
+}
+
+/// Reads a store file written by `save_json`, migrating it if it is old
+pub fn load_json<T, S: Store<T>>(path: &Path) -> Result<S, StoreLoadError> {
+    let text = read_store_file(path)?;
+    let version = schema_version(path, &text)?;
+    S::load_versioned(path, &text, version)
+}
+
+/// Writes to a temp file and renames it over the store file, so a crash mid-write
+/// never leaves us with a truncated store. The previous contents are kept as a backup.
+pub fn save_json<T, S: Store<T>>(store: &S, path: &Path) -> Result<(), StoreError> {
+    let tmp_path = path.with_extension("json.tmp");
+    let written = File::create(&tmp_path).and_then(|file| {
+        let mut writer = BufWriter::new(file);
+        let envelope = Envelope {
+            schema_version: S::SCHEMA_VERSION,
+            data: store,
+        };
+        serde_json::to_writer(&mut writer, &envelope)?;
+        writer.flush()?;
+        writer.get_ref().sync_all()
+    });
+    if let Err(source) = written {
+        let _ = std::fs::remove_file(&tmp_path);
+        return Err(StoreError::Io {
+            path: path.to_path_buf(),
+            source,
+        });
+    }
+    backup_store_file(path);
+    std::fs::rename(&tmp_path, path).map_err(|source| StoreError::Io {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    sync_parent_dir(path);
+    Ok(())
+}
+
+/// Like `Store::load_from`, but an empty or missing file is an empty store
+fn load_or_empty<T, S: Store<T>>(path: &Path) -> Result<S, StoreLoadError> {
+    let has_contents = std::fs::metadata(path)
+        .map(|m| m.len() > 0)
+        .unwrap_or(false);
+    if has_contents {
+        S::load_from(path)
+    } else {
+        Ok(S::default())
+    }
+}
+
+fn read_store_file(path: &Path) -> Result<String, StoreLoadError> {
+    let cfg_file = File::open(path).map_err(|e| StoreLoadError::unreadable(path, e))?;
+    let mut text = String::new();
+    BufReader::new(cfg_file)
+        .read_to_string(&mut text)
+        .map_err(|e| StoreLoadError::unreadable(path, e))?;
+    Ok(text)
+}
+
+/// Pulls the schema_version out of a store file's envelope, files without one are legacy
+fn schema_version(path: &Path, text: &str) -> Result<u32, StoreLoadError> {
+    #[derive(Deserialize)]
+    struct Version {
+        schema_version: Option<u32>,
+    }
+    match serde_json::from_str::<Version>(text) {
+        Ok(version) => Ok(version.schema_version.unwrap_or(LEGACY_SCHEMA_VERSION)),
+        // Anything that isn't an object (or has a nonsense version) still gets a chance to
+        // produce a proper error when it's deserialized as a store
+        Err(e) if e.is_data() => Ok(LEGACY_SCHEMA_VERSION),
+        Err(e) => Err(StoreLoadError::from_json(path, e)),
+    }
+}
+
+/// Keeps a copy of a store file as it was before we migrated it
+fn backup_pre_migration(path: &Path, version: u32) {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let timestamp = chrono::Local::now().format(BACKUP_TIMESTAMP_FORMAT);
+    let backup = path.with_file_name(format!(
+        "{}.pre-migration-v{}-{}.json",
+        stem, version, timestamp
+    ));
+    let _ = std::fs::copy(path, backup);
+}
+
+/// Copies the current store file into a timestamped backup next to it and prunes old generations
+fn backup_store_file(path: &Path) {
+    let has_contents = std::fs::metadata(path)
+        .map(|m| m.len() > 0)
+        .unwrap_or(false);
+    if !has_contents {
+        return;
+    }
+    let timestamp = chrono::Local::now().format(BACKUP_TIMESTAMP_FORMAT);
+    let backup = path.with_file_name(format!("{}{}.json", backup_prefix(path), timestamp));
+    if std::fs::copy(path, backup).is_err() {
+        return;
+    }
+    for old in list_backups(path).into_iter().skip(BACKUP_GENERATIONS) {
+        let _ = std::fs::remove_file(old);
+    }
+}
+
+fn backup_prefix(path: &Path) -> String {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    format!("{}.backup-", stem)
+}
+
+pub fn list_backups(path: &Path) -> Vec<PathBuf> {
+    let prefix = backup_prefix(path);
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+    let mut backups: Vec<PathBuf> = match std::fs::read_dir(dir) {
+        Ok(entries) => entries
+            .filter_map(|e| e.ok())
+            .map(|e| e.path())
+            .filter(|p| {
+                let name = p.file_name().unwrap_or_default().to_string_lossy();
+                name.starts_with(&prefix) && name.ends_with(".json")
+            })
+            .collect(),
+        Err(_) => vec![],
+    };
+    // Timestamps are fixed width, so sorting by name sorts by age
+    backups.sort();
+    backups.reverse();
+    backups
+}
+
+/// Human readable time a backup was taken, parsed back out of its file name
+pub fn backup_label(backup: &Path) -> String {
+    let name = backup.file_stem().unwrap_or_default().to_string_lossy();
+    let timestamp = name
+        .rsplit_once(".backup-")
+        .map(|(_, ts)| ts)
+        .unwrap_or(&name);
+    match chrono::NaiveDateTime::parse_from_str(timestamp, BACKUP_TIMESTAMP_FORMAT) {
+        Ok(time) => time.format("%Y-%m-%d %H:%M:%S").to_string(),
+        Err(_) => name.to_string(),
+    }
+}
+
+/// Makes the rename durable. Directories can't be opened for syncing on Windows.
+#[cfg(unix)]
+fn sync_parent_dir(path: &Path) {
+    if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty())
+        && let Ok(dir) = File::open(dir)
+    {
+        let _ = dir.sync_all();
+    }
+}
+
+#[cfg(not(unix))]
+fn sync_parent_dir(_path: &Path) {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        ingredient_store::IngredientStore, recipie::Recipie, recipie_store::RecipieStore,
+        test_dir::TestDir,
+    };
+
+    fn named(name: &str) -> Recipie {
+        Recipie {
+            name: name.into(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_save_leaves_no_temp_file() {
+        let dir = TestDir::new();
+        let mut store = RecipieStore::from_config(dir.to_path_buf()).unwrap();
+        store.register(named("Daiquiri"));
+        assert!(store.save().is_ok());
+        let path = store.get_config_path().unwrap();
+        assert!(!path.with_extension("json.tmp").exists());
+        let loaded = RecipieStore::load_from(&path).unwrap();
+        assert_eq!(loaded.get_entries().len(), 1);
+    }
+
+    #[test]
+    fn test_backups_rotate() {
+        let dir = TestDir::new();
+        let mut store = RecipieStore::from_config(dir.to_path_buf()).unwrap();
+        for i in 0..BACKUP_GENERATIONS + 3 {
+            store.register(named(&format!("Recipie {}", i)));
+            assert!(store.save().is_ok());
+            // Backups are named by millisecond
+            std::thread::sleep(std::time::Duration::from_millis(2));
+        }
+        let backups = store.list_backups();
+        assert_eq!(backups.len(), BACKUP_GENERATIONS);
+        // Newest backup holds the generation before the current one
+        let newest = RecipieStore::load_from(&backups[0]).unwrap();
+        assert_eq!(newest.get_entries().len(), BACKUP_GENERATIONS + 2);
+    }
+
+    #[test]
+    fn test_empty_file_is_fresh_store() {
+        let dir = TestDir::new();
+        std::fs::File::create(dir.join("recipie.json")).unwrap();
+        let store = RecipieStore::from_config(dir.to_path_buf()).unwrap();
+        assert!(store.get_entries().is_empty());
+    }
+
+    #[test]
+    fn test_malformed_store_is_quarantined() {
+        let dir = TestDir::new();
+        let path = dir.join("recipie.json");
+        std::fs::write(&path, "{\n  \"recipies\": {\n").unwrap();
+        match RecipieStore::from_config(dir.to_path_buf()) {
+            Err(err @ StoreLoadError::Malformed { line, .. }) => {
+                assert_eq!(line, 3);
+                let quarantined = err.quarantined().unwrap();
+                assert!(quarantined.exists());
+                assert!(!path.exists());
+            }
+            _ => panic!("Expected a malformed store"),
+        }
+    }
+
+    #[test]
+    fn test_schema_mismatch() {
+        let dir = TestDir::new();
+        std::fs::write(dir.join("recipie.json"), "{\"recipies\": [1, 2, 3]}").unwrap();
+        let result = RecipieStore::from_config(dir.to_path_buf());
+        assert!(matches!(result, Err(StoreLoadError::SchemaMismatch { .. })));
+    }
+
+    #[test]
+    fn test_missing_backup() {
+        let result = RecipieStore::load_from(&TestDir::new().join("nope.json"));
+        assert!(matches!(result, Err(StoreLoadError::Missing(_))));
+    }
+
+    #[test]
+    fn test_saves_schema_version() {
+        let dir = TestDir::new();
+        let mut store = RecipieStore::from_config(dir.to_path_buf()).unwrap();
+        store.register(named("Gimlet"));
+        assert!(store.save().is_ok());
+        let text = std::fs::read_to_string(dir.join("recipie.json")).unwrap();
+        let value: Value = serde_json::from_str(&text).unwrap();
+        assert_eq!(value["schema_version"], RecipieStore::SCHEMA_VERSION);
+        assert!(value["data"]["recipies"].is_object());
+    }
+
+    #[test]
+    fn test_legacy_store_is_migrated() {
+        let dir = TestDir::new();
+        let legacy = r#"{
+            "ingredient_map": {
+                "6f1c1fcb-4f47-4d39-8a33-9f0bd1e3f2a1": {
+                    "name": "Lime", "quality": "Any", "tags": [], "stock": 3
+                }
+            },
+            "ingredient_tags": [],
+            "config_path": null
+        }"#;
+        std::fs::write(dir.join("ingredient.json"), legacy).unwrap();
+        let store = IngredientStore::from_config(dir.to_path_buf()).unwrap();
+        let lime = store.get_ingredient(&"Lime".to_string()).unwrap();
+        assert_eq!(lime.stock, 3);
+        assert!(!lime.is_liquor);
+        // The pre-migration file is kept around untouched
+        let backups: Vec<PathBuf> = std::fs::read_dir(&dir)
+            .unwrap()
+            .map(|e| e.unwrap().path())
+            .filter(|p| p.to_string_lossy().contains("ingredient.pre-migration-v0-"))
+            .collect();
+        assert_eq!(backups.len(), 1);
+        assert_eq!(std::fs::read_to_string(&backups[0]).unwrap(), legacy);
+    }
+
+    #[test]
+    fn test_newer_schema_is_left_alone() {
+        let dir = TestDir::new();
+        let path = dir.join("recipie.json");
+        std::fs::write(&path, r#"{"schema_version": 99, "data": {}}"#).unwrap();
+        let result = RecipieStore::from_config(dir.to_path_buf());
+        assert!(matches!(
+            result,
+            Err(StoreLoadError::UnsupportedVersion { found: 99, .. })
+        ));
+        assert!(path.exists());
+    }
+
+    #[test]
+    fn test_restore_backup() {
+        let dir = TestDir::new();
+        let mut store = RecipieStore::from_config(dir.to_path_buf()).unwrap();
+        store.register(named("Negroni"));
+        assert!(store.save().is_ok());
+        store.register(named("Boulevardier"));
+        assert!(store.save().is_ok());
+        let backup = store.list_backups()[0].clone();
+        assert!(store.restore_backup(&backup).is_ok());
+        assert_eq!(store.get_entries().len(), 1);
+        assert_eq!(store.get_config_path(), Some(dir.join("recipie.json")));
+    }
+
+    #[test]
+    fn test_store_is_locked_while_open() {
+        let dir = TestDir::new();
+        let mut store = RecipieStore::from_config(dir.to_path_buf()).unwrap();
+        store.register(named("Negroni"));
+        assert!(store.save().is_ok());
+        assert!(matches!(
+            RecipieStore::from_config(dir.to_path_buf()),
+            Err(StoreLoadError::Locked(_))
+        ));
+        let mut read_only = RecipieStore::from_config_read_only(dir.to_path_buf()).unwrap();
+        assert!(read_only.is_read_only());
+        assert_eq!(read_only.get_entries().len(), 1);
+        read_only.register(named("Boulevardier"));
+        assert!(matches!(read_only.save(), Err(StoreError::ReadOnly(_))));
+        drop(store);
+        assert!(RecipieStore::from_config(dir.to_path_buf()).is_ok());
+    }
+
+    #[test]
+    fn test_claim_locks_recovered_store() {
+        let dir = TestDir::new();
+        let path = dir.join("recipie.json");
+        let mut store = RecipieStore::default();
+        assert!(store.claim(path.clone()).is_ok());
+        assert!(!store.is_read_only());
+        assert!(matches!(
+            RecipieStore::from_config(dir.to_path_buf()),
+            Err(StoreLoadError::Locked(_))
+        ));
+        // A second window recovering the same store only gets to look
+        let mut other = RecipieStore::default();
+        assert!(other.claim(path).is_ok());
+        assert!(other.is_read_only());
+        store.register(named("Negroni"));
+        assert!(store.save().is_ok());
+        other.register(named("Boulevardier"));
+        assert!(matches!(other.save(), Err(StoreError::ReadOnly(_))));
+    }
+
+    #[test]
+    fn test_changed_on_disk() {
+        let dir = TestDir::new();
+        let mut store = RecipieStore::from_config(dir.to_path_buf()).unwrap();
+        store.register(named("Negroni"));
+        assert!(store.save().is_ok());
+        assert!(!store.changed_on_disk());
+
+        // Someone else writes the file behind our back
+        let path = store.get_config_path().unwrap();
+        let mut theirs = RecipieStore::load_from(&path).unwrap();
+        theirs.register(named("Boulevardier"));
+        assert!(theirs.save_to(&path).is_ok());
+        assert!(store.changed_on_disk());
+
+        store.register(named("Old Pal"));
+        assert!(matches!(store.save(), Err(StoreError::ChangedOnDisk(_))));
+        assert!(store.merge_from_disk().is_ok());
+        assert_eq!(store.get_entries().len(), 3);
+        assert!(store.save().is_ok());
+
+        theirs.save_to(&path).unwrap();
+        assert!(store.reload_from_disk().is_ok());
+        assert_eq!(store.get_entries().len(), 2);
+        assert!(!store.is_dirty());
+        // Still holding on to the lock
+        assert!(matches!(
+            RecipieStore::from_config(dir.to_path_buf()),
+            Err(StoreLoadError::Locked(_))
+        ));
+    }
+
+    #[test]
+    fn test_trash() {
+        let dir = TestDir::new();
+        let mut store = RecipieStore::from_config(dir.to_path_buf()).unwrap();
+        let negroni = store.register(named("Negroni"));
+        let old_pal = store.register(named("Old Pal"));
+        assert!(store.move_to_trash(negroni).is_ok());
+        assert!(store.move_to_trash(old_pal).is_ok());
+        assert!(matches!(
+            store.move_to_trash(old_pal),
+            Err(StoreError::UnknownId(_))
+        ));
+        assert!(store.get_entry(negroni).is_none());
+        assert!(store.save().is_ok());
+        drop(store);
+
+        let mut store = RecipieStore::from_config(dir.to_path_buf()).unwrap();
+        assert_eq!(store.trash_bin().entries().len(), 2);
+        assert!(store.restore_from_trash(negroni).is_ok());
+        assert_eq!(store.get_entry(negroni).unwrap().name, "Negroni");
+        assert_eq!(store.purge_older_than(Duration::from_secs(3600)), 0);
+        assert!(store.purge(old_pal).is_ok());
+        assert!(store.trash_bin().is_empty());
+    }
+}
@@ -0,0 +1,49 @@
+use std::{
+    ops::Deref,
+    path::{Path, PathBuf},
+};
+
+use crate::{ingredient_store::IngredientSelector, measure::Measure, recipie::Component};
+
+/// A fresh directory under the system temp dir, removed again when dropped so a
+/// failing test doesn't leave it behind
+pub struct TestDir(PathBuf);
+
+impl TestDir {
+    pub fn new() -> Self {
+        let dir = std::env::temp_dir().join(format!("drink_solver_test_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        TestDir(dir)
+    }
+}
+
+impl Deref for TestDir {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl AsRef<Path> for TestDir {
+    fn as_ref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TestDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+/// `amount` of the ingredient called `name`
+pub fn component(name: &str, amount: Measure) -> Component {
+    Component {
+        ingredient: IngredientSelector {
+            name: Some(name.into()),
+            ..Default::default()
+        },
+        amount,
+    }
+}
@@ -0,0 +1,67 @@
+use std::{collections::HashMap, time::Duration};
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Deleted entries that can still be restored, kept under the ids they had in the store
+#[derive(Serialize, Deserialize, Clone)]
+pub struct TrashBin<T> {
+    entries: HashMap<Uuid, Trashed<T>>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Trashed<T> {
+    pub entry: T,
+    /// Unix timestamp, in seconds
+    pub deleted_at: i64,
+}
+
+// Derived, this would demand T: Default
+impl<T> Default for TrashBin<T> {
+    fn default() -> Self {
+        TrashBin {
+            entries: HashMap::new(),
+        }
+    }
+}
+
+impl<T> TrashBin<T> {
+    pub fn put(&mut self, id: Uuid, entry: T, deleted_at: i64) {
+        self.entries.insert(id, Trashed { entry, deleted_at });
+    }
+
+    pub fn take(&mut self, id: Uuid) -> Option<Trashed<T>> {
+        self.entries.remove(&id)
+    }
+
+    pub fn get(&self, id: Uuid) -> Option<&Trashed<T>> {
+        self.entries.get(&id)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Ids of entries deleted more than `max_age` before `now`
+    pub fn expired(&self, max_age: Duration, now: i64) -> Vec<Uuid> {
+        let cutoff = now.saturating_sub(max_age.as_secs() as i64);
+        self.entries
+            .iter()
+            .filter(|(_, trashed)| trashed.deleted_at < cutoff)
+            .map(|(id, _)| *id)
+            .collect()
+    }
+}
+
+impl<T: Clone> TrashBin<T> {
+    /// Newest first
+    pub fn entries(&self) -> Vec<(Uuid, Trashed<T>)> {
+        let mut entries: Vec<(Uuid, Trashed<T>)> = self
+            .entries
+            .iter()
+            .map(|(id, trashed)| (*id, trashed.clone()))
+            .collect();
+        entries.sort_by_key(|(_, trashed)| std::cmp::Reverse(trashed.deleted_at));
+        entries
+    }
+}
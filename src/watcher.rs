@@ -0,0 +1,139 @@
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+    sync::mpsc::{Receiver, channel},
+};
+
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+
+/// Watches store files for changes made outside the app, e.g. by a sync client or a script.
+/// JSON saves rename a new file over the old one, so it's the folders that are watched rather
+/// than the files themselves. A SQLite database is written in place, and the stores sharing
+/// it tell their own changes apart with `Store::changed_on_disk`.
+pub struct StoreWatcher {
+    paths: Vec<PathBuf>,
+    changes: Receiver<PathBuf>,
+    _watcher: RecommendedWatcher,
+}
+
+impl StoreWatcher {
+    /// `on_change` is called from the watcher's thread whenever one of `paths` changes
+    pub fn new(
+        paths: Vec<PathBuf>,
+        on_change: impl Fn() + Send + 'static,
+    ) -> notify::Result<StoreWatcher> {
+        // Events come back with the folder we asked to watch as their prefix
+        let watched: Vec<(PathBuf, PathBuf)> = paths
+            .iter()
+            .map(|path| (watch_path(path), path.clone()))
+            .collect();
+        let dirs: HashSet<PathBuf> = watched
+            .iter()
+            .filter_map(|(watch, _)| watch.parent().map(Path::to_path_buf))
+            .collect();
+        let (sender, changes) = channel();
+        let mut watcher = notify::recommended_watcher(move |event: notify::Result<Event>| {
+            let Ok(event) = event else {
+                return;
+            };
+            if matches!(event.kind, EventKind::Access(_)) {
+                return;
+            }
+            let mut changed = false;
+            for (watch, path) in &watched {
+                if event.paths.contains(watch) {
+                    let _ = sender.send(path.clone());
+                    changed = true;
+                }
+            }
+            if changed {
+                on_change();
+            }
+        })?;
+        for dir in dirs {
+            watcher.watch(&dir, RecursiveMode::NonRecursive)?;
+        }
+        Ok(StoreWatcher {
+            paths,
+            changes,
+            _watcher: watcher,
+        })
+    }
+
+    pub fn paths(&self) -> &[PathBuf] {
+        &self.paths
+    }
+
+    /// Store files that changed since the last call
+    pub fn changed(&self) -> HashSet<PathBuf> {
+        self.changes.try_iter().collect()
+    }
+}
+
+/// The store file as the watcher will report it, with its folder resolved
+fn watch_path(path: &Path) -> PathBuf {
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    let dir = dir.canonicalize().unwrap_or(dir.to_path_buf());
+    dir.join(path.file_name().unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::*;
+    use crate::{
+        ingredient::Ingredient,
+        ingredient_store::IngredientStore,
+        sqlite_store::{SqliteIngredientStore, SqliteRecipieStore},
+        store::Store,
+        test_dir::TestDir,
+    };
+
+    fn wait_for_changes(watcher: &StoreWatcher) -> HashSet<PathBuf> {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let mut changed = HashSet::new();
+        while changed.is_empty() && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(20));
+            changed = watcher.changed();
+        }
+        changed
+    }
+
+    #[test]
+    fn test_watcher_reports_store_changes() {
+        let dir = TestDir::new();
+        let store = dir.join("recipie.json");
+        let watcher = StoreWatcher::new(vec![store.clone()], || ()).unwrap();
+        std::fs::write(dir.join("unrelated.json"), "{}").unwrap();
+        std::fs::write(dir.join("recipie.json.tmp"), "{}").unwrap();
+        std::fs::rename(dir.join("recipie.json.tmp"), &store).unwrap();
+
+        let changed = wait_for_changes(&watcher);
+        assert_eq!(changed, HashSet::from([store]));
+    }
+
+    #[test]
+    fn test_watcher_reports_database_changes() {
+        let dir = TestDir::new();
+        let ingredients = SqliteIngredientStore::from_config(dir.to_path_buf())
+            .unwrap()
+            .into_inner();
+        let recipies = SqliteRecipieStore::from_config(dir.to_path_buf())
+            .unwrap()
+            .into_inner();
+        let db = ingredients.get_config_path().unwrap();
+        let watcher = StoreWatcher::new(vec![db.clone(), db.clone()], || ()).unwrap();
+
+        // Another instance saves its ingredients to the shared database
+        let mut theirs = IngredientStore::default();
+        theirs.register(Ingredient::default());
+        SqliteIngredientStore::write(&theirs, &db, None).unwrap();
+        assert_eq!(wait_for_changes(&watcher), HashSet::from([db]));
+        assert!(ingredients.changed_on_disk());
+        assert!(!recipies.changed_on_disk());
+    }
+}
@@ -0,0 +1,41 @@
+use std::{cell::RefCell, rc::Rc};
+
+use crate::{
+    ingredient_store::IngredientStore,
+    recipie_store::RecipieStore,
+    store::{Store, backup_label},
+    widgets::error_log::ErrorLog,
+};
+
+pub fn build_menu(
+    ui: &mut egui::Ui,
+    recipie_store: Rc<RefCell<RecipieStore>>,
+    ingredient_store: Rc<RefCell<IngredientStore>>,
+    errors: &Rc<RefCell<ErrorLog>>,
+) -> egui::Response {
+    ui.menu_button("Restore from backup", |ui| {
+        ui.menu_button("Recipies", |ui| backup_list(ui, recipie_store, errors));
+        ui.menu_button("Ingredients", |ui| {
+            backup_list(ui, ingredient_store, errors)
+        });
+    })
+    .response
+}
+
+fn backup_list<T, S: Store<T>>(
+    ui: &mut egui::Ui,
+    store: Rc<RefCell<S>>,
+    errors: &Rc<RefCell<ErrorLog>>,
+) {
+    let backups = store.borrow().list_backups();
+    if backups.is_empty() {
+        ui.label("No backups yet");
+    }
+    for backup in backups {
+        if ui.button(backup_label(&backup)).clicked() {
+            let result = store.borrow_mut().restore_backup(&backup);
+            errors.borrow_mut().check("Couldn't restore backup", result);
+            ui.close();
+        }
+    }
+}
@@ -0,0 +1,134 @@
+use std::{cell::RefCell, rc::Rc};
+
+use egui::{Button, Id, Modal};
+
+use crate::{
+    ingredient_store::IngredientStore, recipie_store::RecipieStore, store::Store,
+    widgets::error_log::ErrorLog,
+};
+
+// Shown instead of saving when someone else wrote to a store file since we loaded it
+pub struct ConflictDialog {
+    recipie_store: Rc<RefCell<RecipieStore>>,
+    ingredient_store: Rc<RefCell<IngredientStore>>,
+    errors: Rc<RefCell<ErrorLog>>,
+    recipies_changed: bool,
+    ingredients_changed: bool,
+}
+
+impl ConflictDialog {
+    pub fn new(
+        recipie_store: Rc<RefCell<RecipieStore>>,
+        ingredient_store: Rc<RefCell<IngredientStore>>,
+        errors: Rc<RefCell<ErrorLog>>,
+    ) -> ConflictDialog {
+        ConflictDialog {
+            recipie_store,
+            ingredient_store,
+            errors,
+            recipies_changed: false,
+            ingredients_changed: false,
+        }
+    }
+
+    pub fn open(&mut self, recipies_changed: bool, ingredients_changed: bool) {
+        self.recipies_changed |= recipies_changed;
+        self.ingredients_changed |= ingredients_changed;
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.recipies_changed || self.ingredients_changed
+    }
+
+    // Returns true when a store was reloaded, so anything caching its contents is stale
+    pub fn show(&mut self, ctx: &egui::Context) -> bool {
+        if !self.is_open() {
+            return false;
+        }
+        let mut reloaded = false;
+        Modal::new(Id::new("conflict_dialog")).show(ctx, |ui| {
+            ui.heading("Your bar was changed somewhere else");
+            ui.label(
+                "Someone saved changes since you opened it. Nothing has been overwritten yet.",
+            );
+            if self.recipies_changed {
+                match resolve(ui, "Recipies", &self.recipie_store, &self.errors) {
+                    Resolution::Unresolved => (),
+                    Resolution::Reloaded => {
+                        self.recipies_changed = false;
+                        reloaded = true;
+                    }
+                    Resolution::Kept => self.recipies_changed = false,
+                }
+            }
+            if self.ingredients_changed {
+                match resolve(ui, "Ingredients", &self.ingredient_store, &self.errors) {
+                    Resolution::Unresolved => (),
+                    Resolution::Reloaded => {
+                        self.ingredients_changed = false;
+                        reloaded = true;
+                    }
+                    Resolution::Kept => self.ingredients_changed = false,
+                }
+            }
+        });
+        reloaded
+    }
+}
+
+enum Resolution {
+    Unresolved,
+    // The store now holds what's on disk, possibly alongside our changes
+    Reloaded,
+    // Our version went over theirs
+    Kept,
+}
+
+fn resolve<T, S: Store<T>>(
+    ui: &mut egui::Ui,
+    name: &str,
+    store: &Rc<RefCell<S>>,
+    errors: &Rc<RefCell<ErrorLog>>,
+) -> Resolution {
+    let mut resolution = Resolution::Unresolved;
+    ui.separator();
+    ui.strong(name);
+    ui.horizontal(|ui| {
+        let mut store = store.borrow_mut();
+        if ui
+            .button("Reload")
+            .on_hover_text("Discard your unsaved changes and load theirs")
+            .clicked()
+        {
+            match store.reload_from_disk() {
+                Ok(()) => resolution = Resolution::Reloaded,
+                Err(err) => errors.borrow_mut().report("Couldn't reload", err),
+            }
+        }
+        if ui
+            .button("Merge")
+            .on_hover_text(
+                "Keep your version of everything you have, and add anything new of theirs",
+            )
+            .clicked()
+        {
+            match store.merge_from_disk() {
+                Ok(()) => resolution = Resolution::Reloaded,
+                Err(err) => errors.borrow_mut().report("Couldn't merge", err),
+            }
+        }
+        // Read-only stores can't be saved, so there's nothing to overwrite with
+        let overwrite = Button::new("Overwrite");
+        if ui
+            .add_enabled(!store.is_read_only(), overwrite)
+            .on_hover_text("Save your version over theirs")
+            .clicked()
+        {
+            match store.overwrite_disk() {
+                Ok(()) => resolution = Resolution::Kept,
+                Err(err) => errors.borrow_mut().report("Couldn't overwrite", err),
+            }
+        }
+    });
+    resolution
+}
@@ -1,6 +1,6 @@
-use std::{cell::RefCell, rc::Rc};
+use std::{cell::RefCell, mem::discriminant, rc::Rc};
 
-use egui::{Align, ComboBox, DragValue, Layout, Widget};
+use egui::{Align, ComboBox, DragValue, Layout, TextEdit, Widget};
 use strum::IntoEnumIterator;
 
 use crate::{
@@ -8,7 +8,7 @@ use crate::{
     component_builder::ComponentBuilder,
     ingredient::{IngredientTag, Quality},
     ingredient_store::IngredientStore,
-    measure::Measure,
+    measure::{AmountStyle, Measure, format_amount, parse_amount},
     recipie::Component,
     widgets::{create_vec::CreateVecWidget, create_vec_kernels::VecEnumWidget},
 };
@@ -19,29 +19,31 @@ pub struct CreateComponentEntryWidget {
     tag_widget: CreateVecWidget<String, VecEnumWidget>,
     id: uuid::Uuid,
     tags: Vec<String>,
-    names: Vec<String>
+    names: Vec<String>,
+    amount_style: AmountStyle,
 }
 
 impl CreateComponentEntryWidget {
     pub fn new(store: Rc<RefCell<IngredientStore>>) -> CreateComponentEntryWidget {
-        let mut tags: Vec<String> = store
-            .borrow()
-            .get_tags()
-            .iter()
-            .map(|t| t.value.clone())
-            .collect();
-        tags.sort_by_key(|t| t.to_ascii_lowercase());
-        let mut names = store.borrow().get_ingredient_names();
-        names.sort_by_key(|n| n.to_ascii_lowercase());
+        let (tags, names) = options(&store.borrow());
         CreateComponentEntryWidget {
             builder: ComponentBuilder::default(),
             tag_widget: CreateVecWidget::new(VecEnumWidget::new(tags.clone())),
             id: uuid::Uuid::new_v4(),
             tags: tags,
-            names: names
+            names: names,
+            amount_style: AmountStyle::default(),
         }
     }
 
+    // Picks up ingredients and tags added since the widget was made, keeping what's entered
+    pub fn refresh(&mut self, store: &Rc<RefCell<IngredientStore>>) {
+        (self.tags, self.names) = options(&store.borrow());
+        self.tag_widget = CreateVecWidget::from(
+            VecEnumWidget::new(self.tags.clone()),
+            self.tag_widget.get_entries());
+    }
+
     pub fn set_component(&mut self, component: Component) {
         self.builder = ComponentBuilder::from(component.clone());
         self.tag_widget = CreateVecWidget::from(
@@ -63,6 +65,19 @@ impl CreateComponentEntryWidget {
     }
 }
 
+// Tags and ingredient names to offer, sorted
+fn options(store: &IngredientStore) -> (Vec<String>, Vec<String>) {
+    let mut tags: Vec<String> = store
+        .get_tags()
+        .iter()
+        .map(|t| t.value.clone())
+        .collect();
+    tags.sort_by_key(|t| t.to_ascii_lowercase());
+    let mut names = store.get_ingredient_names();
+    names.sort_by_key(|n| n.to_ascii_lowercase());
+    (tags, names)
+}
+
 impl Widget for &mut CreateComponentEntryWidget {
     fn ui(self, ui: &mut egui::Ui) -> egui::Response {
         ui.horizontal(|ui| {
@@ -94,47 +109,54 @@ impl Widget for &mut CreateComponentEntryWidget {
                     });
                 ui.label("Quantity");
                 ui.horizontal(|ui| {
-                    match &mut self.builder.measure {
-                        Measure::Taste => (),
-                        Measure::Oz(val) => {
-                            ui.add(DragValue::new(val).speed(0.1));
-                        }
-                        Measure::Shot(val) => {
-                            ui.add(DragValue::new(val).speed(0.1));
-                        }
-                        Measure::Ml(val) => {
-                            ui.add(DragValue::new(val).speed(0.1));
-                        }
-                        Measure::Liter(val) => {
-                            ui.add(DragValue::new(val).speed(0.1));
-                        }
-                        Measure::Handle(val) => {
-                            ui.add(DragValue::new(val).speed(0.1));
-                        }
-                        Measure::Dash(val) => {
-                            ui.add(DragValue::new(val).speed(1));
-                        },
-                        Measure::Teaspoon(val) => {
-                            ui.add(DragValue::new(val).speed(0.25));
-                        }
-                        Measure::Tablespoon(val) => {
-                            ui.add(DragValue::new(val).speed(0.25));
-                        }
-                        Measure::Part(val) => {
-                            ui.add(DragValue::new(val).min_decimals(2).speed(0.25));
-                        }
+                    let style = self.amount_style;
+                    // A range is edited as its low end, whose units it's in, and its high end
+                    let (unit, max) = match &mut self.builder.measure {
+                        Measure::Range { min, max } => (&mut **min, Some(max)),
+                        measure => (measure, None),
                     };
+                    let speed = drag_speed(unit);
+                    if let Some(val) = unit.amount_mut() {
+                        ui.add(amount_drag(val, speed, style));
+                    }
+                    let mut is_range = max.is_some();
+                    if let Some(max) = max {
+                        ui.label("to");
+                        ui.add(amount_drag(max, speed, style));
+                    }
                     ComboBox::from_id_salt(("Quantity", self.id))
-                        .selected_text(self.builder.measure.to_string())
+                        .selected_text(unit_choice(unit))
                         .show_ui(ui, |ui| {
                             for measure in Measure::iter() {
-                                ui.selectable_value(
-                                    &mut self.builder.measure,
-                                    measure.clone(),
-                                    measure.to_string(),
-                                );
+                                if matches!(measure, Measure::Range { .. }) {
+                                    continue;
+                                }
+                                let selected = discriminant(&measure) == discriminant(unit);
+                                if ui.selectable_label(selected, unit_choice(&measure)).clicked() && !selected {
+                                    // Switching units keeps the number
+                                    *unit = match unit.amount() {
+                                        Some(amount) => measure.with_amount(amount),
+                                        None => measure,
+                                    };
+                                }
                             }
-                        })
+                        });
+                    if let Measure::Count { unit, .. } = unit {
+                        ui.add(TextEdit::singleline(unit).desired_width(80.0).hint_text("wedges"));
+                    }
+                    if unit.amount().is_some() {
+                        ui.checkbox(&mut is_range, "Range");
+                    }
+                    let changed = match &self.builder.measure {
+                        // Units without a number can't be a range either
+                        Measure::Range { min, .. } if !is_range || min.amount().is_none() => Some((**min).clone()),
+                        Measure::Range { .. } => None,
+                        measure if is_range => measure.amount().map(|a| Measure::range(measure.clone(), a, a)),
+                        _ => None,
+                    };
+                    if let Some(measure) = changed {
+                        self.builder.measure = measure;
+                    }
                 });
                 ui.label("Liquor/Mixer");
                 ComboBox::from_id_salt(("Is Liquor", self.id))
@@ -156,10 +178,36 @@ impl Widget for &mut CreateComponentEntryWidget {
     }
 }
 
+// Number of units the quantity moves per pixel dragged
+fn drag_speed(measure: &Measure) -> f64 {
+    match measure {
+        Measure::Dash(_) | Measure::Drop(_) | Measure::Pinch(_) | Measure::Count { .. } => 1.0,
+        Measure::Teaspoon(_) | Measure::Barspoon(_) | Measure::Tablespoon(_) | Measure::Part(_) | Measure::Cup(_) => 0.25,
+        _ => 0.1,
+    }
+}
+
+fn amount_drag(value: &mut f32, speed: f64, style: AmountStyle) -> DragValue<'_> {
+    // Typing "1 1/2" works too
+    DragValue::new(value)
+        .speed(speed)
+        .custom_formatter(move |n, _| format_amount(n as f32, style))
+        .custom_parser(|s| parse_amount(s).map(f64::from))
+}
+
+// How a unit is listed in the quantity combo box
+fn unit_choice(measure: &Measure) -> String {
+    match measure {
+        Measure::Count { .. } => "count".to_string(),
+        measure => measure.unit().to_string(),
+    }
+}
+
 #[derive(Clone)]
 pub struct CreateComponentWidget {
     entries: Vec<CreateComponentEntryWidget>,
     store: Rc<RefCell<IngredientStore>>,
+    amount_style: AmountStyle,
 }
 
 impl CreateComponentWidget {
@@ -167,6 +215,7 @@ impl CreateComponentWidget {
         CreateComponentWidget {
             entries: vec![],
             store: store,
+            amount_style: AmountStyle::default(),
         }
     }
 
@@ -178,10 +227,33 @@ impl CreateComponentWidget {
         self.entries.clear()
     }
 
+    pub fn refresh(&mut self) {
+        for entry in &mut self.entries {
+            entry.refresh(&self.store);
+        }
+    }
+
+    pub fn set_amount_style(&mut self, style: AmountStyle) {
+        self.amount_style = style;
+        for entry in &mut self.entries {
+            entry.amount_style = style;
+        }
+    }
+
+    fn new_entry(&self) -> CreateComponentEntryWidget {
+        let mut entry = CreateComponentEntryWidget::new(self.store.clone());
+        entry.amount_style = self.amount_style;
+        entry
+    }
+
     pub fn set_components(&mut self, components: Vec<Component>) {
         self.clear();
+        self.add_components(components);
+    }
+
+    pub fn add_components(&mut self, components: Vec<Component>) {
         for component in components {
-            let mut widget = CreateComponentEntryWidget::new(self.store.clone());
+            let mut widget = self.new_entry();
             widget.set_component(component);
             self.entries.push(widget);
         }
@@ -201,8 +273,8 @@ impl Widget for &mut CreateComponentWidget {
             }
             let resp = ui.button("Add Component");
             if resp.clicked() {
-                self.entries
-                    .push(CreateComponentEntryWidget::new(self.store.clone()));
+                let entry = self.new_entry();
+                self.entries.push(entry);
             }
             resp
         })
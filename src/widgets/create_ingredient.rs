@@ -5,10 +5,10 @@ use strum::IntoEnumIterator;
 
 use crate::{
     builder::Builder,
+    history::History,
     ingredient::Quality,
     ingredient_builder::IngredientBuilder,
     ingredient_store::IngredientStore,
-    store::Store,
     widgets::{
         create_vec::{CreateVecWidget, CreateVecWidgetKernel},
         create_vec_kernels::VecWidget,
@@ -20,13 +20,15 @@ pub struct CreateIngredientWidget {
     builder: IngredientBuilder,
     tag_widget: CreateVecWidget<String, VecWidget>,
     store: Rc<RefCell<IngredientStore>>,
+    history: Rc<RefCell<History>>,
 }
 
 impl CreateIngredientWidget {
-    pub fn new(store: Rc<RefCell<IngredientStore>>) -> Self {
+    pub fn new(store: Rc<RefCell<IngredientStore>>, history: Rc<RefCell<History>>) -> Self {
         CreateIngredientWidget {
             builder: IngredientBuilder::default(),
             store: store,
+            history,
             tag_widget: CreateVecWidget::default(),
         }
     }
@@ -44,7 +46,9 @@ impl Widget for &mut CreateIngredientWidget {
                 let btn = Button::new("Save");
                 if ui.add_enabled(!self.builder.name.is_empty(), btn).clicked() {
                     self.builder.tags = self.tag_widget.get_entries();
-                    self.store.borrow_mut().build_from(&self.builder);
+                    self.history
+                        .borrow_mut()
+                        .register(&mut *self.store.borrow_mut(), self.builder.build());
                     self.clear();
                 };
                 if ui.button("Reset").clicked() {
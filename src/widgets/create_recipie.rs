@@ -4,13 +4,14 @@ use egui::{Button, CentralPanel, Layout, ScrollArea, Separator, TopBottomPanel,
 
 use crate::{
     builder::Builder,
+    history::History,
     ingredient_store::IngredientStore,
+    measure::AmountStyle,
     recipie_builder::RecipieBuilder,
     recipie_store::RecipieStore,
-    store::Store,
     widgets::{
         create_component::CreateComponentWidget, create_vec::CreateVecWidget,
-        create_vec_kernels::VecWidget,
+        create_vec_kernels::VecWidget, paste_components::PasteComponentsDialog,
     },
 };
 
@@ -18,26 +19,42 @@ use crate::{
 pub struct CreateRecipieWidget {
     builder: RecipieBuilder,
     recipie_store: Rc<RefCell<RecipieStore>>,
+    history: Rc<RefCell<History>>,
     component_widget: CreateComponentWidget,
     instruction_widget: CreateVecWidget<String, VecWidget>,
+    paste_dialog: PasteComponentsDialog,
 }
 
 impl CreateRecipieWidget {
     pub fn new(
         store: Rc<RefCell<RecipieStore>>,
         ingredient_store: Rc<RefCell<IngredientStore>>,
+        history: Rc<RefCell<History>>,
     ) -> CreateRecipieWidget {
         CreateRecipieWidget {
             builder: RecipieBuilder::default(),
             recipie_store: store.clone(),
+            history,
             component_widget: CreateComponentWidget::new(ingredient_store.clone()),
             instruction_widget: CreateVecWidget::default(),
+            paste_dialog: PasteComponentsDialog::new(ingredient_store),
         }
     }
+
+    pub fn refresh(&mut self) {
+        self.component_widget.refresh();
+    }
+
+    pub fn set_amount_style(&mut self, style: AmountStyle) {
+        self.component_widget.set_amount_style(style);
+    }
 }
 
 impl Widget for &mut CreateRecipieWidget {
     fn ui(self, ui: &mut egui::Ui) -> egui::Response {
+        if let Some(components) = self.paste_dialog.show(ui.ctx()) {
+            self.component_widget.add_components(components);
+        }
         TopBottomPanel::top("create_recipie_top").show_inside(ui, |ui| {
             ui.label("Name");
             ui.text_edit_singleline(&mut self.builder.name);
@@ -48,7 +65,9 @@ impl Widget for &mut CreateRecipieWidget {
                 if ui.add_enabled(!self.builder.name.is_empty(), btn).clicked() {
                     self.builder.instructions = self.instruction_widget.get_entries();
                     self.builder.components = self.component_widget.get_components();
-                    self.recipie_store.borrow_mut().build_from(&self.builder);
+                    self.history
+                        .borrow_mut()
+                        .register(&mut *self.recipie_store.borrow_mut(), self.builder.build());
                     self.instruction_widget.clear();
                     self.component_widget.clear();
                     self.builder.clear();
@@ -87,7 +106,12 @@ impl Widget for &mut CreateRecipieWidget {
                         });
                         ui.separator();
                         ui.push_id(1, |ui| {
-                            ScrollArea::vertical().show(ui, |ui| ui.add(&mut self.component_widget))
+                            ScrollArea::vertical().show(ui, |ui| {
+                                if ui.button("Paste components…").clicked() {
+                                    self.paste_dialog.open();
+                                }
+                                ui.add(&mut self.component_widget)
+                            })
                         })
                     },
                 )
@@ -0,0 +1,124 @@
+use std::{
+    fmt::Display,
+    time::{Duration, Instant},
+};
+
+use egui::{Align2, Area, Frame, Id, ScrollArea, Window};
+
+// How long an error stays up as a toast before it's only in the log
+const TOAST_DURATION: Duration = Duration::from_secs(6);
+
+// Oldest entries are dropped past this
+const LOG_LIMIT: usize = 100;
+
+struct LogEntry {
+    message: String,
+    // For the log, in the user's time zone
+    logged_at: chrono::DateTime<chrono::Local>,
+    // For the toast
+    shown_at: Instant,
+}
+
+// Failures the user should hear about, shown as toasts that fade on their own and kept in a
+// log window that can be opened later. Nothing here blocks the UI.
+#[derive(Default)]
+pub struct ErrorLog {
+    entries: Vec<LogEntry>,
+    open: bool,
+}
+
+impl ErrorLog {
+    // e.g. report("Couldn't save recipies", err)
+    pub fn report(&mut self, what: &str, err: impl Display) {
+        let message = format!("{}: {}", what, err);
+        self.entries.push(LogEntry {
+            message,
+            logged_at: chrono::Local::now(),
+            shown_at: Instant::now(),
+        });
+        if self.entries.len() > LOG_LIMIT {
+            self.entries.remove(0);
+        }
+    }
+
+    // Reports the error, if there was one
+    pub fn check<T, E: Display>(&mut self, what: &str, result: Result<T, E>) -> Option<T> {
+        match result {
+            Ok(value) => Some(value),
+            Err(err) => {
+                self.report(what, err);
+                None
+            }
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn toggle(&mut self) {
+        self.open = !self.open;
+    }
+
+    pub fn show(&mut self, ctx: &egui::Context) {
+        self.show_toasts(ctx);
+        let mut open = self.open;
+        let mut clear = false;
+        Window::new("Error log")
+            .open(&mut open)
+            .default_width(400.0)
+            .show(ctx, |ui| {
+                if self.entries.is_empty() {
+                    ui.label("Nothing has gone wrong");
+                }
+                ScrollArea::vertical().show(ui, |ui| {
+                    for entry in self.entries.iter().rev() {
+                        ui.horizontal_wrapped(|ui| {
+                            ui.weak(entry.logged_at.format("%H:%M:%S").to_string());
+                            ui.label(&entry.message);
+                        });
+                    }
+                });
+                ui.separator();
+                clear = ui.button("Clear").clicked();
+            });
+        self.open = open;
+        if clear {
+            self.entries.clear();
+        }
+    }
+
+    fn show_toasts(&mut self, ctx: &egui::Context) {
+        let recent: Vec<&LogEntry> = self
+            .entries
+            .iter()
+            .filter(|e| e.shown_at.elapsed() < TOAST_DURATION)
+            .collect();
+        let Some(newest) = recent.last() else {
+            return;
+        };
+        // Wake up again to take the toasts down
+        ctx.request_repaint_after(TOAST_DURATION.saturating_sub(newest.shown_at.elapsed()));
+        let mut dismissed = false;
+        Area::new(Id::new("error_toasts"))
+            .anchor(Align2::RIGHT_BOTTOM, [-8.0, -8.0])
+            .show(ctx, |ui| {
+                Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.set_max_width(320.0);
+                    for entry in &recent {
+                        ui.colored_label(ui.visuals().error_fg_color, &entry.message);
+                    }
+                    dismissed = ui.small_button("Dismiss").clicked();
+                });
+            });
+        if dismissed {
+            for entry in &mut self.entries {
+                entry.shown_at -= TOAST_DURATION;
+            }
+        }
+    }
+}
@@ -6,19 +6,23 @@ use egui::{
 use strum::IntoEnumIterator;
 use uuid::Uuid;
 
-use crate::{ingredient::{Ingredient, IngredientTag, Quality}, ingredient_store::IngredientStore, store::Store, widgets::{create_vec::CreateVecWidget, create_vec_kernels::{VecEnumWidget, VecWidget}}};
+use crate::{history::History, ingredient::{Ingredient, IngredientTag, Quality}, ingredient_store::IngredientStore, store::Store, widgets::{create_vec::CreateVecWidget, create_vec_kernels::{VecEnumWidget, VecWidget}, error_log::ErrorLog}};
 
 pub struct IngredientWidget {
     ingredient_store: Rc<RefCell<IngredientStore>>,
+    history: Rc<RefCell<History>>,
+    errors: Rc<RefCell<ErrorLog>>,
     selected_ingredient: uuid::Uuid,
     tag_editor: CreateVecWidget<String, VecWidget>,
     editing: bool,
 }
 
 impl IngredientWidget {
-    pub fn new(store: Rc<RefCell<IngredientStore>>) -> IngredientWidget {
+    pub fn new(store: Rc<RefCell<IngredientStore>>, history: Rc<RefCell<History>>, errors: Rc<RefCell<ErrorLog>>) -> IngredientWidget {
         IngredientWidget {
             ingredient_store: store,
+            history,
+            errors,
             selected_ingredient: uuid::Uuid::nil(),
             tag_editor: CreateVecWidget::default(),
             editing: false
@@ -29,8 +33,13 @@ impl IngredientWidget {
         Grid::new(("ingredient_widget_sidepane_list", is_liquor))
             .striped(true)
             .show(ui, |ui| {
-                let mut binding = self.ingredient_store.borrow_mut();
-                let mut entries: Vec<(Uuid, &mut Ingredient)> = binding.get_ingredient_entries().into_iter().filter(|i| i.1.is_liquor == is_liquor).collect();
+                let mut entries: Vec<(Uuid, Ingredient)> = self
+                    .ingredient_store
+                    .borrow()
+                    .get_entries_with_ids()
+                    .into_iter()
+                    .filter(|i| i.1.is_liquor == is_liquor)
+                    .collect();
                 entries.sort_by_key(|e| e.1.name.to_lowercase());
                 for (id, entry) in entries {
                     ui.selectable_value(
@@ -38,15 +47,22 @@ impl IngredientWidget {
                         id,
                         &entry.name,
                     );
+                    let mut stock = entry.stock;
                     ui.horizontal(|ui| {
-                        if ui.add_enabled(entry.stock > 0, Button::new("-")).clicked() {
-                            entry.stock -= 1;
+                        if ui.add_enabled(stock > 0, Button::new("-")).clicked() {
+                            stock -= 1;
                         }
-                        ui.add(DragValue::new(&mut entry.stock));
+                        ui.add(DragValue::new(&mut stock));
                         if ui.button("+").clicked() {
-                            entry.stock += 1;
+                            stock += 1;
                         }
                     });
+                    // Only touch the store when something actually changed, so it isn't marked dirty
+                    if stock != entry.stock {
+                        let ingredient = Ingredient { stock, ..entry };
+                        let result = self.history.borrow_mut().update(&mut *self.ingredient_store.borrow_mut(), id, ingredient);
+                        self.errors.borrow_mut().check("Couldn't change stock", result);
+                    }
                     ui.end_row();
                 }
             }).response
@@ -73,20 +89,25 @@ impl Widget for &mut IngredientWidget {
         if self.selected_ingredient != uuid::Uuid::nil() {
             TopBottomPanel::bottom("ingredient_footer").show_inside(ui, |ui| {
                 let mut store = self.ingredient_store.borrow_mut();
+                let mut history = self.history.borrow_mut();
                 ui.horizontal(|ui| {
                     if ui.button("Delete entry").clicked() {
-                        store.deregister(self.selected_ingredient);
+                        let result = history.move_to_trash(&mut *store, self.selected_ingredient);
+                        self.errors.borrow_mut().check("Couldn't delete ingredient", result);
                         self.selected_ingredient = uuid::Uuid::nil();
                     }
                     if ui.toggle_value(&mut self.editing, "Edit ingredient").clicked() {
-                        let tags = store.get_tags();
-                        if let Some(ingredient) = store.get_entry_mut(self.selected_ingredient) {
-                            if self.editing {
+                        if self.editing {
+                            if let Some(ingredient) = store.get_entry(self.selected_ingredient) {
                                 self.tag_editor = CreateVecWidget::from(
                                     VecWidget::default(),
                                     ingredient.tags.iter().map(|t| t.value.clone()).collect())
-                            } else {
-                                ingredient.tags = self.tag_editor.get_entries().into_iter().map(|t| IngredientTag { value: t.clone() }).collect();
+                            }
+                        } else {
+                            let tags: Vec<IngredientTag> = self.tag_editor.get_entries().into_iter().map(|t| IngredientTag { value: t.clone() }).collect();
+                            if let Some(ingredient) = store.get_entry(self.selected_ingredient) && ingredient.tags != tags {
+                                let result = history.update(&mut *store, self.selected_ingredient, Ingredient { tags, ..ingredient });
+                                self.errors.borrow_mut().check("Couldn't change tags", result);
                             }
                         }
                     }
@@ -95,11 +116,9 @@ impl Widget for &mut IngredientWidget {
         }
         CentralPanel::default()
             .show_inside(ui, |ui| {
-                if let Some(ingredient) = self
-                    .ingredient_store
-                    .borrow_mut()
-                    .get_entry_mut(self.selected_ingredient)
-                {
+                let selected = self.ingredient_store.borrow().get_entry(self.selected_ingredient);
+                if let Some(mut ingredient) = selected {
+                    let before = ingredient.clone();
                     ui.vertical(|ui| {
                         if self.editing {
                             ui.text_edit_singleline(&mut ingredient.name);
@@ -117,17 +136,43 @@ impl Widget for &mut IngredientWidget {
                                     }
                                 });
                             ui.separator();
+                            // Orders can only take pours out of stock once we know how big a bottle is
+                            let mut tracked = ingredient.bottle_ml.is_some();
+                            ui.checkbox(&mut tracked, "Take orders' pours out of stock");
+                            ingredient.bottle_ml = match (tracked, ingredient.bottle_ml) {
+                                (true, Some(mut ml)) => {
+                                    ui.horizontal(|ui| {
+                                        ui.label("Bottle size");
+                                        ui.add(DragValue::new(&mut ml).range(1..=5000).suffix(" ml"));
+                                    });
+                                    Some(ml)
+                                }
+                                (true, None) => Some(750),
+                                (false, _) => None,
+                            };
+                            ui.separator();
                             ui.add(&mut self.tag_editor);
                         } else {
                             ui.heading(&ingredient.name);
                             ui.separator();
                             ui.label(format!("Quality: {}", ingredient.quality.to_string()));
+                            if let (Some(left), Some(bottle)) = (ingredient.left_in_bottle_ml(), ingredient.bottle_ml) {
+                                ui.label(format!("{} of {} ml left in the open bottle", left, bottle));
+                            }
                             ui.separator();
                             for tag in &ingredient.tags {
                                 ui.label(&tag.value);
                             }
                         }
                     });
+                    if ingredient != before {
+                        let result = self.history.borrow_mut().update(
+                            &mut *self.ingredient_store.borrow_mut(),
+                            self.selected_ingredient,
+                            ingredient,
+                        );
+                        self.errors.borrow_mut().check("Couldn't edit ingredient", result);
+                    }
                 }
             })
             .response
@@ -0,0 +1,194 @@
+use std::{cell::RefCell, path::PathBuf, rc::Rc};
+
+use egui::{CollapsingHeader, ComboBox, Grid, RichText, ScrollArea, TextEdit, Window};
+
+use crate::{
+    ingredient_store::IngredientStore,
+    inventory_csv::{self, Column, ImportPlan, RowAction, Table},
+    widgets::error_log::ErrorLog,
+};
+
+// Exports the inventory to a spreadsheet, and imports it back once the user has said which
+// column is which and seen what it'd do
+pub struct CsvDialog {
+    ingredient_store: Rc<RefCell<IngredientStore>>,
+    errors: Rc<RefCell<ErrorLog>>,
+    open: bool,
+    path: String,
+    // The file being imported, and what each of its columns is
+    table: Option<Table>,
+    columns: Vec<Column>,
+}
+
+impl CsvDialog {
+    pub fn new(
+        ingredient_store: Rc<RefCell<IngredientStore>>,
+        errors: Rc<RefCell<ErrorLog>>,
+        default_path: PathBuf,
+    ) -> CsvDialog {
+        CsvDialog {
+            ingredient_store,
+            errors,
+            open: false,
+            path: default_path.display().to_string(),
+            table: None,
+            columns: vec![],
+        }
+    }
+
+    pub fn open(&mut self) {
+        self.open = true;
+    }
+
+    // Returns true when an import changed the store
+    pub fn show(&mut self, ctx: &egui::Context) -> bool {
+        let mut open = self.open;
+        let mut imported = false;
+        Window::new("Inventory spreadsheet")
+            .open(&mut open)
+            .default_width(560.0)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("CSV file");
+                    ui.add(TextEdit::singleline(&mut self.path).desired_width(320.0));
+                });
+                ui.horizontal(|ui| {
+                    if ui.button("Export").clicked() {
+                        self.export();
+                    }
+                    if ui.button("Open for import").clicked() {
+                        self.load();
+                    }
+                });
+                if self.table.is_some() {
+                    ui.separator();
+                    imported = self.import(ui);
+                }
+            });
+        self.open = open;
+        imported
+    }
+
+    fn export(&mut self) {
+        let result = std::fs::File::create(&self.path)
+            .map_err(inventory_csv::CsvError::from)
+            .and_then(|file| inventory_csv::export(&self.ingredient_store.borrow(), file));
+        self.errors
+            .borrow_mut()
+            .check("Couldn't export the inventory", result);
+    }
+
+    fn load(&mut self) {
+        let table = std::fs::File::open(&self.path)
+            .map_err(inventory_csv::CsvError::from)
+            .and_then(Table::read);
+        if let Some(table) = self
+            .errors
+            .borrow_mut()
+            .check("Couldn't open the CSV", table)
+        {
+            self.columns = table.guess_columns();
+            self.table = Some(table);
+        }
+    }
+
+    fn import(&mut self, ui: &mut egui::Ui) -> bool {
+        let Some(table) = &self.table else {
+            return false;
+        };
+        ui.label(RichText::new("Which column is which?").strong());
+        Grid::new("csv_columns").striped(true).show(ui, |ui| {
+            for (index, header) in table.headers.iter().enumerate() {
+                ui.label(header);
+                column_choice(ui, index, header, &mut self.columns[index]);
+                // A sample, to tell columns with unhelpful headers apart
+                let sample = table
+                    .rows
+                    .iter()
+                    .map(|r| r[index].as_str())
+                    .find(|c| !c.is_empty());
+                ui.weak(sample.unwrap_or_default());
+                ui.end_row();
+            }
+        });
+        ui.separator();
+        let plan = inventory_csv::plan(table, &self.columns, &self.ingredient_store.borrow());
+        let plan = match plan {
+            Ok(plan) => plan,
+            Err(err) => {
+                ui.colored_label(ui.visuals().error_fg_color, err.to_string());
+                return false;
+            }
+        };
+        ui.label(plan.summary());
+        preview(ui, &plan);
+        CollapsingHeader::new("Dry-run report").show(ui, |ui| {
+            let report = plan.report();
+            if ui.button("Copy").clicked() {
+                ui.ctx().copy_text(report.clone());
+            }
+            ScrollArea::vertical()
+                .id_salt("csv_report")
+                .max_height(160.0)
+                .show(ui, |ui| ui.monospace(report));
+        });
+        ui.separator();
+        let changes = plan.added() + plan.updated();
+        let import = ui.add_enabled(
+            changes > 0,
+            egui::Button::new(format!("Import {} changes", changes)),
+        );
+        if !import.clicked() {
+            return false;
+        }
+        plan.apply(&mut self.ingredient_store.borrow_mut());
+        self.table = None;
+        true
+    }
+}
+
+fn column_choice(ui: &mut egui::Ui, index: usize, header: &str, column: &mut Column) {
+    let mut choices = Column::fields().to_vec();
+    choices.push(Column::Extra(header.trim().to_string()));
+    choices.push(Column::Skip);
+    ComboBox::from_id_salt(("csv_column", index))
+        .selected_text(column.to_string())
+        .show_ui(ui, |ui| {
+            for choice in choices {
+                let label = choice.to_string();
+                ui.selectable_value(column, choice, label);
+            }
+        });
+}
+
+// Every row that'd change, or can't be imported
+fn preview(ui: &mut egui::Ui, plan: &ImportPlan) {
+    CollapsingHeader::new("Preview")
+        .default_open(true)
+        .show(ui, |ui| {
+            ScrollArea::vertical()
+                .id_salt("csv_preview")
+                .max_height(240.0)
+                .show(ui, |ui| {
+                    Grid::new("csv_preview_rows").striped(true).show(ui, |ui| {
+                        for row in &plan.rows {
+                            let (action, details) = match &row.action {
+                                RowAction::Add => ("New", String::new()),
+                                RowAction::Update(_, changes) => ("Update", changes.join(", ")),
+                                RowAction::Unchanged(_) => continue,
+                                RowAction::Skip(reason) => ("Skipped", reason.clone()),
+                            };
+                            ui.weak(format!("line {}", row.line));
+                            if action == "Skipped" {
+                                ui.colored_label(ui.visuals().warn_fg_color, action);
+                            } else {
+                                ui.strong(action);
+                            }
+                            ui.label(&row.ingredient.name);
+                            ui.label(details);
+                            ui.end_row();
+                        }
+                    });
+                });
+        });
+}
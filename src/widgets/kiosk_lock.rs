@@ -0,0 +1,121 @@
+use egui::{Id, Key, Modal, TextEdit};
+
+use crate::preferences::{MIN_PIN_LENGTH, Preferences, is_valid_pin};
+
+// What the user asked for in a PIN prompt
+pub enum LockChange {
+    Lock(String),
+    Unlock,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Prompt {
+    Lock,
+    Unlock,
+}
+
+// The PIN prompts for locking the app to the kiosk screen and getting back out
+#[derive(Default)]
+pub struct KioskLock {
+    prompt: Option<Prompt>,
+    pin: String,
+    confirm: String,
+    error: Option<String>,
+}
+
+impl KioskLock {
+    pub fn ask_to_lock(&mut self) {
+        self.open(Prompt::Lock);
+    }
+
+    pub fn ask_to_unlock(&mut self) {
+        self.open(Prompt::Unlock);
+    }
+
+    fn open(&mut self, prompt: Prompt) {
+        *self = KioskLock {
+            prompt: Some(prompt),
+            ..Default::default()
+        };
+    }
+
+    // Shows whichever prompt is open
+    pub fn show(&mut self, ctx: &egui::Context, preferences: &Preferences) -> Option<LockChange> {
+        let prompt = self.prompt?;
+        let mut change = None;
+        let mut cancelled = false;
+        Modal::new(Id::new("kiosk_lock")).show(ctx, |ui| {
+            let submitted = match prompt {
+                Prompt::Lock => {
+                    ui.heading("Lock to the kiosk screen");
+                    ui.label(format!(
+                        "Pick a PIN of at least {} digits. You'll need it to get back out.",
+                        MIN_PIN_LENGTH
+                    ));
+                    pin_field(ui, &mut self.pin, "PIN", true);
+                    pin_field(ui, &mut self.confirm, "Again", false)
+                }
+                Prompt::Unlock => {
+                    ui.heading("Staff only");
+                    pin_field(ui, &mut self.pin, "PIN", true)
+                }
+            };
+            if let Some(error) = &self.error {
+                ui.colored_label(ui.visuals().error_fg_color, error);
+            }
+            ui.horizontal(|ui| {
+                let label = match prompt {
+                    Prompt::Lock => "Lock",
+                    Prompt::Unlock => "Unlock",
+                };
+                if ui.button(label).clicked() || submitted {
+                    change = self.check(preferences);
+                }
+                cancelled = ui.button("Cancel").clicked();
+            });
+        });
+        if change.is_some() || cancelled {
+            *self = KioskLock::default();
+        }
+        change
+    }
+
+    fn check(&mut self, preferences: &Preferences) -> Option<LockChange> {
+        let pin = self.pin.trim();
+        match self.prompt {
+            Some(Prompt::Lock) if !is_valid_pin(pin) => {
+                self.error = Some(format!("Use {} or more digits", MIN_PIN_LENGTH));
+                None
+            }
+            Some(Prompt::Lock) if pin != self.confirm.trim() => {
+                self.error = Some("Those PINs don't match".to_string());
+                None
+            }
+            Some(Prompt::Lock) => Some(LockChange::Lock(pin.to_string())),
+            Some(Prompt::Unlock) if preferences.unlocks(pin) => Some(LockChange::Unlock),
+            Some(Prompt::Unlock) => {
+                self.error = Some("Wrong PIN".to_string());
+                self.pin.clear();
+                None
+            }
+            None => None,
+        }
+    }
+}
+
+// Returns true when Enter was pressed in it
+fn pin_field(ui: &mut egui::Ui, pin: &mut String, label: &str, focus: bool) -> bool {
+    ui.horizontal(|ui| {
+        ui.label(label);
+        let response = ui.add(
+            TextEdit::singleline(pin)
+                .password(true)
+                .desired_width(120.0),
+        );
+        if focus && !response.has_focus() && pin.is_empty() {
+            response.request_focus();
+        }
+        response.lost_focus() && ui.input(|i| i.key_pressed(Key::Enter))
+    })
+    .inner
+}
@@ -0,0 +1,145 @@
+use std::{cell::RefCell, rc::Rc};
+
+use egui::{Id, Modal, ViewportCommand};
+
+use crate::{
+    ingredient_store::IngredientStore,
+    recipie_store::RecipieStore,
+    store::{Store, StoreError, StoreLoadError, backup_label, list_backups},
+    widgets::error_log::ErrorLog,
+};
+
+// Startup dialog explaining why a store couldn't be loaded. Until the user picks
+// a way forward the affected store has no config path, so it is never saved.
+pub struct LoadErrorDialog {
+    recipie_store: Rc<RefCell<RecipieStore>>,
+    ingredient_store: Rc<RefCell<IngredientStore>>,
+    errors: Rc<RefCell<ErrorLog>>,
+    recipie_error: Option<StoreLoadError>,
+    ingredient_error: Option<StoreLoadError>,
+}
+
+impl LoadErrorDialog {
+    pub fn new(
+        recipie_store: Rc<RefCell<RecipieStore>>,
+        recipie_error: Option<StoreLoadError>,
+        ingredient_store: Rc<RefCell<IngredientStore>>,
+        ingredient_error: Option<StoreLoadError>,
+        errors: Rc<RefCell<ErrorLog>>,
+    ) -> LoadErrorDialog {
+        LoadErrorDialog {
+            recipie_store,
+            ingredient_store,
+            errors,
+            recipie_error,
+            ingredient_error,
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.recipie_error.is_some() || self.ingredient_error.is_some()
+    }
+
+    pub fn show(&mut self, ctx: &egui::Context) {
+        if !self.is_open() {
+            return;
+        }
+        Modal::new(Id::new("load_error_dialog")).show(ctx, |ui| {
+            ui.heading("Your bar couldn't be loaded");
+            if let Some(err) = &self.recipie_error
+                && resolve(ui, "Recipies", err, &self.recipie_store, &self.errors)
+            {
+                self.recipie_error = None;
+            }
+            if let Some(err) = &self.ingredient_error
+                && resolve(ui, "Ingredients", err, &self.ingredient_store, &self.errors)
+            {
+                self.ingredient_error = None;
+            }
+            ui.separator();
+            if ui.button("Quit").clicked() {
+                ctx.send_viewport_cmd(ViewportCommand::Close);
+            }
+        });
+    }
+}
+
+// Returns true once the user has chosen what to do with this store
+fn resolve<T, S: Store<T>>(
+    ui: &mut egui::Ui,
+    name: &str,
+    err: &StoreLoadError,
+    store: &Rc<RefCell<S>>,
+    errors: &Rc<RefCell<ErrorLog>>,
+) -> bool {
+    let mut resolved = false;
+    ui.separator();
+    ui.strong(name);
+    ui.label(err.to_string());
+    if let Some(quarantined) = err.quarantined() {
+        ui.label(format!(
+            "The broken file was moved to {}",
+            quarantined.display()
+        ));
+    }
+    if let StoreLoadError::Locked(path) = err {
+        let dir = path.parent().map(|p| p.to_path_buf()).unwrap_or_default();
+        ui.horizontal(|ui| {
+            if ui.button("Open read-only").clicked() {
+                resolved = replace_with(store, S::from_config_read_only(dir.clone()), errors);
+            }
+            if ui.button("Try again").clicked() {
+                resolved = replace_with(store, S::from_config(dir), errors);
+            }
+        });
+        return resolved;
+    }
+    ui.horizontal(|ui| {
+        let backups = list_backups(err.path());
+        ui.add_enabled_ui(!backups.is_empty(), |ui| {
+            ui.menu_button("Open a backup", |ui| {
+                for backup in backups {
+                    if ui.button(backup_label(&backup)).clicked() {
+                        let mut store = store.borrow_mut();
+                        let result = store
+                            .claim(err.path().clone())
+                            .map_err(StoreError::from)
+                            .and_then(|()| store.restore_backup(&backup));
+                        resolved = errors
+                            .borrow_mut()
+                            .check("Couldn't open backup", result)
+                            .is_some();
+                        ui.close();
+                    }
+                }
+            });
+        });
+        // Only start over once the old file is out of the way, so we never save on top of it
+        let can_start_fresh = !err.path().exists();
+        if ui
+            .add_enabled(can_start_fresh, egui::Button::new("Start fresh"))
+            .clicked()
+        {
+            let result = store.borrow_mut().claim(err.path().clone());
+            resolved = errors
+                .borrow_mut()
+                .check("Couldn't start fresh", result)
+                .is_some();
+        }
+    });
+    resolved
+}
+
+fn replace_with<T, S: Store<T>>(
+    store: &Rc<RefCell<S>>,
+    loaded: Result<S, StoreLoadError>,
+    errors: &Rc<RefCell<ErrorLog>>,
+) -> bool {
+    match errors.borrow_mut().check("Couldn't open store", loaded) {
+        Some(loaded) => {
+            *store.borrow_mut() = loaded;
+            true
+        }
+        None => false,
+    }
+}
@@ -1,61 +1,741 @@
-use std::{cell::RefCell, rc::Rc};
+use std::{cell::RefCell, collections::HashSet, path::PathBuf, rc::Rc, time::Duration};
 
-use egui::{CentralPanel, MenuBar, TopBottomPanel};
+use egui::{
+    Align, Button, CentralPanel, Key, KeyboardShortcut, Layout, MenuBar, Modifiers, TopBottomPanel,
+    ViewportCommand,
+};
 
 use super::recipie::RecipieWidget;
 use crate::{
+    backend::Backend,
+    history::History,
     ingredient_store::IngredientStore,
+    markdown,
+    measure::{AmountStyle, UnitSystem},
+    menu::{self, MenuHandle, MenuServer},
+    order_store::OrderStore,
+    preferences::Preferences,
+    profile::{DEFAULT_PROFILE, default_data_dir, profile_dir},
     recipie_store::RecipieStore,
-    store::Store,
+    store::{Store, StoreError, StoreLoadError},
+    watcher::StoreWatcher,
     widgets::{
-        create_ingredient::CreateIngredientWidget, create_recipie::CreateRecipieWidget,
-        ingredient::IngredientWidget, styling,
+        backup,
+        conflict::ConflictDialog,
+        create_ingredient::CreateIngredientWidget,
+        create_recipie::CreateRecipieWidget,
+        error_log::ErrorLog,
+        ingredient::IngredientWidget,
+        inventory_csv::CsvDialog,
+        kiosk_lock::{KioskLock, LockChange},
+        load_error::LoadErrorDialog,
+        orders::{KioskWidget, OrderQueueWidget},
+        profile,
+        recipie_folder::{FolderAction, RecipieFolderDialog},
+        styling, trash,
+        trash::TrashWidget,
     },
 };
 
+// Autosave once nothing has changed for a little while...
+const AUTOSAVE_DEBOUNCE: Duration = Duration::from_secs(2);
+// ...but don't let a steady trickle of edits put it off forever
+const AUTOSAVE_MAX_DELAY: Duration = Duration::from_secs(30);
+
+const SAVE_SHORTCUT: KeyboardShortcut = KeyboardShortcut::new(Modifiers::COMMAND, Key::S);
+const UNDO_SHORTCUT: KeyboardShortcut = KeyboardShortcut::new(Modifiers::COMMAND, Key::Z);
+const REDO_SHORTCUT: KeyboardShortcut =
+    KeyboardShortcut::new(Modifiers::COMMAND.plus(Modifiers::SHIFT), Key::Z);
+
 #[derive(PartialEq, Eq)]
 enum ActiveTab {
     Inventory,
     CreateIngredient,
     Recipies,
     CreateRecipie,
+    Trash,
+    Kiosk,
+    Orders,
 }
 
 pub struct MyApp {
     ingredient_store: Rc<RefCell<IngredientStore>>,
     recipie_store: Rc<RefCell<RecipieStore>>,
+    // Kept as JSON whichever backend the bar uses
+    order_store: Rc<RefCell<OrderStore>>,
+    history: Rc<RefCell<History>>,
+    errors: Rc<RefCell<ErrorLog>>,
     recipie_widget: RecipieWidget,
     ingredient_widget: IngredientWidget,
     create_ingredient_widget: CreateIngredientWidget,
     create_recipie_widget: CreateRecipieWidget,
+    trash_widget: TrashWidget,
+    kiosk_widget: KioskWidget,
+    order_queue_widget: OrderQueueWidget,
     active_tab: ActiveTab,
+    load_error_dialog: LoadErrorDialog,
+    conflict_dialog: ConflictDialog,
+    csv_dialog: CsvDialog,
+    recipie_folder: RecipieFolderDialog,
+    backend: Backend,
+    data_dir: PathBuf,
+    preferences: Preferences,
+    profile: String,
+    new_profile_name: String,
+    watcher: Option<StoreWatcher>,
+    // The guest menu, while it's being shared, and where guests can find it
+    guest_menu: Option<(MenuHandle, String)>,
+    kiosk_lock: KioskLock,
+    // Whether we've put the window in fullscreen for a locked kiosk
+    fullscreen: bool,
+}
+
+// A store that failed to load comes back empty and without a config path, so it won't be saved
+fn load_or_default<T, S: Store<T>>(
+    loaded: Result<S, StoreLoadError>,
+) -> (S, Option<StoreLoadError>) {
+    match loaded {
+        Ok(store) => (store, None),
+        Err(err) => (S::default(), Some(err)),
+    }
 }
 
 impl Default for MyApp {
     fn default() -> Self {
-        let recipie_store = Rc::new(RefCell::new(RecipieStore::new()));
-        let ingredient_store = Rc::new(RefCell::new(IngredientStore::new()));
-        Self {
+        MyApp::new(Backend::default(), default_data_dir())
+    }
+}
+
+impl MyApp {
+    pub fn new(backend: Backend, data_dir: PathBuf) -> Self {
+        let recipie_store = Rc::new(RefCell::new(RecipieStore::default()));
+        let ingredient_store = Rc::new(RefCell::new(IngredientStore::default()));
+        let order_store = Rc::new(RefCell::new(OrderStore::default()));
+        let history = Rc::new(RefCell::new(History::default()));
+        let errors = Rc::new(RefCell::new(ErrorLog::default()));
+        let mut app = Self {
             ingredient_store: ingredient_store.clone(),
             recipie_store: recipie_store.clone(),
-            recipie_widget: RecipieWidget::new(recipie_store.clone(), ingredient_store.clone()),
-            ingredient_widget: IngredientWidget::new(ingredient_store.clone()),
-            create_ingredient_widget: CreateIngredientWidget::new(ingredient_store.clone()),
+            order_store: order_store.clone(),
+            history: history.clone(),
+            errors: errors.clone(),
+            recipie_widget: RecipieWidget::new(
+                recipie_store.clone(),
+                ingredient_store.clone(),
+                history.clone(),
+                errors.clone(),
+            ),
+            ingredient_widget: IngredientWidget::new(
+                ingredient_store.clone(),
+                history.clone(),
+                errors.clone(),
+            ),
+            create_ingredient_widget: CreateIngredientWidget::new(
+                ingredient_store.clone(),
+                history.clone(),
+            ),
             create_recipie_widget: CreateRecipieWidget::new(
                 recipie_store.clone(),
                 ingredient_store.clone(),
+                history.clone(),
+            ),
+            trash_widget: TrashWidget::new(
+                recipie_store.clone(),
+                ingredient_store.clone(),
+                history.clone(),
+                errors.clone(),
+            ),
+            kiosk_widget: KioskWidget::new(
+                recipie_store.clone(),
+                ingredient_store.clone(),
+                order_store.clone(),
+            ),
+            order_queue_widget: OrderQueueWidget::new(
+                recipie_store.clone(),
+                ingredient_store.clone(),
+                order_store.clone(),
+                history.clone(),
+                errors.clone(),
             ),
             active_tab: ActiveTab::Inventory,
+            load_error_dialog: LoadErrorDialog::new(
+                recipie_store.clone(),
+                None,
+                ingredient_store.clone(),
+                None,
+                errors.clone(),
+            ),
+            conflict_dialog: ConflictDialog::new(
+                recipie_store.clone(),
+                ingredient_store.clone(),
+                errors.clone(),
+            ),
+            csv_dialog: CsvDialog::new(
+                ingredient_store.clone(),
+                errors.clone(),
+                data_dir.join("ingredients.csv"),
+            ),
+            recipie_folder: RecipieFolderDialog::default(),
+            backend,
+            preferences: Preferences::default(),
+            data_dir,
+            profile: DEFAULT_PROFILE.to_string(),
+            new_profile_name: String::new(),
+            watcher: None,
+            guest_menu: None,
+            kiosk_lock: KioskLock::default(),
+            fullscreen: false,
+        };
+        let preferences = Preferences::load(&app.data_dir);
+        app.preferences = app
+            .errors
+            .borrow_mut()
+            .check("Couldn't load preferences, using the defaults", preferences)
+            .unwrap_or_default();
+        app.load_profile(DEFAULT_PROFILE);
+        app
+    }
+
+    // Loads a profile's stores into the shared stores and rebuilds every widget caching them
+    fn load_profile(&mut self, profile: &str) {
+        let dir = profile_dir(&self.data_dir, profile);
+        let (recipie_store, recipie_error) =
+            load_or_default(self.backend.load_recipies(dir.clone()));
+        let (ingredient_store, ingredient_error) =
+            load_or_default(self.backend.load_ingredients(dir.clone()));
+        let (order_store, order_error) = load_or_default(OrderStore::from_config(dir));
+        if let Some(err) = order_error {
+            self.errors
+                .borrow_mut()
+                .report("Couldn't load the order queue", err);
+        }
+        *self.recipie_store.borrow_mut() = recipie_store;
+        *self.ingredient_store.borrow_mut() = ingredient_store;
+        *self.order_store.borrow_mut() = order_store;
+        self.history.borrow_mut().clear();
+        self.purge_expired_trash();
+        self.rebuild_widgets();
+        self.load_error_dialog = LoadErrorDialog::new(
+            self.recipie_store.clone(),
+            recipie_error,
+            self.ingredient_store.clone(),
+            ingredient_error,
+            self.errors.clone(),
+        );
+        self.conflict_dialog = ConflictDialog::new(
+            self.recipie_store.clone(),
+            self.ingredient_store.clone(),
+            self.errors.clone(),
+        );
+        self.profile = profile.to_string();
+        self.sync_recipie_folder();
+    }
+
+    // Two-way sync with the profile's folder of Markdown recipies, if it has one
+    fn sync_recipie_folder(&mut self) {
+        let Some(dir) = self.preferences.recipie_folders.get(&self.profile).cloned() else {
+            return;
+        };
+        // Whatever it imported couldn't be saved
+        if self.is_read_only() {
+            return;
+        }
+        let result = markdown::sync(&mut self.recipie_store.borrow_mut(), &dir);
+        let Some(report) = self
+            .errors
+            .borrow_mut()
+            .check("Couldn't sync the recipie folder", result)
+        else {
+            return;
+        };
+        self.recipie_folder.synced(report.summary());
+        if report.changed_store() {
+            self.history.borrow_mut().clear();
+            self.refresh_widgets();
+        }
+    }
+
+    fn set_recipie_folder(&mut self, folder: Option<PathBuf>) {
+        match folder {
+            Some(folder) => self
+                .preferences
+                .recipie_folders
+                .insert(self.profile.clone(), folder),
+            None => self.preferences.recipie_folders.remove(&self.profile),
+        };
+        let result = self.preferences.save(&self.data_dir);
+        self.errors
+            .borrow_mut()
+            .check("Couldn't save preferences", result);
+        self.sync_recipie_folder();
+    }
+
+    fn share_guest_menu(&mut self, ctx: &egui::Context, share: bool) {
+        // Stop any old server first, so a new one can take its port
+        self.guest_menu = None;
+        if !share {
+            return;
+        }
+        let dir = profile_dir(&self.data_dir, &self.profile);
+        match MenuServer::bind(self.backend, dir, menu::DEFAULT_ADDRESS) {
+            Ok(server) => {
+                let url = server
+                    .lan_url()
+                    .unwrap_or_else(|| menu::DEFAULT_ADDRESS.to_string());
+                // Wake the UI so the menu's errors show up in the log
+                let ctx = ctx.clone();
+                let handle = server.spawn(move || ctx.request_repaint());
+                self.guest_menu = Some((handle, url));
+            }
+            Err(err) => self
+                .errors
+                .borrow_mut()
+                .report("Couldn't share the menu", err),
+        }
+    }
+
+    // Widgets cache bits of the stores, so they start over whenever the stores are replaced
+    fn rebuild_widgets(&mut self) {
+        self.recipie_widget = RecipieWidget::new(
+            self.recipie_store.clone(),
+            self.ingredient_store.clone(),
+            self.history.clone(),
+            self.errors.clone(),
+        );
+        self.ingredient_widget = IngredientWidget::new(
+            self.ingredient_store.clone(),
+            self.history.clone(),
+            self.errors.clone(),
+        );
+        self.create_ingredient_widget =
+            CreateIngredientWidget::new(self.ingredient_store.clone(), self.history.clone());
+        self.create_recipie_widget = CreateRecipieWidget::new(
+            self.recipie_store.clone(),
+            self.ingredient_store.clone(),
+            self.history.clone(),
+        );
+        self.trash_widget = TrashWidget::new(
+            self.recipie_store.clone(),
+            self.ingredient_store.clone(),
+            self.history.clone(),
+            self.errors.clone(),
+        );
+        self.kiosk_widget = KioskWidget::new(
+            self.recipie_store.clone(),
+            self.ingredient_store.clone(),
+            self.order_store.clone(),
+        );
+        self.order_queue_widget = OrderQueueWidget::new(
+            self.recipie_store.clone(),
+            self.ingredient_store.clone(),
+            self.order_store.clone(),
+            self.history.clone(),
+            self.errors.clone(),
+        );
+        self.apply_amount_style();
+    }
+
+    fn apply_amount_style(&mut self) {
+        let style = self.preferences.amount_style;
+        self.recipie_widget.set_amount_style(style);
+        self.create_recipie_widget.set_amount_style(style);
+        self.recipie_widget
+            .set_unit_system(self.preferences.unit_system);
+    }
+
+    fn purge_expired_trash(&self) {
+        if let Some(max_age) = self.preferences.trash_retention() {
+            self.recipie_store.borrow_mut().purge_older_than(max_age);
+            self.ingredient_store.borrow_mut().purge_older_than(max_age);
+        }
+    }
+
+    // Keeps widgets' cached copies of store contents up to date, without losing anything
+    // the user is in the middle of entering
+    fn refresh_widgets(&mut self) {
+        self.recipie_widget.refresh();
+        self.create_recipie_widget.refresh();
+    }
+
+    // (Re)starts watching the store files whenever they move, e.g. on a profile switch
+    fn watch_stores(&mut self, ctx: &egui::Context) {
+        let mut paths: Vec<PathBuf> = [
+            self.recipie_store.borrow().get_config_path(),
+            self.ingredient_store.borrow().get_config_path(),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+        // Under the SQLite backend both stores live in the same database
+        paths.dedup();
+        if self.watcher.as_ref().map(|w| w.paths()) == Some(paths.as_slice()) {
+            return;
+        }
+        let ctx = ctx.clone();
+        self.watcher = match StoreWatcher::new(paths, move || ctx.request_repaint()) {
+            Ok(watcher) => Some(watcher),
+            Err(err) => {
+                self.errors
+                    .borrow_mut()
+                    .report("Couldn't watch store files for changes", err);
+                None
+            }
+        };
+    }
+
+    // Picks up changes other programs made to the store files. Unsaved edits are never
+    // thrown away; the conflict dialog asks what to do with them instead.
+    fn reload_changed_stores(&mut self) {
+        let Some(watcher) = &self.watcher else {
+            return;
+        };
+        let changed = watcher.changed();
+        if changed.is_empty() {
+            return;
+        }
+        let recipies = reload_if_changed(&self.recipie_store, &changed, &self.errors);
+        let ingredients = reload_if_changed(&self.ingredient_store, &changed, &self.errors);
+        self.conflict_dialog.open(
+            recipies == ExternalChange::Conflict,
+            ingredients == ExternalChange::Conflict,
+        );
+        if recipies == ExternalChange::Reloaded || ingredients == ExternalChange::Reloaded {
+            // What the history would undo to no longer matches the store
+            self.history.borrow_mut().clear();
+            self.refresh_widgets();
+        }
+    }
+
+    fn undo(&mut self) {
+        let undone = self.history.borrow_mut().undo(
+            &mut self.recipie_store.borrow_mut(),
+            &mut self.ingredient_store.borrow_mut(),
+        );
+        if self.errors.borrow_mut().check("Couldn't undo", undone) != Some(false) {
+            self.refresh_widgets();
+        }
+    }
+
+    fn redo(&mut self) {
+        let redone = self.history.borrow_mut().redo(
+            &mut self.recipie_store.borrow_mut(),
+            &mut self.ingredient_store.borrow_mut(),
+        );
+        if self.errors.borrow_mut().check("Couldn't redo", redone) != Some(false) {
+            self.refresh_widgets();
+        }
+    }
+
+    fn build_edit_menu(&mut self, ctx: &egui::Context, ui: &mut egui::Ui) {
+        let (undo, redo) = {
+            let history = self.history.borrow();
+            (
+                history.next_undo().map(|c| c.describe()),
+                history.next_redo().map(|c| c.describe()),
+            )
+        };
+        let btn = Button::new(match &undo {
+            Some(what) => format!("Undo {}", what),
+            None => "Undo".to_string(),
+        })
+        .shortcut_text(ctx.format_shortcut(&UNDO_SHORTCUT));
+        if ui.add_enabled(undo.is_some(), btn).clicked() {
+            self.undo();
+            ui.close();
+        }
+        let btn = Button::new(match &redo {
+            Some(what) => format!("Redo {}", what),
+            None => "Redo".to_string(),
+        })
+        .shortcut_text(ctx.format_shortcut(&REDO_SHORTCUT));
+        if ui.add_enabled(redo.is_some(), btn).clicked() {
+            self.redo();
+            ui.close();
+        }
+    }
+
+    // Stores whose file someone else wrote to since we loaded it aren't saved; the
+    // conflict dialog asks what to do with them instead
+    fn save_stores(&mut self) {
+        let recipies_changed = self.recipie_store.borrow().changed_on_disk();
+        let ingredients_changed = self.ingredient_store.borrow().changed_on_disk();
+        if !recipies_changed {
+            let mut store = self.recipie_store.borrow_mut();
+            let result = self.backend.save_recipies(&mut store);
+            report_save_error(&mut *store, "recipies", result, &self.errors);
+        }
+        if !ingredients_changed {
+            let mut store = self.ingredient_store.borrow_mut();
+            let result = self.backend.save_ingredients(&mut store);
+            report_save_error(&mut *store, "ingredients", result, &self.errors);
+        }
+        // Only this app writes the order queue, so there's nothing for it to conflict with
+        {
+            let mut store = self.order_store.borrow_mut();
+            let result = store.save();
+            report_save_error(&mut *store, "orders", result, &self.errors);
+        }
+        self.conflict_dialog
+            .open(recipies_changed, ingredients_changed);
+        if !recipies_changed {
+            self.sync_recipie_folder();
+        }
+    }
+
+    fn is_read_only(&self) -> bool {
+        self.recipie_store.borrow().is_read_only() || self.ingredient_store.borrow().is_read_only()
+    }
+
+    fn is_dirty(&self) -> bool {
+        self.recipie_store.borrow().is_dirty()
+            || self.ingredient_store.borrow().is_dirty()
+            || self.order_store.borrow().is_dirty()
+    }
+
+    // Saves once edits have settled down, and keeps the UI ticking until then
+    fn autosave(&mut self, ctx: &egui::Context) {
+        if self.conflict_dialog.is_open() {
+            return;
+        }
+        let mut pending = vec![];
+        for (read_only, dirty) in [
+            (
+                self.recipie_store.borrow().is_read_only(),
+                self.recipie_store.borrow().dirty().clone(),
+            ),
+            (
+                self.ingredient_store.borrow().is_read_only(),
+                self.ingredient_store.borrow().dirty().clone(),
+            ),
+            (
+                self.order_store.borrow().is_read_only(),
+                self.order_store.borrow().dirty().clone(),
+            ),
+        ] {
+            // Read-only stores stay dirty forever, there's no point waking up for them
+            if !read_only {
+                pending.extend(dirty.save_due_in(AUTOSAVE_DEBOUNCE, AUTOSAVE_MAX_DELAY));
+            }
+        }
+        let due_in = pending.into_iter().min();
+        match due_in {
+            Some(due_in) if due_in.is_zero() => self.save_stores(),
+            Some(due_in) => ctx.request_repaint_after(due_in),
+            None => (),
+        }
+    }
+
+    // Locking goes through the PIN prompts; this just records the outcome
+    fn change_lock(&mut self, change: LockChange) {
+        self.preferences.kiosk_pin = match change {
+            LockChange::Lock(pin) => Some(pin),
+            LockChange::Unlock => None,
+        };
+        let result = self.preferences.save(&self.data_dir);
+        self.errors
+            .borrow_mut()
+            .check("Couldn't save preferences", result);
+    }
+
+    // The kiosk screen on its own, fullscreen, with a way out for staff
+    fn show_locked(&mut self, ctx: &egui::Context) {
+        // Guests can't close the app either
+        if ctx.input(|i| i.viewport().close_requested()) {
+            ctx.send_viewport_cmd(ViewportCommand::CancelClose);
+        }
+        TopBottomPanel::top("kiosk_header").show(ctx, |ui| {
+            ui.with_layout(Layout::right_to_left(Align::Center), |ui| {
+                if ui.small_button("🔒 Staff").clicked() {
+                    self.kiosk_lock.ask_to_unlock();
+                }
+            });
+        });
+        CentralPanel::default().show(ctx, |ui| ui.add(&mut self.kiosk_widget));
+    }
+
+    fn switch_profile(&mut self, ctx: &egui::Context, profile: &str) {
+        if profile == self.profile {
+            return;
+        }
+        self.save_stores();
+        // Don't walk away from changes that still need a decision
+        if self.conflict_dialog.is_open() {
+            return;
+        }
+        // Or from ones that couldn't be saved
+        if self.is_dirty() {
+            self.errors.borrow_mut().report(
+                &format!("Couldn't switch to {}", profile),
+                "this profile has changes that couldn't be saved",
+            );
+            return;
+        }
+        self.load_profile(profile);
+        // Guests should see the bar we're looking at
+        if self.guest_menu.is_some() {
+            self.share_guest_menu(ctx, true);
         }
     }
 }
 
 impl eframe::App for MyApp {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        self.load_error_dialog.show(ctx);
+        self.errors.borrow_mut().show(ctx);
+        self.watch_stores(ctx);
+        if let Some((menu, _)) = &self.guest_menu {
+            for err in menu.errors() {
+                self.errors.borrow_mut().report("Guest menu", err);
+            }
+        }
+        self.reload_changed_stores();
+        if self.conflict_dialog.show(ctx) {
+            self.history.borrow_mut().clear();
+            self.refresh_widgets();
+        }
+        // An import changes too much at once to undo row by row
+        if self.csv_dialog.show(ctx) {
+            self.history.borrow_mut().clear();
+            self.refresh_widgets();
+        }
+        let folder = self.preferences.recipie_folders.get(&self.profile).cloned();
+        match self.recipie_folder.show(ctx, folder.as_deref()) {
+            Some(FolderAction::Set(folder)) => self.set_recipie_folder(folder),
+            Some(FolderAction::SyncNow) => self.sync_recipie_folder(),
+            None => {}
+        }
+        if ctx.input_mut(|i| i.consume_shortcut(&SAVE_SHORTCUT)) {
+            self.save_stores();
+        }
+        if let Some(change) = self.kiosk_lock.show(ctx, &self.preferences) {
+            self.change_lock(change);
+        }
+        let locked = self.preferences.is_kiosk_locked();
+        if locked != self.fullscreen {
+            ctx.send_viewport_cmd(ViewportCommand::Fullscreen(locked));
+            self.fullscreen = locked;
+        }
+        if locked {
+            self.show_locked(ctx);
+            self.autosave(ctx);
+            return;
+        }
+        // Redo first, since Ctrl+Z would also match Ctrl+Shift+Z
+        if ctx.input_mut(|i| i.consume_shortcut(&REDO_SHORTCUT)) {
+            self.redo();
+        } else if ctx.input_mut(|i| i.consume_shortcut(&UNDO_SHORTCUT)) {
+            self.undo();
+        }
         TopBottomPanel::top("main_header").show(ctx, |ui| {
-            ui.heading("Drink Solver");
+            ui.horizontal(|ui| {
+                ui.heading("Drink Solver");
+                if self.is_read_only() {
+                    ui.label("Read-only: open in another window");
+                } else if self.is_dirty() {
+                    ui.label("● Unsaved changes");
+                }
+                if let Some((_, url)) = &self.guest_menu {
+                    ui.hyperlink_to(format!("Guest menu at {}", url), url);
+                }
+            });
             MenuBar::new().ui(ui, |ui| {
-                ui.menu_button("Preferences", |ui| styling::build_menu(ctx, ui))
+                ui.menu_button("File", |ui| {
+                    let btn =
+                        Button::new("Save").shortcut_text(ctx.format_shortcut(&SAVE_SHORTCUT));
+                    if ui.add(btn).clicked() {
+                        self.save_stores();
+                        ui.close();
+                    }
+                    let mut sharing = self.guest_menu.is_some();
+                    if ui
+                        .checkbox(&mut sharing, "Share menu with guests")
+                        .on_hover_text("Phones on the same network can see what you can make")
+                        .clicked()
+                    {
+                        self.share_guest_menu(ctx, sharing);
+                    }
+                    if ui
+                        .button("Inventory spreadsheet…")
+                        .on_hover_text("Export the ingredients to CSV, or import them from one")
+                        .clicked()
+                    {
+                        self.csv_dialog.open();
+                        ui.close();
+                    }
+                    if ui
+                        .button("Recipie folder…")
+                        .on_hover_text("Keep the recipies in sync with a folder of Markdown notes")
+                        .clicked()
+                    {
+                        let folder = self.preferences.recipie_folders.get(&self.profile);
+                        self.recipie_folder.open(folder.map(PathBuf::as_path));
+                        ui.close();
+                    }
+                    if ui
+                        .button("Lock to kiosk…")
+                        .on_hover_text("Fullscreen ordering for guests, with a PIN to get out")
+                        .clicked()
+                    {
+                        self.kiosk_lock.ask_to_lock();
+                        ui.close();
+                    }
+                });
+                ui.menu_button("Edit", |ui| self.build_edit_menu(ctx, ui));
+                let chosen = profile::build_menu(
+                    ui,
+                    &self.data_dir,
+                    &self.profile,
+                    &mut self.new_profile_name,
+                    &self.errors,
+                );
+                if let Some(profile) = chosen {
+                    self.switch_profile(ctx, &profile);
+                }
+                ui.menu_button("Preferences", |ui| {
+                    styling::build_menu(ctx, ui);
+                    if trash::build_menu(ui, &mut self.preferences.trash_retention_days) {
+                        let result = self.preferences.save(&self.data_dir);
+                        self.errors
+                            .borrow_mut()
+                            .check("Couldn't save preferences", result);
+                        self.purge_expired_trash();
+                    }
+                    let amounts = [
+                        (AmountStyle::Decimal, "Decimals (0.75 oz)"),
+                        (AmountStyle::Fraction, "Fractions (¾ oz)"),
+                    ];
+                    let units = [
+                        (UnitSystem::Imperial, "Imperial (oz)"),
+                        (UnitSystem::Metric, "Metric (ml)"),
+                    ];
+                    if build_choice_menu(
+                        ui,
+                        "Amounts",
+                        &amounts,
+                        &mut self.preferences.amount_style,
+                    ) | build_choice_menu(
+                        ui,
+                        "My units",
+                        &units,
+                        &mut self.preferences.unit_system,
+                    ) {
+                        let result = self.preferences.save(&self.data_dir);
+                        self.errors
+                            .borrow_mut()
+                            .check("Couldn't save preferences", result);
+                        self.apply_amount_style();
+                    }
+                    backup::build_menu(
+                        ui,
+                        self.recipie_store.clone(),
+                        self.ingredient_store.clone(),
+                        &self.errors,
+                    )
+                });
+                let logged = self.errors.borrow().len();
+                if logged > 0 && ui.button(format!("Errors ({})", logged)).clicked() {
+                    self.errors.borrow_mut().toggle();
+                }
             });
             ui.horizontal(|ui| {
                 ui.selectable_value(&mut self.active_tab, ActiveTab::Inventory, "Inventory");
@@ -70,6 +750,15 @@ impl eframe::App for MyApp {
                     ActiveTab::CreateRecipie,
                     "Create Recipie",
                 );
+                ui.selectable_value(&mut self.active_tab, ActiveTab::Trash, "Trash");
+                ui.separator();
+                ui.selectable_value(&mut self.active_tab, ActiveTab::Kiosk, "Kiosk");
+                let waiting = self.order_store.borrow().queue().len();
+                let orders = match waiting {
+                    0 => "Orders".to_string(),
+                    n => format!("Orders ({})", n),
+                };
+                ui.selectable_value(&mut self.active_tab, ActiveTab::Orders, orders);
             });
         });
         CentralPanel::default().show(ctx, |ui| {
@@ -78,15 +767,118 @@ impl eframe::App for MyApp {
                 ActiveTab::Inventory => ui.add(&mut self.ingredient_widget),
                 ActiveTab::CreateIngredient => ui.add(&mut self.create_ingredient_widget),
                 ActiveTab::CreateRecipie => ui.add(&mut self.create_recipie_widget),
+                ActiveTab::Trash => ui.add(&mut self.trash_widget),
+                ActiveTab::Kiosk => ui.add(&mut self.kiosk_widget),
+                ActiveTab::Orders => {
+                    let response = ui.add(&mut self.order_queue_widget);
+                    // Made orders took their pours out of stock
+                    if response.changed() {
+                        self.refresh_widgets();
+                    }
+                    response
+                }
             })
             .response
         });
+        self.autosave(ctx);
     }
 }
 
 impl Drop for MyApp {
     fn drop(&mut self) {
-        self.recipie_store.borrow().save();
-        self.ingredient_store.borrow().save();
+        self.save_stores();
+        // Too late to ask, so park our version next to theirs rather than lose it
+        park_conflicting(&*self.recipie_store.borrow(), &self.errors);
+        park_conflicting(&*self.ingredient_store.borrow(), &self.errors);
+    }
+}
+
+#[derive(PartialEq, Eq)]
+enum ExternalChange {
+    Unchanged,
+    Reloaded,
+    // Changed on disk while we have unsaved edits
+    Conflict,
+}
+
+fn reload_if_changed<T, S: Store<T>>(
+    store: &Rc<RefCell<S>>,
+    changed: &HashSet<PathBuf>,
+    errors: &Rc<RefCell<ErrorLog>>,
+) -> ExternalChange {
+    let mut store = store.borrow_mut();
+    let ours = store
+        .get_config_path()
+        .is_some_and(|p| changed.contains(&p));
+    // Our own saves show up here too, but leave the file as we last saw it
+    if !ours || !store.changed_on_disk() {
+        return ExternalChange::Unchanged;
+    }
+    if store.is_dirty() {
+        return ExternalChange::Conflict;
+    }
+    match store.reload_from_disk() {
+        Ok(()) => ExternalChange::Reloaded,
+        // Most likely caught halfway through being written; the next event tries again
+        Err(err) => {
+            errors.borrow_mut().report("Couldn't reload", err);
+            ExternalChange::Unchanged
+        }
+    }
+}
+
+// Returns true when the user picked something
+fn build_choice_menu<T: PartialEq + Copy>(
+    ui: &mut egui::Ui,
+    title: &str,
+    choices: &[(T, &str)],
+    value: &mut T,
+) -> bool {
+    let mut changed = false;
+    ui.menu_button(title, |ui| {
+        for &(choice, label) in choices {
+            if ui.selectable_value(value, choice, label).clicked() {
+                changed = true;
+                ui.close();
+            }
+        }
+    });
+    changed
+}
+
+// Stores that failed to load or are open read-only aren't meant to be saved, so those
+// aren't worth mentioning. Anything else waits a while before autosave tries again.
+fn report_save_error<T, S: Store<T>>(
+    store: &mut S,
+    name: &str,
+    result: Result<(), StoreError>,
+    errors: &Rc<RefCell<ErrorLog>>,
+) {
+    match result {
+        Ok(()) | Err(StoreError::NotLoaded) | Err(StoreError::ReadOnly(_)) => (),
+        Err(err) => {
+            errors
+                .borrow_mut()
+                .report(&format!("Couldn't save {}", name), err);
+            store.dirty_mut().postpone();
+        }
+    }
+}
+
+fn park_conflicting<T, S: Store<T>>(store: &S, errors: &Rc<RefCell<ErrorLog>>) {
+    if !store.is_dirty() || !store.changed_on_disk() {
+        return;
+    }
+    let path = store.get_config_path().unwrap_or_default();
+    let what = format!("{} was changed elsewhere", path.display());
+    match store.save_conflict_copy() {
+        Ok(copy) => errors.borrow_mut().report(
+            &what,
+            format!("your unsaved changes went to {}", copy.display()),
+        ),
+        Err(err) => errors.borrow_mut().report(
+            &format!("{}, couldn't keep your unsaved changes", what),
+            err,
+        ),
     }
 }
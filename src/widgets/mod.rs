@@ -1,9 +1,20 @@
+pub mod backup;
+pub mod conflict;
 pub mod create_component;
 pub mod create_ingredient;
 pub mod create_recipie;
 pub mod create_vec;
 pub mod create_vec_kernels;
+pub mod error_log;
 pub mod ingredient;
+pub mod inventory_csv;
+pub mod kiosk_lock;
+pub mod load_error;
 pub mod main_widget;
+pub mod orders;
+pub mod paste_components;
+pub mod profile;
 pub mod recipie;
+pub mod recipie_folder;
 pub mod styling;
+pub mod trash;
@@ -0,0 +1,368 @@
+use std::{
+    cell::RefCell,
+    rc::Rc,
+    time::{Duration, Instant},
+};
+
+use egui::{Button, CollapsingHeader, Frame, Grid, RichText, ScrollArea, TextEdit, Widget, Window};
+
+use crate::{
+    history::History,
+    ingredient_store::IngredientStore,
+    order::{Order, Summary},
+    order_store::OrderStore,
+    recipie::Recipie,
+    recipie_store::RecipieStore,
+    store::Store,
+    widgets::error_log::ErrorLog,
+};
+
+// How long a guest sees that their order went through
+const CONFIRMATION_DURATION: Duration = Duration::from_secs(4);
+
+const TILE_SIZE: egui::Vec2 = egui::vec2(260.0, 160.0);
+
+// Where guests order for themselves: a big card per drink we can make right now. It's
+// what a locked kiosk shows.
+pub struct KioskWidget {
+    recipie_store: Rc<RefCell<RecipieStore>>,
+    ingredient_store: Rc<RefCell<IngredientStore>>,
+    order_store: Rc<RefCell<OrderStore>>,
+    guest: String,
+    // The drink just ordered, to confirm it
+    placed: Option<(String, Instant)>,
+}
+
+impl KioskWidget {
+    pub fn new(
+        recipie_store: Rc<RefCell<RecipieStore>>,
+        ingredient_store: Rc<RefCell<IngredientStore>>,
+        order_store: Rc<RefCell<OrderStore>>,
+    ) -> KioskWidget {
+        KioskWidget {
+            recipie_store,
+            ingredient_store,
+            order_store,
+            guest: String::new(),
+            placed: None,
+        }
+    }
+
+    // One card per drink, sized to read from across the bar with room for a picture
+    fn tile(&mut self, ui: &mut egui::Ui, id: uuid::Uuid, recipie: &Recipie) {
+        Frame::group(ui.style()).show(ui, |ui| {
+            ui.set_width(TILE_SIZE.x);
+            ui.set_min_height(TILE_SIZE.y);
+            ui.vertical_centered(|ui| {
+                ui.label(RichText::new(&recipie.name).size(28.0).strong());
+                if !recipie.short_description.is_empty() {
+                    ui.label(RichText::new(&recipie.short_description).size(16.0));
+                }
+                ui.add_space(8.0);
+                let order = Button::new(RichText::new("Order").size(22.0))
+                    .min_size(egui::vec2(TILE_SIZE.x * 0.6, 44.0));
+                if ui.add(order).clicked() {
+                    self.order_store.borrow_mut().register(Order::new(
+                        id,
+                        &recipie.name,
+                        &self.guest,
+                    ));
+                    self.placed = Some((recipie.name.clone(), Instant::now()));
+                    // Ready for whoever's next
+                    self.guest.clear();
+                }
+            });
+        });
+    }
+}
+
+impl Widget for &mut KioskWidget {
+    fn ui(self, ui: &mut egui::Ui) -> egui::Response {
+        ui.vertical_centered(|ui| {
+            ui.label(RichText::new("What can we get you?").size(36.0).strong());
+            ui.horizontal(|ui| {
+                ui.label(RichText::new("Your name").size(20.0));
+                ui.add(TextEdit::singleline(&mut self.guest).font(egui::TextStyle::Heading));
+            });
+            match &self.placed {
+                Some((name, at)) if at.elapsed() < CONFIRMATION_DURATION => {
+                    ui.label(
+                        RichText::new(format!("Your {} is on its way!", name))
+                            .size(24.0)
+                            .strong(),
+                    );
+                    ui.ctx()
+                        .request_repaint_after(CONFIRMATION_DURATION.saturating_sub(at.elapsed()));
+                }
+                _ => self.placed = None,
+            }
+            ui.separator();
+            let makeable = self
+                .recipie_store
+                .borrow()
+                .makeable(&self.ingredient_store.borrow());
+            if makeable.is_empty() {
+                ui.label(
+                    RichText::new("Nothing on the menu right now, check back soon.").size(20.0),
+                );
+            }
+            ScrollArea::vertical().show(ui, |ui| {
+                ui.horizontal_wrapped(|ui| {
+                    for (id, recipie) in makeable {
+                        self.tile(ui, id, &recipie);
+                    }
+                });
+            });
+        })
+        .response
+    }
+}
+
+// The bartender's side: what's waiting, oldest first, and what's been made tonight.
+// Its response is marked changed when a finished order took its pours out of stock, which
+// can be undone like any other stock change.
+pub struct OrderQueueWidget {
+    recipie_store: Rc<RefCell<RecipieStore>>,
+    ingredient_store: Rc<RefCell<IngredientStore>>,
+    order_store: Rc<RefCell<OrderStore>>,
+    history: Rc<RefCell<History>>,
+    errors: Rc<RefCell<ErrorLog>>,
+    summary_open: bool,
+}
+
+impl OrderQueueWidget {
+    pub fn new(
+        recipie_store: Rc<RefCell<RecipieStore>>,
+        ingredient_store: Rc<RefCell<IngredientStore>>,
+        order_store: Rc<RefCell<OrderStore>>,
+        history: Rc<RefCell<History>>,
+        errors: Rc<RefCell<ErrorLog>>,
+    ) -> OrderQueueWidget {
+        OrderQueueWidget {
+            recipie_store,
+            ingredient_store,
+            order_store,
+            history,
+            errors,
+            summary_open: false,
+        }
+    }
+
+    // What goes into the drink, for the bartender to check while making it
+    fn recipie_hint(&self, order: &Order) -> String {
+        match self.recipie_store.borrow().get_entry(order.recipie) {
+            Some(recipie) => recipie
+                .components
+                .iter()
+                .map(|c| format!("{}: {}", c.ingredient, c.amount))
+                .collect::<Vec<String>>()
+                .join("\n"),
+            None => "This recipie has been deleted".to_string(),
+        }
+    }
+
+    // Returns true if an order was made
+    fn queue(&mut self, ui: &mut egui::Ui) -> bool {
+        let queue = self.order_store.borrow().queue();
+        if queue.is_empty() {
+            ui.label("Nothing waiting");
+            return false;
+        }
+        // Keep the waiting times current
+        ui.ctx().request_repaint_after(Duration::from_secs(30));
+        let mut made = false;
+        Grid::new("order_queue").striped(true).show(ui, |ui| {
+            for (id, order) in queue {
+                ui.label(time_label(order.placed_at));
+                ui.weak(waited_label(order.placed_at));
+                ui.label(RichText::new(&order.recipie_name).strong())
+                    .on_hover_text(self.recipie_hint(&order));
+                ui.label(&order.guest);
+                if ui.button("Done").clicked() {
+                    let result = self.order_store.borrow_mut().complete(
+                        id,
+                        &self.recipie_store.borrow(),
+                        &mut self.ingredient_store.borrow_mut(),
+                        &mut self.history.borrow_mut(),
+                    );
+                    made |= self
+                        .errors
+                        .borrow_mut()
+                        .check("Couldn't finish the order", result)
+                        .is_some();
+                }
+                if ui.button("Cancel").clicked() {
+                    let result = self.order_store.borrow_mut().move_to_trash(id);
+                    self.errors
+                        .borrow_mut()
+                        .check("Couldn't cancel the order", result);
+                }
+                ui.end_row();
+            }
+        });
+        made
+    }
+
+    fn finished(&self, ui: &mut egui::Ui) {
+        let finished = self.order_store.borrow().finished();
+        CollapsingHeader::new(format!("Made tonight ({})", finished.len())).show(ui, |ui| {
+            Grid::new("finished_orders").striped(true).show(ui, |ui| {
+                for (_, order) in finished {
+                    ui.label(time_label(order.done_at.unwrap_or_default()));
+                    ui.label(&order.recipie_name);
+                    ui.label(&order.guest);
+                    let untracked: Vec<&str> = order
+                        .poured
+                        .iter()
+                        .filter(|p| p.untracked)
+                        .map(|p| p.ingredient.as_str())
+                        .collect();
+                    if untracked.is_empty() {
+                        ui.label("");
+                    } else {
+                        ui.weak("Stock not updated").on_hover_text(format!(
+                            "No bottle size for {}, so it wasn't taken out of stock",
+                            untracked.join(", ")
+                        ));
+                    }
+                    ui.end_row();
+                }
+            });
+        });
+    }
+
+    fn cancelled(&self, ui: &mut egui::Ui) {
+        let cancelled = self.order_store.borrow().trash_bin().entries();
+        if cancelled.is_empty() {
+            return;
+        }
+        CollapsingHeader::new(format!("Cancelled ({})", cancelled.len())).show(ui, |ui| {
+            Grid::new("cancelled_orders").striped(true).show(ui, |ui| {
+                for (id, trashed) in cancelled {
+                    ui.label(time_label(trashed.entry.placed_at));
+                    ui.label(&trashed.entry.recipie_name);
+                    ui.label(&trashed.entry.guest);
+                    if ui.button("Restore").clicked() {
+                        let result = self.order_store.borrow_mut().restore_from_trash(id);
+                        self.errors
+                            .borrow_mut()
+                            .check("Couldn't restore the order", result);
+                    }
+                    ui.end_row();
+                }
+            });
+        });
+    }
+
+    fn summary(&mut self, ctx: &egui::Context) {
+        let mut open = self.summary_open;
+        let mut new_night = false;
+        Window::new("End of night")
+            .open(&mut open)
+            .default_width(300.0)
+            .show(ctx, |ui| {
+                let summary = self.order_store.borrow().summary();
+                show_summary(ui, &summary);
+                ui.separator();
+                new_night = ui
+                    .button("Start a new night")
+                    .on_hover_text("Clears out tonight's made and cancelled orders")
+                    .clicked();
+            });
+        if new_night {
+            self.order_store.borrow_mut().end_night();
+            open = false;
+        }
+        self.summary_open = open;
+    }
+}
+
+impl Widget for &mut OrderQueueWidget {
+    fn ui(self, ui: &mut egui::Ui) -> egui::Response {
+        let mut response = ui.vertical(|ui| {
+            ui.horizontal(|ui| {
+                ui.heading("Orders");
+                if ui.button("End of night…").clicked() {
+                    self.summary_open = true;
+                }
+            });
+            ui.separator();
+            ScrollArea::vertical()
+                .show(ui, |ui| {
+                    let made = self.queue(ui);
+                    ui.separator();
+                    self.finished(ui);
+                    self.cancelled(ui);
+                    made
+                })
+                .inner
+        });
+        self.summary(ui.ctx());
+        if response.inner {
+            response.response.mark_changed();
+        }
+        response.response
+    }
+}
+
+fn show_summary(ui: &mut egui::Ui, summary: &Summary) {
+    ui.label(format!("{} drinks made", summary.served));
+    if summary.waiting > 0 {
+        ui.label(format!("{} still waiting", summary.waiting));
+    }
+    if let (Some(first), Some(last)) = (summary.first_order, summary.last_served) {
+        ui.weak(format!(
+            "From {} to {}",
+            time_label(first),
+            time_label(last)
+        ));
+    }
+    for (heading, rows) in [
+        ("Drinks", counted(&summary.drinks)),
+        ("Guests", counted(&summary.guests)),
+        (
+            "Poured",
+            summary
+                .poured
+                .iter()
+                .map(|(name, ml)| (name.clone(), format!("{:.0} ml", ml)))
+                .collect(),
+        ),
+    ] {
+        if rows.is_empty() {
+            continue;
+        }
+        ui.separator();
+        ui.strong(heading);
+        Grid::new(ui.id().with(heading)).show(ui, |ui| {
+            for (name, value) in rows {
+                ui.label(name);
+                ui.label(value);
+                ui.end_row();
+            }
+        });
+    }
+}
+
+fn counted(counts: &[(String, usize)]) -> Vec<(String, String)> {
+    counts
+        .iter()
+        .map(|(name, count)| (name.clone(), count.to_string()))
+        .collect()
+}
+
+fn time_label(at: i64) -> String {
+    match chrono::DateTime::from_timestamp(at, 0) {
+        Some(at) => at.with_timezone(&chrono::Local).format("%H:%M").to_string(),
+        None => String::new(),
+    }
+}
+
+fn waited_label(placed_at: i64) -> String {
+    let minutes = (chrono::Utc::now().timestamp() - placed_at).max(0) / 60;
+    match minutes {
+        0 => "just now".to_string(),
+        1 => "1 min ago".to_string(),
+        m => format!("{} mins ago", m),
+    }
+}
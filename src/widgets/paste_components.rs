@@ -0,0 +1,167 @@
+use std::{cell::RefCell, rc::Rc};
+
+use egui::{ComboBox, Grid, RichText, ScrollArea, TextEdit, Window};
+
+use crate::{
+    ingredient_line::{self, Candidate, ParsedLine, Resolution},
+    ingredient_store::IngredientStore,
+    recipie::Component,
+};
+
+// Turns a pasted ingredient list into components, asking about any line that doesn't match
+// exactly one ingredient or tag in the bar
+#[derive(Clone)]
+pub struct PasteComponentsDialog {
+    store: Rc<RefCell<IngredientStore>>,
+    open: bool,
+    text: String,
+    lines: Vec<ParsedLine>,
+    candidates: Vec<Candidate>,
+}
+
+impl PasteComponentsDialog {
+    pub fn new(store: Rc<RefCell<IngredientStore>>) -> PasteComponentsDialog {
+        PasteComponentsDialog {
+            store,
+            open: false,
+            text: String::new(),
+            lines: vec![],
+            candidates: vec![],
+        }
+    }
+
+    pub fn open(&mut self) {
+        self.open = true;
+    }
+
+    // Returns the components once they've all been reviewed
+    pub fn show(&mut self, ctx: &egui::Context) -> Option<Vec<Component>> {
+        let mut components = None;
+        let mut open = self.open;
+        Window::new("Paste components")
+            .open(&mut open)
+            .default_width(560.0)
+            .show(ctx, |ui| {
+                if self.lines.is_empty() {
+                    self.paste(ui);
+                } else {
+                    components = self.review(ui);
+                }
+            });
+        if components.is_some() {
+            open = false;
+            self.text.clear();
+            self.lines.clear();
+        }
+        self.open = open;
+        components
+    }
+
+    fn paste(&mut self, ui: &mut egui::Ui) {
+        ui.label("Paste the recipie's ingredients, one to a line, like \"3/4 oz lime juice\".");
+        ui.add(
+            TextEdit::multiline(&mut self.text)
+                .desired_rows(8)
+                .desired_width(f32::INFINITY),
+        );
+        if ui
+            .add_enabled(
+                !self.text.trim().is_empty(),
+                egui::Button::new("Read lines"),
+            )
+            .clicked()
+        {
+            let store = self.store.borrow();
+            self.candidates = Candidate::all(&store);
+            self.lines = ingredient_line::parse_lines(&self.text, &store);
+        }
+    }
+
+    fn review(&mut self, ui: &mut egui::Ui) -> Option<Vec<Component>> {
+        let unsettled = self.lines.iter().filter(|l| l.needs_review()).count();
+        match unsettled {
+            0 => ui.label("Every line matched up. Change any that look wrong."),
+            1 => ui.label("1 line needs a look."),
+            n => ui.label(format!("{} lines need a look.", n)),
+        };
+        ScrollArea::vertical().max_height(360.0).show(ui, |ui| {
+            Grid::new("pasted_components").striped(true).show(ui, |ui| {
+                for (index, line) in self.lines.iter_mut().enumerate() {
+                    ui.weak(&line.line);
+                    ui.label(line.component.amount.to_string());
+                    choose(ui, index, line, &self.candidates);
+                    let (note, warn) = match &line.resolution {
+                        Resolution::Matched(_) => ("", false),
+                        Resolution::AsWritten => ("Not behind the bar", false),
+                        Resolution::Ambiguous(_) => ("Which one?", true),
+                        Resolution::Unmatched => ("No match", true),
+                    };
+                    if warn {
+                        ui.colored_label(ui.visuals().warn_fg_color, note);
+                    } else {
+                        ui.weak(note);
+                    }
+                    ui.end_row();
+                }
+            });
+        });
+        ui.separator();
+        let mut components = None;
+        ui.horizontal(|ui| {
+            if ui.button("Back").clicked() {
+                self.lines.clear();
+            }
+            let add = egui::Button::new(format!("Add {} components", self.lines.len()));
+            if ui.add_enabled(unsettled == 0, add).clicked() {
+                components = Some(self.lines.iter().map(|l| l.component.clone()).collect());
+            }
+        });
+        components
+    }
+}
+
+// What a line's ingredient is: the likely candidates first, then everything in the bar
+fn choose(ui: &mut egui::Ui, index: usize, line: &mut ParsedLine, candidates: &[Candidate]) {
+    let selected = match &line.resolution {
+        Resolution::Matched(candidate) => candidate.to_string(),
+        Resolution::AsWritten => line.ingredient.clone(),
+        Resolution::Ambiguous(_) | Resolution::Unmatched => "Pick…".to_string(),
+    };
+    let likely = match &line.resolution {
+        Resolution::Ambiguous(likely) => likely.clone(),
+        _ => vec![],
+    };
+    let mut picked = None;
+    ComboBox::from_id_salt(("pasted_component", index))
+        .selected_text(RichText::new(selected))
+        .width(220.0)
+        .show_ui(ui, |ui| {
+            for candidate in &likely {
+                if ui
+                    .selectable_label(false, RichText::new(candidate.to_string()).strong())
+                    .clicked()
+                {
+                    picked = Some(candidate.clone());
+                }
+            }
+            if !likely.is_empty() {
+                ui.separator();
+            }
+            let as_written = line.resolution == Resolution::AsWritten;
+            if ui
+                .selectable_label(as_written, format!("As written: {}", line.ingredient))
+                .clicked()
+            {
+                line.keep_as_written();
+            }
+            for candidate in candidates {
+                let chosen = line.resolution == Resolution::Matched(candidate.clone());
+                if ui.selectable_label(chosen, candidate.to_string()).clicked() {
+                    picked = Some(candidate.clone());
+                }
+            }
+        });
+    if let Some(candidate) = picked {
+        line.resolve(candidate);
+    }
+}
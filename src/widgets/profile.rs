@@ -0,0 +1,46 @@
+use std::{cell::RefCell, path::Path, rc::Rc};
+
+use egui::Button;
+
+use crate::{
+    profile::{create_profile, is_valid_profile_name, list_profiles},
+    widgets::error_log::ErrorLog,
+};
+
+// Profile menu for the menu bar. Returns the profile the user picked, if they picked one.
+pub fn build_menu(
+    ui: &mut egui::Ui,
+    data_dir: &Path,
+    current: &str,
+    new_profile_name: &mut String,
+    errors: &Rc<RefCell<ErrorLog>>,
+) -> Option<String> {
+    let mut chosen = None;
+    ui.menu_button(format!("Profile: {}", current), |ui| {
+        for profile in list_profiles(data_dir) {
+            if ui.selectable_label(profile == current, &profile).clicked() {
+                chosen = Some(profile);
+                ui.close();
+            }
+        }
+        ui.separator();
+        ui.horizontal(|ui| {
+            ui.text_edit_singleline(new_profile_name);
+            let btn = Button::new("New profile");
+            if ui
+                .add_enabled(is_valid_profile_name(new_profile_name), btn)
+                .clicked()
+            {
+                match create_profile(data_dir, new_profile_name) {
+                    Ok(_) => {
+                        chosen = Some(new_profile_name.trim().to_string());
+                        new_profile_name.clear();
+                        ui.close();
+                    }
+                    Err(err) => errors.borrow_mut().report("Couldn't create profile", err),
+                }
+            }
+        });
+    });
+    chosen
+}
@@ -3,15 +3,19 @@ use egui::{CentralPanel, ScrollArea, SidePanel, TopBottomPanel, Widget, style::S
 use std::{cell::RefCell, rc::Rc};
 
 use crate::{
-    ingredient_store::IngredientStore,
+    history::History,
+    ingredient_store::{IngredientSelector, IngredientStore},
+    measure::{AmountStyle, UnitSystem},
     recipie::{self, Component},
     recipie_store::RecipieStore,
-    store::Store, widgets::{create_component::CreateComponentWidget, create_vec::{CreateVecWidget, CreateVecWidgetKernel}, create_vec_kernels::VecWidget},
+    store::Store, widgets::{create_component::CreateComponentWidget, create_vec::{CreateVecWidget, CreateVecWidgetKernel}, create_vec_kernels::VecWidget, error_log::ErrorLog},
 };
 
 pub struct RecipieWidget {
     recipie_store: Rc<RefCell<RecipieStore>>,
     ingredient_store: Rc<RefCell<IngredientStore>>,
+    history: Rc<RefCell<History>>,
+    errors: Rc<RefCell<ErrorLog>>,
     selected_recipie: uuid::Uuid,
     old_selected_recipie: uuid::Uuid,
     component_widgets: Vec<ComponentWidget>,
@@ -19,24 +23,35 @@ pub struct RecipieWidget {
     // Only show recipies we can make with our current stock
     show_in_stock: bool,
     edit_instruction_widget: CreateVecWidget<String, VecWidget>,
-    edit_components_widget: CreateComponentWidget
+    edit_components_widget: CreateComponentWidget,
+    amount_style: AmountStyle,
+    unit_system: UnitSystem,
+    // Convert every component to the user's units, rather than the recipie's
+    in_my_units: bool,
 }
 
 impl RecipieWidget {
     pub fn new(
         recipie_store: Rc<RefCell<RecipieStore>>,
         ingredient_store: Rc<RefCell<IngredientStore>>,
+        history: Rc<RefCell<History>>,
+        errors: Rc<RefCell<ErrorLog>>,
     ) -> RecipieWidget {
         RecipieWidget {
             recipie_store: recipie_store,
             ingredient_store: ingredient_store.clone(),
+            history,
+            errors,
             selected_recipie: uuid::Uuid::nil(),
             old_selected_recipie: uuid::Uuid::nil(),
             component_widgets: vec![],
             editing: false,
             show_in_stock: false,
             edit_instruction_widget: CreateVecWidget::default(),
-            edit_components_widget: CreateComponentWidget::new(ingredient_store.clone())
+            edit_components_widget: CreateComponentWidget::new(ingredient_store.clone()),
+            amount_style: AmountStyle::default(),
+            unit_system: UnitSystem::default(),
+            in_my_units: false,
         }
     }
 
@@ -45,6 +60,25 @@ impl RecipieWidget {
             return;
         }
         self.old_selected_recipie = self.selected_recipie;
+        self.load_components();
+    }
+
+    // Call after the stores were reloaded, so nothing shown is stale
+    pub fn refresh(&mut self) {
+        self.load_components();
+        self.edit_components_widget.refresh();
+    }
+
+    pub fn set_amount_style(&mut self, style: AmountStyle) {
+        self.amount_style = style;
+        self.edit_components_widget.set_amount_style(style);
+    }
+
+    pub fn set_unit_system(&mut self, units: UnitSystem) {
+        self.unit_system = units;
+    }
+
+    fn load_components(&mut self) {
         self.component_widgets.clear();
         if let Some(recipie) = self.recipie_store.borrow().get_entry(self.selected_recipie) {
             self.component_widgets = recipie
@@ -64,31 +98,20 @@ impl Widget for &mut RecipieWidget {
                 .clicked()
             {
                 self.selected_recipie = uuid::Uuid::new_v4();
-                let stock = if self.show_in_stock { Some(true) } else { None };
-                for entry in self.recipie_store.borrow_mut().get_entries_mut() {
-                    entry
-                        .components
-                        .iter_mut()
-                        .for_each(|c| c.ingredient.in_stock = stock);
-                }
             }
             ui.separator();
             egui::ScrollArea::vertical().show(ui, |ui| {
                 ui.vertical(|ui| {
-                    let mut recipies = self.recipie_store.borrow().get_recipie_entries();
-                    recipies.sort_by_key(|r| r.1.name.clone().to_ascii_lowercase());
-                    if self.show_in_stock {
-                        for (id, recipie) in recipies {
-                            if recipie.can_make(self.ingredient_store.clone()) {
-                                ui.selectable_value(&mut self.selected_recipie, id, recipie.name)
-                                    .on_hover_text(recipie.short_description);
-                            }
-                        }
+                    let recipies = if self.show_in_stock {
+                        self.recipie_store.borrow().makeable(&self.ingredient_store.borrow())
                     } else {
-                        for (id, recipie) in recipies {
-                            ui.selectable_value(&mut self.selected_recipie, id, recipie.name)
-                                .on_hover_text(recipie.short_description);
-                        }
+                        let mut recipies = self.recipie_store.borrow().get_recipie_entries();
+                        recipies.sort_by_key(|r| r.1.name.clone().to_ascii_lowercase());
+                        recipies
+                    };
+                    for (id, recipie) in recipies {
+                        ui.selectable_value(&mut self.selected_recipie, id, recipie.name)
+                            .on_hover_text(recipie.short_description);
                     }
                 })
             });
@@ -98,21 +121,23 @@ impl Widget for &mut RecipieWidget {
             TopBottomPanel::bottom(("recipie_bottom_panel")).show_inside(ui, |ui| {
                 ui.horizontal(|ui| {
                     if ui.button("Delete entry").clicked() {
-                        self.recipie_store.borrow_mut().deregister(self.selected_recipie);
+                        let result = self.history.borrow_mut().move_to_trash(&mut *self.recipie_store.borrow_mut(), self.selected_recipie);
+                        self.errors.borrow_mut().check("Couldn't delete recipie", result);
                         self.selected_recipie = uuid::Uuid::nil();
                     }
                     if ui.toggle_value(&mut self.editing, "Edit recipie").clicked() {
-                        if self.editing && let Some(recipie) = self.recipie_store.borrow_mut().get_entry_mut(self.selected_recipie) {
+                        if self.editing && let Some(recipie) = self.recipie_store.borrow().get_entry(self.selected_recipie) {
                             self.edit_instruction_widget = CreateVecWidget::from(VecWidget::default(), recipie.instructions.clone());
                             self.edit_components_widget.set_components(recipie.components.clone());
-                        } else if let Some(recipie) = self.recipie_store.borrow_mut().get_entry_mut(self.selected_recipie) {
-                            recipie.instructions = self.edit_instruction_widget.get_entries();
-                            recipie.components = self.edit_components_widget.get_components();
-                            self.component_widgets = recipie
-                                .components
-                                .iter()
-                                .map(|c| return ComponentWidget::new(c.clone(), self.ingredient_store.clone()))
-                                .collect();
+                        } else {
+                            let selected = self.recipie_store.borrow().get_entry(self.selected_recipie);
+                            if let Some(mut recipie) = selected {
+                                recipie.instructions = self.edit_instruction_widget.get_entries();
+                                recipie.components = self.edit_components_widget.get_components();
+                                let result = self.history.borrow_mut().update(&mut *self.recipie_store.borrow_mut(), self.selected_recipie, recipie);
+                                self.errors.borrow_mut().check("Couldn't edit recipie", result);
+                                self.load_components();
+                            }
                         }
                     }
                 })
@@ -120,15 +145,17 @@ impl Widget for &mut RecipieWidget {
         }
         CentralPanel::default()
             .show_inside(ui, |ui| {
-                if let Some(recipie) = self.recipie_store.borrow_mut().get_entry_mut(self.selected_recipie)
-                {
+                let selected = self.recipie_store.borrow().get_entry(self.selected_recipie);
+                if let Some(mut recipie) = selected {
+                    // Only written back when edited, so merely looking doesn't mark the store dirty
+                    let mut changed = false;
                     ScrollArea::vertical().show(ui, |ui| {
                         ui.vertical(|ui| {
                             if self.editing {
                                 ui.label("Name:");
-                                ui.text_edit_singleline(&mut recipie.name);
+                                changed |= ui.text_edit_singleline(&mut recipie.name).changed();
                                 ui.label("Short description:");
-                                ui.text_edit_singleline(&mut recipie.short_description);
+                                changed |= ui.text_edit_singleline(&mut recipie.short_description).changed();
                                 ui.separator();
                                 ui.horizontal(|ui| {
                                     ui.vertical(|ui| {
@@ -141,12 +168,15 @@ impl Widget for &mut RecipieWidget {
                                 });
                                 ui.separator();
                                 ui.label("Description:");
-                                ui.text_edit_multiline(&mut recipie.description);
+                                changed |= ui.text_edit_multiline(&mut recipie.description).changed();
                                 ui.separator();
                                 ui.label("Notes:");
-                                ui.text_edit_multiline(&mut recipie.notes);
+                                changed |= ui.text_edit_multiline(&mut recipie.notes).changed();
                             } else {
-                                ui.heading(&recipie.name);
+                                ui.horizontal(|ui| {
+                                    ui.heading(&recipie.name);
+                                    ui.checkbox(&mut self.in_my_units, "Show in my units");
+                                });
                                 ui.separator();
                                 ui.horizontal(|ui| {
                                     ui.vertical(|ui| {
@@ -157,6 +187,9 @@ impl Widget for &mut RecipieWidget {
                                     ui.separator();
                                     ui.vertical(|ui| {
                                         for widget in &mut self.component_widgets {
+                                            widget.amount_style = self.amount_style;
+                                            widget.in_stock_only = self.show_in_stock;
+                                            widget.units = self.in_my_units.then_some(self.unit_system);
                                             ui.add(widget);
                                         }
                                     });
@@ -173,7 +206,15 @@ impl Widget for &mut RecipieWidget {
                                 }
                             }
                         });
-                    }).inner
+                    });
+                    if changed {
+                        let result = self.history.borrow_mut().update(
+                            &mut *self.recipie_store.borrow_mut(),
+                            self.selected_recipie,
+                            recipie,
+                        );
+                        self.errors.borrow_mut().check("Couldn't edit recipie", result);
+                    }
                 }
             })
             .response
@@ -185,6 +226,11 @@ pub struct ComponentWidget {
     ingredient_store: Rc<RefCell<IngredientStore>>,
     id: uuid::Uuid,
     selected: String,
+    amount_style: AmountStyle,
+    // Units to convert the amount to, if any
+    units: Option<UnitSystem>,
+    // Only offer bottles we have, without touching the stored recipie
+    in_stock_only: bool,
 }
 
 impl ComponentWidget {
@@ -199,27 +245,36 @@ impl ComponentWidget {
             ingredient_store: store,
             id: uuid::Uuid::new_v4(),
             selected: selected,
+            amount_style: AmountStyle::default(),
+            units: None,
+            in_stock_only: false,
         }
     }
+
+    fn selector(&self) -> IngredientSelector {
+        let mut selector = self.component.ingredient.clone();
+        if self.in_stock_only {
+            selector.in_stock = Some(true);
+        }
+        selector
+    }
 }
 
 impl Widget for &mut ComponentWidget {
     fn ui(self, ui: &mut egui::Ui) -> egui::Response {
         ui.horizontal(|ui| {
-            let ingredients = self
-                .ingredient_store
-                .borrow()
-                .select(&self.component.ingredient);
+            let selector = self.selector();
+            let ingredients = self.ingredient_store.borrow().select(&selector);
+            if !ingredients.iter().any(|i| i.name == self.selected) {
+                self.selected = ingredients.first().map(|i| i.name.clone()).unwrap_or_default();
+            }
             if ingredients.len() == 1 {
                 ui.label(ingredients[0].name.clone());
             } else {
                 egui::containers::ComboBox::from_id_salt(self.id)
                     .selected_text(self.selected.clone())
                     .show_ui(ui, |ui| {
-                        let mut ingredients = self
-                            .ingredient_store
-                            .borrow()
-                            .select(&self.component.ingredient);
+                        let mut ingredients = self.ingredient_store.borrow().select(&selector);
                         ingredients.sort_by_key(|i| i.name.clone().to_ascii_lowercase());
                         for ingredient in ingredients {
                             ui.selectable_value(
@@ -230,7 +285,11 @@ impl Widget for &mut ComponentWidget {
                         }
                     });
             }
-            ui.label(format!(" {}", self.component.amount));
+            let amount = match self.units {
+                Some(units) => self.component.amount.in_units(units),
+                None => self.component.amount.clone(),
+            };
+            ui.label(format!(" {}", amount.display(self.amount_style)));
         })
         .response
     }
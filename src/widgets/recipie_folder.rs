@@ -0,0 +1,81 @@
+use std::path::{Path, PathBuf};
+
+use egui::{TextEdit, Window};
+
+// What the user asked for in the recipie folder window
+pub enum FolderAction {
+    // Start syncing with a folder, or stop with None
+    Set(Option<PathBuf>),
+    SyncNow,
+}
+
+// Picks the folder of Markdown recipies a profile is kept in sync with
+#[derive(Default)]
+pub struct RecipieFolderDialog {
+    open: bool,
+    path: String,
+    // How the last sync went
+    last_sync: Option<String>,
+}
+
+impl RecipieFolderDialog {
+    pub fn open(&mut self, folder: Option<&Path>) {
+        self.open = true;
+        self.path = folder.map(|f| f.display().to_string()).unwrap_or_default();
+    }
+
+    pub fn synced(&mut self, summary: String) {
+        self.last_sync = Some(summary);
+    }
+
+    pub fn show(&mut self, ctx: &egui::Context, folder: Option<&Path>) -> Option<FolderAction> {
+        let mut action = None;
+        let mut open = self.open;
+        Window::new("Recipie folder")
+            .open(&mut open)
+            .default_width(420.0)
+            .show(ctx, |ui| {
+                ui.label(
+                    "Keep this bar's recipies as Markdown notes in a folder. Changes made on \
+                     either side are copied to the other when the app opens and whenever it \
+                     saves.",
+                );
+                ui.horizontal(|ui| {
+                    ui.label("Folder");
+                    ui.add(TextEdit::singleline(&mut self.path).desired_width(300.0));
+                });
+                let path = self.path.trim();
+                ui.horizontal(|ui| {
+                    let changed = !path.is_empty() && folder != Some(Path::new(path));
+                    if ui
+                        .add_enabled(changed, egui::Button::new("Sync with this folder"))
+                        .clicked()
+                    {
+                        action = Some(FolderAction::Set(Some(PathBuf::from(path))));
+                    }
+                    if folder.is_some() {
+                        if ui.button("Sync now").clicked() {
+                            action = Some(FolderAction::SyncNow);
+                        }
+                        if ui.button("Stop syncing").clicked() {
+                            action = Some(FolderAction::Set(None));
+                            self.last_sync = None;
+                        }
+                    }
+                });
+                match (folder, &self.last_sync) {
+                    (Some(folder), Some(summary)) => {
+                        ui.weak(format!("Synced with {}: {}", folder.display(), summary));
+                    }
+                    (Some(folder), None) => {
+                        ui.weak(format!("Syncing with {}", folder.display()));
+                    }
+                    (None, _) => {
+                        ui.weak("Not syncing");
+                    }
+                }
+            });
+        self.open = open;
+        action
+    }
+}
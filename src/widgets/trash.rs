@@ -0,0 +1,132 @@
+use std::{cell::RefCell, rc::Rc};
+
+use egui::{CentralPanel, Grid, ScrollArea, SidePanel, Widget};
+
+use crate::{
+    history::{History, Recorded},
+    ingredient_store::IngredientStore,
+    recipie_store::RecipieStore,
+    store::Store,
+    widgets::error_log::ErrorLog,
+};
+
+// How long trashed entries are kept, as offered in the preferences menu
+const RETENTION_CHOICES: [Option<u32>; 5] = [Some(1), Some(7), Some(30), Some(90), None];
+
+// Browses deleted recipies and ingredients, to restore or purge them
+pub struct TrashWidget {
+    recipie_store: Rc<RefCell<RecipieStore>>,
+    ingredient_store: Rc<RefCell<IngredientStore>>,
+    history: Rc<RefCell<History>>,
+    errors: Rc<RefCell<ErrorLog>>,
+}
+
+impl TrashWidget {
+    pub fn new(
+        recipie_store: Rc<RefCell<RecipieStore>>,
+        ingredient_store: Rc<RefCell<IngredientStore>>,
+        history: Rc<RefCell<History>>,
+        errors: Rc<RefCell<ErrorLog>>,
+    ) -> TrashWidget {
+        TrashWidget {
+            recipie_store,
+            ingredient_store,
+            history,
+            errors,
+        }
+    }
+}
+
+impl Widget for &mut TrashWidget {
+    fn ui(self, ui: &mut egui::Ui) -> egui::Response {
+        SidePanel::left("trash_recipies").show_inside(ui, |ui| {
+            ui.label("Recipies");
+            trash_list(ui, self, &self.recipie_store, |r| &r.name)
+        });
+        CentralPanel::default()
+            .show_inside(ui, |ui| {
+                ui.label("Ingredients");
+                trash_list(ui, self, &self.ingredient_store, |i| &i.name)
+            })
+            .response
+    }
+}
+
+fn trash_list<T: Recorded, S: Store<T>>(
+    ui: &mut egui::Ui,
+    widget: &TrashWidget,
+    store: &Rc<RefCell<S>>,
+    name: fn(&T) -> &String,
+) {
+    let entries = store.borrow().trash_bin().entries();
+    if entries.is_empty() {
+        ui.label("Nothing in the trash");
+        return;
+    }
+    if ui.button("Empty trash").clicked() {
+        let mut store = store.borrow_mut();
+        for (id, _) in &entries {
+            let result = store.purge(*id);
+            widget
+                .errors
+                .borrow_mut()
+                .check("Couldn't empty trash", result);
+        }
+        return;
+    }
+    ui.separator();
+    ScrollArea::vertical().show(ui, |ui| {
+        Grid::new(ui.id().with("trash_list"))
+            .striped(true)
+            .show(ui, |ui| {
+                for (id, trashed) in entries {
+                    ui.label(name(&trashed.entry));
+                    ui.label(deleted_label(trashed.deleted_at));
+                    if ui.button("Restore").clicked() {
+                        let result = widget
+                            .history
+                            .borrow_mut()
+                            .restore_from_trash(&mut *store.borrow_mut(), id);
+                        widget.errors.borrow_mut().check("Couldn't restore", result);
+                    }
+                    if ui.button("Delete forever").clicked() {
+                        let result = store.borrow_mut().purge(id);
+                        widget
+                            .errors
+                            .borrow_mut()
+                            .check("Couldn't delete forever", result);
+                    }
+                    ui.end_row();
+                }
+            });
+    });
+}
+
+fn deleted_label(deleted_at: i64) -> String {
+    match chrono::DateTime::from_timestamp(deleted_at, 0) {
+        Some(at) => format!(
+            "Deleted {}",
+            at.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M")
+        ),
+        None => String::new(),
+    }
+}
+
+// Preferences submenu for how long the trash keeps things. Returns true if it changed.
+pub fn build_menu(ui: &mut egui::Ui, retention_days: &mut Option<u32>) -> bool {
+    let mut changed = false;
+    ui.menu_button("Empty trash after", |ui| {
+        for choice in RETENTION_CHOICES {
+            let label = match choice {
+                Some(1) => "1 day".to_string(),
+                Some(days) => format!("{} days", days),
+                None => "Never".to_string(),
+            };
+            if ui.selectable_value(retention_days, choice, label).clicked() {
+                changed = true;
+                ui.close();
+            }
+        }
+    });
+    changed
+}
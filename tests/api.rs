@@ -0,0 +1,181 @@
+use std::{
+    io::{Read, Write},
+    net::{SocketAddr, TcpStream},
+    path::PathBuf,
+};
+
+use drink_solver::{
+    backend::Backend,
+    ingredient_store::IngredientStore,
+    measure::Measure,
+    recipie::Recipie,
+    server::{Api, Server},
+    store::Store,
+};
+// test_dir.rs is shared with the library's tests, and finds these through crate::
+use drink_solver::{ingredient_store, measure, recipie};
+use serde_json::{Value, json};
+
+#[path = "../src/test_dir.rs"]
+mod test_dir;
+use test_dir::{TestDir, component};
+
+// Starts a server on a free port, which keeps running until the tests exit
+fn spawn_server(dir: PathBuf) -> SocketAddr {
+    let api = Api::open(Backend::Json, dir).unwrap();
+    let server = Server::bind(api, "127.0.0.1:0").unwrap();
+    let address = server.address().unwrap();
+    std::thread::spawn(move || server.run(|err| eprintln!("{}", err)));
+    address
+}
+
+// Bare-bones HTTP/1.1, enough to talk to our own server
+fn request(address: SocketAddr, method: &str, path: &str, body: Option<Value>) -> (u16, Value) {
+    let body = body.map(|b| b.to_string()).unwrap_or_default();
+    let mut stream = TcpStream::connect(address).unwrap();
+    write!(
+        stream,
+        "{} {} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\nContent-Length: {}\r\n\r\n{}",
+        method,
+        path,
+        body.len(),
+        body
+    )
+    .unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    let (head, body) = response.split_once("\r\n\r\n").unwrap();
+    let status = head.split_whitespace().nth(1).unwrap().parse().unwrap();
+    let body = serde_json::from_str(body).unwrap_or(Value::Null);
+    (status, body)
+}
+
+fn ingredient(name: &str, stock: u16) -> Value {
+    json!({ "name": name, "quality": "Any", "tags": [], "stock": stock, "is_liquor": false })
+}
+
+#[test]
+fn test_ingredient_crud() {
+    let dir = TestDir::new();
+    let address = spawn_server(dir.to_path_buf());
+
+    let (status, gin) = request(address, "POST", "/ingredients", Some(ingredient("Gin", 1)));
+    assert_eq!(status, 201);
+    let id = gin["id"].as_str().unwrap().to_string();
+    let path = format!("/ingredients/{}", id);
+
+    let (status, fetched) = request(address, "GET", &path, None);
+    assert_eq!(status, 200);
+    assert_eq!(fetched["name"], "Gin");
+
+    let (status, updated) = request(address, "PUT", &path, Some(ingredient("Gin", 4)));
+    assert_eq!(status, 200);
+    assert_eq!(updated["stock"], 4);
+    // Every change is saved straight away
+    let saved = IngredientStore::from_config_read_only(dir.to_path_buf()).unwrap();
+    assert_eq!(saved.get_entries()[0].stock, 4);
+
+    let (status, listed) = request(address, "GET", "/ingredients", None);
+    assert_eq!(status, 200);
+    assert_eq!(listed.as_array().unwrap().len(), 1);
+
+    assert_eq!(request(address, "DELETE", &path, None).0, 204);
+    assert_eq!(request(address, "GET", &path, None).0, 404);
+    assert_eq!(request(address, "DELETE", &path, None).0, 404);
+    let missing = format!("/ingredients/{}", uuid::Uuid::new_v4());
+    let (status, _) = request(address, "PUT", &missing, Some(ingredient("Rum", 1)));
+    assert_eq!(status, 404);
+    let (status, error) = request(address, "POST", "/ingredients", Some(json!({ "nope": 1 })));
+    assert_eq!(status, 400);
+    assert!(error["error"].is_string());
+}
+
+#[test]
+fn test_select_and_makeable() {
+    let dir = TestDir::new();
+    let address = spawn_server(dir.to_path_buf());
+    request(address, "POST", "/ingredients", Some(ingredient("Gin", 1)));
+    let (_, vermouth) = request(
+        address,
+        "POST",
+        "/ingredients",
+        Some(ingredient("Vermouth", 0)),
+    );
+    let martini = Recipie {
+        name: "Martini".into(),
+        components: vec![
+            component("Gin", Measure::Oz(2.0)),
+            component("Vermouth", Measure::Oz(1.0)),
+        ],
+        ..Default::default()
+    };
+    let martini = serde_json::to_value(martini).unwrap();
+    assert_eq!(request(address, "POST", "/recipes", Some(martini)).0, 201);
+
+    let (status, selected) = request(
+        address,
+        "POST",
+        "/ingredients/select",
+        Some(json!({ "in_stock": true })),
+    );
+    assert_eq!(status, 200);
+    assert_eq!(selected.as_array().unwrap().len(), 1);
+    assert_eq!(selected[0]["name"], "Gin");
+
+    let (_, makeable) = request(address, "GET", "/recipes/makeable", None);
+    assert_eq!(makeable, json!([]));
+    let path = format!("/ingredients/{}", vermouth["id"].as_str().unwrap());
+    request(address, "PUT", &path, Some(ingredient("Vermouth", 1)));
+    let (_, makeable) = request(address, "GET", "/recipes/makeable", None);
+    assert_eq!(makeable[0]["name"], "Martini");
+}
+
+#[test]
+fn test_outside_edits_between_writes() {
+    let dir = TestDir::new();
+    let address = spawn_server(dir.to_path_buf());
+    let path = dir.join("ingredient.json");
+    // Another program writing the store file behind the server's back
+    let write_outside = |names: &[&str]| {
+        let mut theirs = IngredientStore::default();
+        for name in names {
+            theirs.register(serde_json::from_value(ingredient(name, 1)).unwrap());
+        }
+        theirs.save_to(&path).unwrap();
+    };
+    let names = || {
+        let (_, listed) = request(address, "GET", "/ingredients", None);
+        let mut names: Vec<String> = listed
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|i| i["name"].as_str().unwrap().to_string())
+            .collect();
+        names.sort();
+        names
+    };
+
+    assert_eq!(
+        request(address, "POST", "/ingredients", Some(ingredient("Gin", 1))).0,
+        201
+    );
+    write_outside(&["Gin", "Vermouth"]);
+    assert_eq!(
+        request(address, "POST", "/ingredients", Some(ingredient("Rum", 1))).0,
+        201
+    );
+    assert_eq!(names(), ["Gin", "Rum", "Vermouth"]);
+
+    // A half-written file turns writes away without the server getting stuck on them
+    std::fs::write(&path, "{").unwrap();
+    let (status, _) = request(address, "POST", "/ingredients", Some(ingredient("Lime", 1)));
+    assert!(status >= 400);
+    write_outside(&["Vermouth"]);
+    assert_eq!(
+        request(address, "POST", "/ingredients", Some(ingredient("Lime", 1))).0,
+        201
+    );
+    assert_eq!(names(), ["Lime", "Vermouth"]);
+    let saved = IngredientStore::from_config_read_only(dir.to_path_buf()).unwrap();
+    assert_eq!(saved.get_entries().len(), 2);
+}